@@ -0,0 +1,739 @@
+//! Animated image encoding
+//!
+//! `GifWriter` streams frames into an optimized animated GIF: each frame is
+//! quantized to its own palette, identical consecutive frames are merged
+//! into one longer frame, and later frames only store the rectangle that
+//! changed, with unchanged pixels left transparent.
+//!
+//! Frame sequences can also be written as APNG or animated WebP, which keep
+//! full color and alpha. Frames come from memory or disk and are loaded one
+//! at a time while encoding, so long sequences don't need to fit in memory.
+
+use gif::{DisposalMethod, Encoder, Repeat};
+use image::codecs::webp::WebPEncoder;
+use image::{ExtendedColorType, RgbaImage};
+use serde::Deserialize;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+
+use crate::utils::{ensure_dir, generate_filename, AppResult};
+
+/// GIF delays are in hundredths of a second and most viewers treat anything
+/// below 2 as "as fast as possible", so this is the shortest reliable delay
+const MIN_GIF_DELAY_CS: u32 = 2;
+
+/// Map a 1-100 quality to the NeuQuant sampling speed (1 is best, 30 fastest)
+fn quantizer_speed(quality: u8) -> i32 {
+    let quality = quality.clamp(1, 100) as i32;
+    30 - (quality - 1) * 29 / 99
+}
+
+/// Bounding box of the pixels that differ between two same-sized frames
+fn changed_bounds(previous: &RgbaImage, frame: &RgbaImage) -> Option<(u32, u32, u32, u32)> {
+    let (mut min_x, mut min_y) = (u32::MAX, u32::MAX);
+    let (mut max_x, mut max_y) = (0, 0);
+
+    for (x, y, pixel) in frame.enumerate_pixels() {
+        if previous.get_pixel(x, y) != pixel {
+            min_x = min_x.min(x);
+            min_y = min_y.min(y);
+            max_x = max_x.max(x);
+            max_y = max_y.max(y);
+        }
+    }
+
+    (min_x != u32::MAX).then(|| (min_x, min_y, max_x - min_x + 1, max_y - min_y + 1))
+}
+
+/// Streaming animated GIF encoder
+pub struct GifWriter<W: Write> {
+    encoder: Encoder<W>,
+    width: u32,
+    height: u32,
+    speed: i32,
+    /// Last frame written, which delta frames are computed against
+    previous: Option<RgbaImage>,
+    /// Frame waiting to be written until we know it isn't repeated
+    pending: Option<(RgbaImage, u32)>,
+    frames: u32,
+}
+
+impl<W: Write> GifWriter<W> {
+    /// Start a looping GIF of the given size; `quality` ranges from 1 to 100
+    pub fn new(writer: W, width: u32, height: u32, quality: u8) -> AppResult<Self> {
+        if width == 0 || height == 0 || width > u16::MAX as u32 || height > u16::MAX as u32 {
+            return Err(format!("Invalid GIF size: {}x{}", width, height));
+        }
+
+        let mut encoder = Encoder::new(writer, width as u16, height as u16, &[])
+            .map_err(|e| format!("Failed to start GIF: {}", e))?;
+        encoder
+            .set_repeat(Repeat::Infinite)
+            .map_err(|e| format!("Failed to start GIF: {}", e))?;
+
+        Ok(Self {
+            encoder,
+            width,
+            height,
+            speed: quantizer_speed(quality),
+            previous: None,
+            pending: None,
+            frames: 0,
+        })
+    }
+
+    /// Add a frame that stays on screen for `delay_ms` milliseconds
+    pub fn push(&mut self, frame: RgbaImage, delay_ms: u32) -> AppResult<()> {
+        if frame.dimensions() != (self.width, self.height) {
+            return Err(format!(
+                "Frame is {}x{} but the GIF is {}x{}",
+                frame.width(),
+                frame.height(),
+                self.width,
+                self.height
+            ));
+        }
+
+        match self.pending.take() {
+            Some((pending, pending_delay)) if pending == frame => {
+                self.pending = Some((pending, pending_delay.saturating_add(delay_ms)));
+            }
+            Some((pending, pending_delay)) => {
+                self.write(pending, pending_delay)?;
+                self.pending = Some((frame, delay_ms));
+            }
+            None => self.pending = Some((frame, delay_ms)),
+        }
+
+        Ok(())
+    }
+
+    /// Number of frames written so far, after merging duplicates
+    pub fn frames(&self) -> u32 {
+        self.frames
+    }
+
+    /// Write any pending frame and finish the file
+    pub fn finish(mut self) -> AppResult<W> {
+        if let Some((pending, delay)) = self.pending.take() {
+            self.write(pending, delay)?;
+        }
+
+        self.encoder
+            .into_inner()
+            .map_err(|e| format!("Failed to finish GIF: {}", e))
+    }
+
+    fn write(&mut self, mut frame: RgbaImage, delay_ms: u32) -> AppResult<()> {
+        // Captures are opaque; transparency is reserved for unchanged pixels
+        for pixel in frame.pixels_mut() {
+            pixel[3] = 255;
+        }
+
+        let (left, top, mut pixels, width, height) = match &self.previous {
+            Some(previous) => {
+                let (x, y, width, height) =
+                    changed_bounds(previous, &frame).unwrap_or((0, 0, 1, 1));
+                let mut region = image::imageops::crop_imm(&frame, x, y, width, height).to_image();
+                for (rx, ry, pixel) in region.enumerate_pixels_mut() {
+                    if previous.get_pixel(x + rx, y + ry) == pixel {
+                        pixel[3] = 0;
+                    }
+                }
+                (x, y, region.into_raw(), width, height)
+            }
+            None => (0, 0, frame.as_raw().clone(), self.width, self.height),
+        };
+
+        let mut gif_frame =
+            gif::Frame::from_rgba_speed(width as u16, height as u16, &mut pixels, self.speed);
+        gif_frame.left = left as u16;
+        gif_frame.top = top as u16;
+        gif_frame.dispose = DisposalMethod::Keep;
+        gif_frame.delay = ((delay_ms + 5) / 10).clamp(MIN_GIF_DELAY_CS, u16::MAX as u32) as u16;
+
+        self.encoder
+            .write_frame(&gif_frame)
+            .map_err(|e| format!("Failed to write GIF frame: {}", e))?;
+
+        self.previous = Some(frame);
+        self.frames += 1;
+        Ok(())
+    }
+}
+
+/// Palette quality used when a frame sequence is exported as a GIF
+const SEQUENCE_GIF_QUALITY: u8 = 90;
+
+/// Largest canvas an animated WebP can describe
+const MAX_WEBP_DIMENSION: u32 = 1 << 14;
+
+/// ANMF flag telling the decoder to replace the canvas instead of blending
+const WEBP_NO_BLEND: u8 = 0b10;
+
+/// Output format for a frame sequence
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum AnimationFormat {
+    #[default]
+    Gif,
+    Apng,
+    Webp,
+}
+
+impl AnimationFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            AnimationFormat::Gif => "gif",
+            AnimationFormat::Apng => "png",
+            AnimationFormat::Webp => "webp",
+        }
+    }
+}
+
+/// Where a frame's pixels come from
+#[derive(Clone, Debug)]
+pub enum FrameSource {
+    /// Frame already in memory, such as a fresh capture
+    Image(RgbaImage),
+    /// Image file loaded when the frame is encoded
+    File(PathBuf),
+}
+
+impl FrameSource {
+    fn load(&self) -> AppResult<Cow<'_, RgbaImage>> {
+        match self {
+            FrameSource::Image(image) => Ok(Cow::Borrowed(image)),
+            FrameSource::File(path) => image::open(path)
+                .map(|img| Cow::Owned(img.to_rgba8()))
+                .map_err(|e| format!("Failed to open frame {}: {}", path.display(), e)),
+        }
+    }
+}
+
+/// A frame of a sequence and how long it stays on screen
+#[derive(Clone, Debug)]
+pub struct SequenceFrame {
+    pub source: FrameSource,
+    pub delay_ms: u32,
+}
+
+/// Build a sequence from image files.
+///
+/// `delays_ms` holds either one delay per frame or a single delay used for
+/// every frame.
+pub fn frames_from_paths(paths: &[String], delays_ms: &[u32]) -> AppResult<Vec<SequenceFrame>> {
+    if delays_ms.len() != 1 && delays_ms.len() != paths.len() {
+        return Err(format!(
+            "Expected 1 or {} frame delays, got {}",
+            paths.len(),
+            delays_ms.len()
+        ));
+    }
+
+    Ok(paths
+        .iter()
+        .enumerate()
+        .map(|(i, path)| SequenceFrame {
+            source: FrameSource::File(PathBuf::from(path)),
+            delay_ms: delays_ms[i.min(delays_ms.len() - 1)],
+        })
+        .collect())
+}
+
+/// A loaded frame image and its delay in milliseconds
+type LoadedFrame<'a> = AppResult<(Cow<'a, RgbaImage>, u32)>;
+
+/// Load every frame in order, checking they all share the first frame's size
+fn load_frames(
+    frames: &[SequenceFrame],
+) -> AppResult<(u32, u32, impl Iterator<Item = LoadedFrame<'_>>)> {
+    let (first, rest) = frames
+        .split_first()
+        .ok_or("An animation needs at least one frame")?;
+    let first_image = first.source.load()?;
+    let (width, height) = first_image.dimensions();
+
+    let rest = rest.iter().map(move |frame| {
+        let image = frame.source.load()?;
+        if image.dimensions() != (width, height) {
+            return Err(format!(
+                "Frame is {}x{} but the animation is {}x{}",
+                image.width(),
+                image.height(),
+                width,
+                height
+            ));
+        }
+        Ok((image, frame.delay_ms))
+    });
+
+    Ok((
+        width,
+        height,
+        std::iter::once(Ok((first_image, first.delay_ms))).chain(rest),
+    ))
+}
+
+/// APNG delays are a fraction of a second; use milliseconds when they fit
+fn apng_delay(delay_ms: u32) -> (u16, u16) {
+    match u16::try_from(delay_ms) {
+        Ok(ms) => (ms, 1000),
+        Err(_) => ((delay_ms / 10).min(u16::MAX as u32) as u16, 100),
+    }
+}
+
+/// Write a looping APNG
+pub fn write_apng<W: Write>(frames: &[SequenceFrame], writer: W) -> AppResult<()> {
+    let (width, height, images) = load_frames(frames)?;
+
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .set_animated(frames.len() as u32, 0)
+        .map_err(|e| format!("Failed to start APNG: {}", e))?;
+
+    let mut writer = encoder
+        .write_header()
+        .map_err(|e| format!("Failed to start APNG: {}", e))?;
+
+    for frame in images {
+        let (image, delay_ms) = frame?;
+        let (num, den) = apng_delay(delay_ms);
+        writer
+            .set_frame_delay(num, den)
+            .and_then(|_| writer.write_image_data(image.as_raw()))
+            .map_err(|e| format!("Failed to write APNG frame: {}", e))?;
+    }
+
+    writer
+        .finish()
+        .map_err(|e| format!("Failed to finish APNG: {}", e))
+}
+
+fn push_u24(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes()[..3]);
+}
+
+/// Append a RIFF chunk, padded to an even length
+fn push_chunk(out: &mut Vec<u8>, id: &[u8; 4], data: &[u8]) {
+    out.extend_from_slice(id);
+    out.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    out.extend_from_slice(data);
+    if data.len() % 2 == 1 {
+        out.push(0);
+    }
+}
+
+/// Encode a frame as lossless WebP and return its `VP8L` chunk
+fn webp_lossless_chunk(image: &RgbaImage) -> AppResult<Vec<u8>> {
+    let mut riff = Vec::new();
+    WebPEncoder::new_lossless(&mut riff)
+        .encode(
+            image.as_raw(),
+            image.width(),
+            image.height(),
+            ExtendedColorType::Rgba8,
+        )
+        .map_err(|e| format!("Failed to encode WebP frame: {}", e))?;
+
+    // Skip the RIFF header and walk the chunks to find the bitstream
+    let mut offset = 12;
+    while offset + 8 <= riff.len() {
+        let size = u32::from_le_bytes([
+            riff[offset + 4],
+            riff[offset + 5],
+            riff[offset + 6],
+            riff[offset + 7],
+        ]) as usize;
+        let end = (offset + 8 + size + size % 2).min(riff.len());
+        if &riff[offset..offset + 4] == b"VP8L" {
+            return Ok(riff[offset..end].to_vec());
+        }
+        offset = end;
+    }
+
+    Err("Failed to encode WebP frame: no lossless bitstream".to_string())
+}
+
+/// Write a looping, lossless animated WebP
+pub fn write_animated_webp<W: Write>(frames: &[SequenceFrame], mut writer: W) -> AppResult<()> {
+    let (width, height, images) = load_frames(frames)?;
+    if width > MAX_WEBP_DIMENSION || height > MAX_WEBP_DIMENSION {
+        return Err(format!(
+            "WebP frames can be at most {}x{} pixels",
+            MAX_WEBP_DIMENSION, MAX_WEBP_DIMENSION
+        ));
+    }
+
+    // The RIFF header needs the total size, so frames are collected in
+    // compressed form before anything is written
+    let mut body = Vec::new();
+    let mut has_alpha = false;
+    for frame in images {
+        let (image, delay_ms) = frame?;
+        has_alpha |= image.pixels().any(|p| p[3] != 255);
+
+        let mut anmf = Vec::new();
+        push_u24(&mut anmf, 0);
+        push_u24(&mut anmf, 0);
+        push_u24(&mut anmf, width - 1);
+        push_u24(&mut anmf, height - 1);
+        push_u24(&mut anmf, delay_ms.min(0xFF_FFFF));
+        anmf.push(WEBP_NO_BLEND);
+        anmf.extend_from_slice(&webp_lossless_chunk(&image)?);
+        push_chunk(&mut body, b"ANMF", &anmf);
+    }
+
+    let mut vp8x = vec![0x02 | if has_alpha { 0x10 } else { 0 }, 0, 0, 0];
+    push_u24(&mut vp8x, width - 1);
+    push_u24(&mut vp8x, height - 1);
+
+    // Transparent background, loop forever
+    let anim = [0, 0, 0, 0, 0, 0];
+
+    let mut header = Vec::new();
+    push_chunk(&mut header, b"VP8X", &vp8x);
+    push_chunk(&mut header, b"ANIM", &anim);
+
+    let riff_size = 4 + header.len() + body.len();
+    let riff_size =
+        u32::try_from(riff_size).map_err(|_| "Animation is too large for WebP".to_string())?;
+
+    writer
+        .write_all(b"RIFF")
+        .and_then(|_| writer.write_all(&riff_size.to_le_bytes()))
+        .and_then(|_| writer.write_all(b"WEBP"))
+        .and_then(|_| writer.write_all(&header))
+        .and_then(|_| writer.write_all(&body))
+        .and_then(|_| writer.flush())
+        .map_err(|e| format!("Failed to write WebP: {}", e))
+}
+
+/// Write a frame sequence in the chosen format
+pub fn write_animation<W: Write>(
+    frames: &[SequenceFrame],
+    format: AnimationFormat,
+    writer: W,
+) -> AppResult<()> {
+    match format {
+        AnimationFormat::Apng => write_apng(frames, writer),
+        AnimationFormat::Webp => write_animated_webp(frames, writer),
+        AnimationFormat::Gif => {
+            let (width, height, images) = load_frames(frames)?;
+            let mut gif = GifWriter::new(writer, width, height, SEQUENCE_GIF_QUALITY)?;
+            for frame in images {
+                let (image, delay_ms) = frame?;
+                gif.push(image.into_owned(), delay_ms)?;
+            }
+            gif.finish()?;
+            Ok(())
+        }
+    }
+}
+
+/// Encode a frame sequence into a new file in `save_dir`
+pub fn export_animation(
+    frames: &[SequenceFrame],
+    format: AnimationFormat,
+    save_dir: &str,
+) -> AppResult<String> {
+    let save_path = PathBuf::from(save_dir);
+    ensure_dir(&save_path)?;
+
+    let filename = generate_filename("animation", format.extension())?;
+    let path = save_path.join(filename);
+    let file = File::create(&path).map_err(|e| format!("Failed to create animation: {}", e))?;
+
+    if let Err(e) = write_animation(frames, format, BufWriter::new(file)) {
+        let _ = std::fs::remove_file(&path);
+        return Err(e);
+    }
+
+    Ok(path.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::codecs::gif::GifDecoder;
+    use image::codecs::png::PngDecoder;
+    use image::codecs::webp::WebPDecoder;
+    use image::{AnimationDecoder, Frame, Rgba};
+    use std::io::Cursor;
+
+    fn solid(color: [u8; 4]) -> RgbaImage {
+        RgbaImage::from_pixel(32, 24, Rgba(color))
+    }
+
+    /// Composited frames and their delays in milliseconds
+    fn collect_frames<'a>(frames: impl AnimationDecoder<'a>) -> Vec<(RgbaImage, u32)> {
+        frames
+            .into_frames()
+            .map(|f: image::ImageResult<Frame>| {
+                let f = f.unwrap();
+                let (num, den) = f.delay().numer_denom_ms();
+                let delay = num / den;
+                (f.into_buffer(), delay)
+            })
+            .collect()
+    }
+
+    fn decode(bytes: &[u8]) -> Vec<(RgbaImage, u32)> {
+        collect_frames(GifDecoder::new(Cursor::new(bytes)).unwrap())
+    }
+
+    /// Two translucent frames, so alpha survives only in lossless formats
+    fn translucent_sequence() -> Vec<SequenceFrame> {
+        let mut first = solid([255, 0, 0, 128]);
+        first.put_pixel(1, 2, Rgba([10, 20, 30, 0]));
+        let mut second = solid([0, 128, 255, 200]);
+        second.put_pixel(31, 23, Rgba([1, 2, 3, 4]));
+
+        vec![
+            SequenceFrame {
+                source: FrameSource::Image(first),
+                delay_ms: 120,
+            },
+            SequenceFrame {
+                source: FrameSource::Image(second),
+                delay_ms: 70_000,
+            },
+        ]
+    }
+
+    fn frame_image(frame: &SequenceFrame) -> &RgbaImage {
+        match &frame.source {
+            FrameSource::Image(image) => image,
+            FrameSource::File(_) => unreachable!(),
+        }
+    }
+
+    fn temp_save_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "bettershot_test_animation_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn test_quantizer_speed_range() {
+        assert_eq!(quantizer_speed(1), 30);
+        assert_eq!(quantizer_speed(100), 1);
+        assert_eq!(quantizer_speed(0), 30);
+    }
+
+    #[test]
+    fn test_changed_bounds() {
+        let a = solid([0, 0, 0, 255]);
+        let mut b = a.clone();
+        assert_eq!(changed_bounds(&a, &b), None);
+
+        b.put_pixel(3, 4, Rgba([255, 0, 0, 255]));
+        b.put_pixel(10, 7, Rgba([255, 0, 0, 255]));
+        assert_eq!(changed_bounds(&a, &b), Some((3, 4, 8, 4)));
+    }
+
+    #[test]
+    fn test_gif_round_trip_with_delays() {
+        let mut writer = GifWriter::new(Vec::new(), 32, 24, 100).unwrap();
+        writer.push(solid([255, 0, 0, 255]), 100).unwrap();
+        writer.push(solid([0, 0, 255, 255]), 250).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let frames = decode(&bytes);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].1, 100);
+        assert_eq!(frames[1].1, 250);
+        assert_eq!(*frames[0].0.get_pixel(5, 5), Rgba([255, 0, 0, 255]));
+        assert_eq!(*frames[1].0.get_pixel(5, 5), Rgba([0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn test_gif_merges_duplicate_frames() {
+        let mut writer = GifWriter::new(Vec::new(), 32, 24, 80).unwrap();
+        writer.push(solid([255, 0, 0, 255]), 100).unwrap();
+        writer.push(solid([255, 0, 0, 255]), 100).unwrap();
+        writer.push(solid([255, 0, 0, 255]), 100).unwrap();
+        writer.push(solid([0, 255, 0, 255]), 100).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let frames = decode(&bytes);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[0].1, 300);
+    }
+
+    #[test]
+    fn test_gif_delta_frame_keeps_unchanged_pixels() {
+        let background = solid([40, 80, 120, 255]);
+        let mut moved = background.clone();
+        for x in 10..14 {
+            for y in 8..12 {
+                moved.put_pixel(x, y, Rgba([250, 250, 250, 255]));
+            }
+        }
+
+        let mut writer = GifWriter::new(Vec::new(), 32, 24, 100).unwrap();
+        writer.push(background.clone(), 100).unwrap();
+        writer.push(moved.clone(), 100).unwrap();
+        let bytes = writer.finish().unwrap();
+
+        let frames = decode(&bytes);
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].0.get_pixel(0, 0), background.get_pixel(0, 0));
+        assert_eq!(*frames[1].0.get_pixel(11, 9), Rgba([250, 250, 250, 255]));
+    }
+
+    #[test]
+    fn test_gif_delta_frames_are_smaller() {
+        let mut base = solid([0, 0, 0, 255]);
+        for (x, y, pixel) in base.enumerate_pixels_mut() {
+            *pixel = Rgba([(x * 8) as u8, (y * 10) as u8, 100, 255]);
+        }
+
+        let single = {
+            let mut writer = GifWriter::new(Vec::new(), 32, 24, 100).unwrap();
+            writer.push(base.clone(), 100).unwrap();
+            writer.finish().unwrap().len()
+        };
+
+        let mut changed = base.clone();
+        changed.put_pixel(0, 0, Rgba([255, 255, 255, 255]));
+        let double = {
+            let mut writer = GifWriter::new(Vec::new(), 32, 24, 100).unwrap();
+            writer.push(base, 100).unwrap();
+            writer.push(changed, 100).unwrap();
+            writer.finish().unwrap().len()
+        };
+
+        // The second frame is a single changed pixel, not a full image
+        assert!(double - single < single / 4);
+    }
+
+    #[test]
+    fn test_gif_rejects_mismatched_frame() {
+        let mut writer = GifWriter::new(Vec::new(), 32, 24, 80).unwrap();
+        assert!(writer.push(RgbaImage::new(10, 10), 100).is_err());
+        assert!(GifWriter::new(Vec::new(), 0, 24, 80).is_err());
+    }
+
+    #[test]
+    fn test_frames_from_paths_delays() {
+        let paths = vec!["a.png".to_string(), "b.png".to_string()];
+
+        let shared = frames_from_paths(&paths, &[50]).unwrap();
+        assert_eq!(
+            shared.iter().map(|f| f.delay_ms).collect::<Vec<_>>(),
+            vec![50, 50]
+        );
+
+        let each = frames_from_paths(&paths, &[50, 80]).unwrap();
+        assert_eq!(
+            each.iter().map(|f| f.delay_ms).collect::<Vec<_>>(),
+            vec![50, 80]
+        );
+
+        assert!(frames_from_paths(&paths, &[]).is_err());
+        assert!(frames_from_paths(&paths, &[1, 2, 3]).is_err());
+    }
+
+    #[test]
+    fn test_apng_delay() {
+        assert_eq!(apng_delay(120), (120, 1000));
+        assert_eq!(apng_delay(70_000), (7000, 100));
+        assert_eq!(apng_delay(u32::MAX), (u16::MAX, 100));
+    }
+
+    #[test]
+    fn test_apng_round_trip_keeps_alpha() {
+        let sequence = translucent_sequence();
+        let mut bytes = Vec::new();
+        write_apng(&sequence, &mut bytes).unwrap();
+
+        let decoder = PngDecoder::new(Cursor::new(&bytes)).unwrap();
+        assert!(decoder.is_apng().unwrap());
+        let frames = collect_frames(decoder.apng().unwrap());
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(&frames[0].0, frame_image(&sequence[0]));
+        assert_eq!(&frames[1].0, frame_image(&sequence[1]));
+        assert_eq!(frames[0].1, 120);
+        assert_eq!(frames[1].1, 70_000);
+    }
+
+    #[test]
+    fn test_webp_round_trip_keeps_alpha() {
+        let sequence = translucent_sequence();
+        let mut bytes = Vec::new();
+        write_animated_webp(&sequence, &mut bytes).unwrap();
+
+        let decoder = WebPDecoder::new(Cursor::new(&bytes)).unwrap();
+        assert!(decoder.has_animation());
+        let frames = collect_frames(decoder);
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(&frames[0].0, frame_image(&sequence[0]));
+        assert_eq!(&frames[1].0, frame_image(&sequence[1]));
+        assert_eq!(frames[0].1, 120);
+        assert_eq!(frames[1].1, 70_000);
+    }
+
+    #[test]
+    fn test_sequence_rejects_empty_and_mismatched_frames() {
+        for format in [
+            AnimationFormat::Gif,
+            AnimationFormat::Apng,
+            AnimationFormat::Webp,
+        ] {
+            assert!(write_animation(&[], format, Vec::new()).is_err());
+
+            let mut sequence = translucent_sequence();
+            sequence[1].source = FrameSource::Image(RgbaImage::new(8, 8));
+            assert!(write_animation(&sequence, format, Vec::new()).is_err());
+        }
+    }
+
+    #[test]
+    fn test_export_animation_from_disk() {
+        let dir = temp_save_dir("export");
+        let paths: Vec<String> = translucent_sequence()
+            .iter()
+            .enumerate()
+            .map(|(i, frame)| {
+                let path = dir.join(format!("frame_{}.png", i));
+                frame_image(frame).save(&path).unwrap();
+                path.to_string_lossy().into_owned()
+            })
+            .collect();
+        let frames = frames_from_paths(&paths, &[100]).unwrap();
+
+        for format in [
+            AnimationFormat::Gif,
+            AnimationFormat::Apng,
+            AnimationFormat::Webp,
+        ] {
+            let out_dir = dir.join(format.extension());
+            let path = export_animation(&frames, format, out_dir.to_str().unwrap()).unwrap();
+
+            assert!(path.ends_with(&format!(".{}", format.extension())));
+            let decoded = image::open(&path).unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (32, 24));
+        }
+
+        let missing = frames_from_paths(&["/nonexistent/frame.png".to_string()], &[100]).unwrap();
+        let out_dir = dir.join("missing");
+        assert!(
+            export_animation(&missing, AnimationFormat::Apng, out_dir.to_str().unwrap()).is_err()
+        );
+        assert_eq!(std::fs::read_dir(&out_dir).unwrap().count(), 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
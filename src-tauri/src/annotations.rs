@@ -0,0 +1,1154 @@
+//! Annotation model and rasterizer
+//!
+//! Mirrors the annotation types in `src/types/annotations.ts` and draws them
+//! the same way `drawAnnotationOnCanvas` does, so exports rendered here match
+//! what the editor shows.
+
+use ab_glyph::{point, Font, FontVec, PxScale, ScaleFont};
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::image::{blend_pixel, coverage_from_distance, hex_to_rgba, save_image, SavedImage};
+use crate::output::OutputOptions;
+use crate::utils::AppResult;
+
+/// Stroke width the editor falls back to when `border.width` is 0
+const DEFAULT_STROKE_WIDTH: f32 = 5.0;
+
+/// Blur radius the editor falls back to when `blurAmount` is 0
+const DEFAULT_BLUR_AMOUNT: u32 = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct Point {
+    pub x: f32,
+    pub y: f32,
+}
+
+/// Hex color with an opacity from 0 to 100
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Color {
+    pub hex: String,
+    pub opacity: f32,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Border {
+    pub width: f32,
+    pub color: Color,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum HorizontalAlign {
+    #[default]
+    Left,
+    Center,
+    Right,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VerticalAlign {
+    #[default]
+    Top,
+    Middle,
+    Bottom,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Alignment {
+    pub horizontal: HorizontalAlign,
+    pub vertical: VerticalAlign,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineType {
+    Straight,
+    Curved,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ArrowType {
+    Thin,
+    Thick,
+    None,
+}
+
+/// An annotation as serialized by the editor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Annotation {
+    pub id: String,
+    pub x: f32,
+    pub y: f32,
+    pub fill: Color,
+    pub border: Border,
+    #[serde(default)]
+    pub alignment: Alignment,
+    #[serde(flatten)]
+    pub kind: AnnotationKind,
+}
+
+/// Shape-specific annotation data, tagged by the editor's `type` field
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(
+    tag = "type",
+    rename_all = "lowercase",
+    rename_all_fields = "camelCase"
+)]
+pub enum AnnotationKind {
+    Circle {
+        radius: f32,
+    },
+    Rectangle {
+        width: f32,
+        height: f32,
+    },
+    Line {
+        end_x: f32,
+        end_y: f32,
+        line_type: LineType,
+        #[serde(default)]
+        control_points: Vec<Point>,
+    },
+    Arrow {
+        end_x: f32,
+        end_y: f32,
+        line_type: LineType,
+        arrow_type: ArrowType,
+        #[serde(default)]
+        control_points: Vec<Point>,
+    },
+    Text {
+        text: String,
+        font_size: f32,
+        font_family: String,
+        width: f32,
+        height: f32,
+    },
+    Number {
+        number: u32,
+        radius: f32,
+    },
+    Blur {
+        width: f32,
+        height: f32,
+        blur_amount: f32,
+    },
+}
+
+impl Color {
+    fn to_rgba(&self) -> Rgba<u8> {
+        let Rgba([r, g, b, _]) = hex_to_rgba(&self.hex).unwrap_or(Rgba([0, 0, 0, 255]));
+        let alpha = (self.opacity / 100.0).clamp(0.0, 1.0) * 255.0;
+        Rgba([r, g, b, alpha.round() as u8])
+    }
+}
+
+/// Pixel bounds (inclusive start, exclusive end) of a shape, clipped to the image
+fn clip_bounds(
+    img: &RgbaImage,
+    min_x: f32,
+    min_y: f32,
+    max_x: f32,
+    max_y: f32,
+) -> (u32, u32, u32, u32) {
+    let clip = |value: f32, limit: u32| value.clamp(0.0, limit as f32) as u32;
+    (
+        clip(min_x.floor() - 1.0, img.width()),
+        clip(min_y.floor() - 1.0, img.height()),
+        clip(max_x.ceil() + 1.0, img.width()),
+        clip(max_y.ceil() + 1.0, img.height()),
+    )
+}
+
+/// Blend `color` over every pixel in the bounds using a coverage function of the pixel center
+fn fill_coverage<F>(img: &mut RgbaImage, bounds: (u32, u32, u32, u32), color: Rgba<u8>, coverage: F)
+where
+    F: Fn(f32, f32) -> f32,
+{
+    let (x0, y0, x1, y1) = bounds;
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let value = coverage(x as f32 + 0.5, y as f32 + 0.5);
+            if value > 0.0 {
+                blend_pixel(img.get_pixel_mut(x, y), &color, value);
+            }
+        }
+    }
+}
+
+fn distance_to_segment(px: f32, py: f32, a: Point, b: Point) -> f32 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let length_sq = dx * dx + dy * dy;
+    let t = if length_sq > 0.0 {
+        (((px - a.x) * dx + (py - a.y) * dy) / length_sq).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let (cx, cy) = (a.x + dx * t, a.y + dy * t);
+    ((px - cx).powi(2) + (py - cy).powi(2)).sqrt()
+}
+
+/// Flatten a quadratic bezier into a polyline
+fn quadratic_curve(start: Point, control: Point, end: Point) -> Vec<Point> {
+    let length = distance_to_segment(start.x, start.y, control, control)
+        + distance_to_segment(control.x, control.y, end, end);
+    let steps = ((length / 4.0).ceil() as usize).clamp(8, 256);
+
+    (0..=steps)
+        .map(|i| {
+            let t = i as f32 / steps as f32;
+            let mt = 1.0 - t;
+            Point {
+                x: mt * mt * start.x + 2.0 * mt * t * control.x + t * t * end.x,
+                y: mt * mt * start.y + 2.0 * mt * t * control.y + t * t * end.y,
+            }
+        })
+        .collect()
+}
+
+/// Stroke a polyline with round caps and joins (canvas `lineCap = "round"`)
+fn stroke_polyline(img: &mut RgbaImage, points: &[Point], width: f32, color: Rgba<u8>) {
+    if points.is_empty() {
+        return;
+    }
+
+    let half = width / 2.0;
+    let min_x = points.iter().map(|p| p.x).fold(f32::MAX, f32::min) - half;
+    let min_y = points.iter().map(|p| p.y).fold(f32::MAX, f32::min) - half;
+    let max_x = points.iter().map(|p| p.x).fold(f32::MIN, f32::max) + half;
+    let max_y = points.iter().map(|p| p.y).fold(f32::MIN, f32::max) + half;
+    let bounds = clip_bounds(img, min_x, min_y, max_x, max_y);
+
+    let segments: Vec<(Point, Point)> = if points.len() == 1 {
+        vec![(points[0], points[0])]
+    } else {
+        points.windows(2).map(|w| (w[0], w[1])).collect()
+    };
+
+    fill_coverage(img, bounds, color, |px, py| {
+        let distance = segments
+            .iter()
+            .map(|(a, b)| distance_to_segment(px, py, *a, *b))
+            .fold(f32::MAX, f32::min);
+        coverage_from_distance(distance - half)
+    });
+}
+
+/// Stroke a circle outline centered on `radius`
+fn stroke_circle(img: &mut RgbaImage, center: Point, radius: f32, width: f32, color: Rgba<u8>) {
+    let extent = radius + width / 2.0;
+    let bounds = clip_bounds(
+        img,
+        center.x - extent,
+        center.y - extent,
+        center.x + extent,
+        center.y + extent,
+    );
+
+    fill_coverage(img, bounds, color, |px, py| {
+        let distance = ((px - center.x).powi(2) + (py - center.y).powi(2)).sqrt();
+        coverage_from_distance((distance - radius).abs() - width / 2.0)
+    });
+}
+
+fn fill_circle(img: &mut RgbaImage, center: Point, radius: f32, color: Rgba<u8>) {
+    let bounds = clip_bounds(
+        img,
+        center.x - radius,
+        center.y - radius,
+        center.x + radius,
+        center.y + radius,
+    );
+
+    fill_coverage(img, bounds, color, |px, py| {
+        let distance = ((px - center.x).powi(2) + (py - center.y).powi(2)).sqrt();
+        coverage_from_distance(distance - radius)
+    });
+}
+
+/// Signed distance to an axis-aligned box, negative inside
+fn box_distance(px: f32, py: f32, min: Point, max: Point) -> f32 {
+    let half_width = (max.x - min.x) / 2.0;
+    let half_height = (max.y - min.y) / 2.0;
+    let qx = (px - (min.x + half_width)).abs() - half_width;
+    let qy = (py - (min.y + half_height)).abs() - half_height;
+    (qx.max(0.0).powi(2) + qy.max(0.0).powi(2)).sqrt() + qx.max(qy).min(0.0)
+}
+
+/// Stroke a rectangle outline centered on its edges with miter joins (canvas `strokeRect`)
+fn stroke_rect(
+    img: &mut RgbaImage,
+    origin: Point,
+    width: f32,
+    height: f32,
+    stroke: f32,
+    color: Rgba<u8>,
+) {
+    // Normalize negative sizes, which the editor produces when dragging up or left
+    let min = Point {
+        x: origin.x.min(origin.x + width),
+        y: origin.y.min(origin.y + height),
+    };
+    let max = Point {
+        x: origin.x.max(origin.x + width),
+        y: origin.y.max(origin.y + height),
+    };
+    let half = stroke / 2.0;
+    let bounds = clip_bounds(img, min.x - half, min.y - half, max.x + half, max.y + half);
+
+    fill_coverage(img, bounds, color, |px, py| {
+        // Chebyshev distance keeps the outer corners square like a miter join
+        let outside_x = (min.x - px).max(px - max.x);
+        let outside_y = (min.y - py).max(py - max.y);
+        let outer = coverage_from_distance(outside_x.max(outside_y) - half);
+        let inner = coverage_from_distance(box_distance(px, py, min, max) + half);
+        (outer - inner).max(0.0)
+    });
+}
+
+/// Fill a simple polygon using the nonzero winding rule
+fn fill_polygon(img: &mut RgbaImage, points: &[Point], color: Rgba<u8>) {
+    if points.len() < 3 {
+        return;
+    }
+
+    let min_x = points.iter().map(|p| p.x).fold(f32::MAX, f32::min);
+    let min_y = points.iter().map(|p| p.y).fold(f32::MAX, f32::min);
+    let max_x = points.iter().map(|p| p.x).fold(f32::MIN, f32::max);
+    let max_y = points.iter().map(|p| p.y).fold(f32::MIN, f32::max);
+    let bounds = clip_bounds(img, min_x, min_y, max_x, max_y);
+
+    let edges: Vec<(Point, Point)> = (0..points.len())
+        .map(|i| (points[i], points[(i + 1) % points.len()]))
+        .collect();
+
+    fill_coverage(img, bounds, color, |px, py| {
+        let mut winding = 0;
+        let mut distance = f32::MAX;
+
+        for (a, b) in &edges {
+            distance = distance.min(distance_to_segment(px, py, *a, *b));
+            let cross = (b.x - a.x) * (py - a.y) - (px - a.x) * (b.y - a.y);
+            if a.y <= py && b.y > py && cross > 0.0 {
+                winding += 1;
+            } else if a.y > py && b.y <= py && cross < 0.0 {
+                winding -= 1;
+            }
+        }
+
+        let signed = if winding != 0 { -distance } else { distance };
+        coverage_from_distance(signed)
+    });
+}
+
+/// Box blur matching the editor's `applyBoxBlur`, sampling only inside the region
+fn box_blur_region(img: &mut RgbaImage, x: u32, y: u32, width: u32, height: u32, radius: u32) {
+    let region = image::imageops::crop_imm(img, x, y, width, height).to_image();
+    let radius = radius as i64;
+    let count = (radius * 2 + 1) as u32;
+
+    let horizontal = RgbaImage::from_fn(width, height, |rx, ry| {
+        let mut sum = [0u32; 4];
+        for k in -radius..=radius {
+            let sx = (rx as i64 + k).clamp(0, width as i64 - 1) as u32;
+            let pixel = region.get_pixel(sx, ry);
+            for c in 0..4 {
+                sum[c] += pixel[c] as u32;
+            }
+        }
+        Rgba(sum.map(|v| (v / count) as u8))
+    });
+
+    for ry in 0..height {
+        for rx in 0..width {
+            let mut sum = [0u32; 4];
+            for k in -radius..=radius {
+                let sy = (ry as i64 + k).clamp(0, height as i64 - 1) as u32;
+                let pixel = horizontal.get_pixel(rx, sy);
+                for c in 0..4 {
+                    sum[c] += pixel[c] as u32;
+                }
+            }
+            img.put_pixel(x + rx, y + ry, Rgba(sum.map(|v| (v / count) as u8)));
+        }
+    }
+}
+
+/// Directories searched for font files, in priority order
+fn font_dirs() -> Vec<PathBuf> {
+    let mut dirs = vec![
+        PathBuf::from("/System/Library/Fonts"),
+        PathBuf::from("/Library/Fonts"),
+        PathBuf::from("/usr/share/fonts"),
+        PathBuf::from("/usr/local/share/fonts"),
+        PathBuf::from("C:\\Windows\\Fonts"),
+    ];
+    if let Some(home) = dirs::home_dir() {
+        dirs.push(home.join("Library/Fonts"));
+        dirs.push(home.join(".local/share/fonts"));
+        dirs.push(home.join(".fonts"));
+    }
+    dirs
+}
+
+/// Lowercase and strip everything but letters and digits, so "DejaVu Sans" matches "DejaVuSans.ttf"
+fn normalize_font_name(name: &str) -> String {
+    name.chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect()
+}
+
+fn find_font_file(dir: &Path, names: &[String], depth: u32) -> Option<PathBuf> {
+    let entries = std::fs::read_dir(dir).ok()?;
+    let mut subdirs = Vec::new();
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            subdirs.push(path);
+            continue;
+        }
+
+        let is_font = path
+            .extension()
+            .and_then(|e| e.to_str())
+            .is_some_and(|e| matches!(e.to_ascii_lowercase().as_str(), "ttf" | "otf" | "ttc"));
+        let stem = path
+            .file_stem()
+            .map(|s| normalize_font_name(&s.to_string_lossy()))
+            .unwrap_or_default();
+        if is_font && names.contains(&stem) {
+            return Some(path);
+        }
+    }
+
+    if depth == 0 {
+        return None;
+    }
+    subdirs
+        .iter()
+        .find_map(|subdir| find_font_file(subdir, names, depth - 1))
+}
+
+/// Families tried when the requested one isn't installed
+const FALLBACK_FONTS: &[&str] = &[
+    "Arial",
+    "Helvetica",
+    "Liberation Sans",
+    "DejaVu Sans",
+    "Noto Sans",
+];
+
+/// DejaVu Sans, used when neither the requested family nor any fallback is
+/// installed so text still renders on minimal systems
+const BUNDLED_FONT: &[u8] = include_bytes!("../fonts/DejaVuSans.ttf");
+
+/// Load a system font by family name, falling back to common sans-serif fonts
+/// and then to the bundled font
+fn load_font(family: &str, bold: bool) -> AppResult<FontVec> {
+    let families = std::iter::once(family).chain(FALLBACK_FONTS.iter().copied());
+
+    for family in families {
+        let base = normalize_font_name(family);
+        let names = if bold {
+            vec![format!("{}bold", base), format!("{}bd", base)]
+        } else {
+            vec![base.clone(), format!("{}regular", base)]
+        };
+
+        for dir in font_dirs() {
+            let Some(path) = find_font_file(&dir, &names, 3) else {
+                continue;
+            };
+            let Ok(data) = std::fs::read(&path) else {
+                continue;
+            };
+            if let Ok(font) = FontVec::try_from_vec_and_index(data, 0) {
+                return Ok(font);
+            }
+        }
+    }
+
+    if bold {
+        // Faux bold isn't worth it; a regular weight beats failing the whole export
+        return load_font(family, false);
+    }
+    FontVec::try_from_vec(BUNDLED_FONT.to_vec())
+        .map_err(|e| format!("Failed to load bundled font: {}", e))
+}
+
+/// Scale that renders a font at a CSS pixel size (em height)
+fn px_scale(font: &FontVec, size: f32) -> PxScale {
+    let units_per_em = font.units_per_em().unwrap_or(1000.0);
+    PxScale::from(size * font.height_unscaled() / units_per_em)
+}
+
+fn measure_text(font: &FontVec, scale: PxScale, text: &str) -> f32 {
+    let scaled = font.as_scaled(scale);
+    let mut width = 0.0;
+    let mut previous = None;
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(prev) = previous {
+            width += scaled.kern(prev, id);
+        }
+        width += scaled.h_advance(id);
+        previous = Some(id);
+    }
+    width
+}
+
+/// Draw a single line of text with its alphabetic baseline at `baseline`
+fn draw_text(
+    img: &mut RgbaImage,
+    font: &FontVec,
+    scale: PxScale,
+    x: f32,
+    baseline: f32,
+    text: &str,
+    color: Rgba<u8>,
+) {
+    let scaled = font.as_scaled(scale);
+    let mut caret = x;
+    let mut previous = None;
+
+    for c in text.chars() {
+        let id = scaled.glyph_id(c);
+        if let Some(prev) = previous {
+            caret += scaled.kern(prev, id);
+        }
+        let glyph = id.with_scale_and_position(scale, point(caret, baseline));
+        caret += scaled.h_advance(id);
+        previous = Some(id);
+
+        let Some(outline) = font.outline_glyph(glyph) else {
+            continue;
+        };
+        let bounds = outline.px_bounds();
+        outline.draw(|gx, gy, coverage| {
+            let px = bounds.min.x as i64 + gx as i64;
+            let py = bounds.min.y as i64 + gy as i64;
+            if px >= 0 && py >= 0 && (px as u32) < img.width() && (py as u32) < img.height() {
+                blend_pixel(img.get_pixel_mut(px as u32, py as u32), &color, coverage);
+            }
+        });
+    }
+}
+
+/// Caches fonts across annotations of a single render
+#[derive(Default)]
+struct FontCache {
+    fonts: HashMap<(String, bool), FontVec>,
+}
+
+impl FontCache {
+    fn get(&mut self, family: &str, bold: bool) -> AppResult<&FontVec> {
+        let key = (family.to_string(), bold);
+        if !self.fonts.contains_key(&key) {
+            let font = load_font(family, bold)?;
+            self.fonts.insert(key.clone(), font);
+        }
+        Ok(&self.fonts[&key])
+    }
+}
+
+fn draw_annotation(
+    img: &mut RgbaImage,
+    annotation: &Annotation,
+    fonts: &mut FontCache,
+) -> AppResult<()> {
+    let color = annotation.fill.to_rgba();
+    let stroke_width = if annotation.border.width > 0.0 {
+        annotation.border.width
+    } else {
+        DEFAULT_STROKE_WIDTH
+    };
+    let start = Point {
+        x: annotation.x,
+        y: annotation.y,
+    };
+
+    match &annotation.kind {
+        AnnotationKind::Circle { radius } => {
+            stroke_circle(img, start, *radius, stroke_width, color);
+        }
+        AnnotationKind::Rectangle { width, height } => {
+            stroke_rect(img, start, *width, *height, stroke_width, color);
+        }
+        AnnotationKind::Line {
+            end_x,
+            end_y,
+            line_type,
+            control_points,
+        } => {
+            let end = Point {
+                x: *end_x,
+                y: *end_y,
+            };
+            let path = match (line_type, control_points.first()) {
+                (LineType::Curved, Some(control)) => quadratic_curve(start, *control, end),
+                _ => vec![start, end],
+            };
+            stroke_polyline(img, &path, stroke_width, color);
+        }
+        AnnotationKind::Arrow {
+            end_x,
+            end_y,
+            line_type,
+            arrow_type,
+            control_points,
+        } => {
+            let end = Point {
+                x: *end_x,
+                y: *end_y,
+            };
+            let control = match line_type {
+                LineType::Curved => control_points.first().copied(),
+                LineType::Straight => None,
+            };
+
+            let from = control.unwrap_or(start);
+            let angle = (end.y - from.y).atan2(end.x - from.x);
+
+            let head_length = match arrow_type {
+                ArrowType::Thick => (stroke_width * 6.0).max(20.0),
+                ArrowType::Thin => (stroke_width * 3.0).max(12.0),
+                ArrowType::None => 0.0,
+            };
+
+            // Stop the shaft inside the head so its round cap doesn't poke through the tip
+            let shorten_by = head_length * 0.7;
+            let line_end = Point {
+                x: end.x - shorten_by * angle.cos(),
+                y: end.y - shorten_by * angle.sin(),
+            };
+
+            let path = match control {
+                Some(control) => quadratic_curve(start, control, line_end),
+                None => vec![start, line_end],
+            };
+            stroke_polyline(img, &path, stroke_width, color);
+
+            if head_length > 0.0 {
+                let wing = std::f32::consts::PI / 6.0;
+                let head = [
+                    end,
+                    Point {
+                        x: end.x - head_length * (angle - wing).cos(),
+                        y: end.y - head_length * (angle - wing).sin(),
+                    },
+                    Point {
+                        x: end.x - head_length * 0.6 * angle.cos(),
+                        y: end.y - head_length * 0.6 * angle.sin(),
+                    },
+                    Point {
+                        x: end.x - head_length * (angle + wing).cos(),
+                        y: end.y - head_length * (angle + wing).sin(),
+                    },
+                ];
+                fill_polygon(img, &head, color);
+            }
+        }
+        AnnotationKind::Text {
+            text,
+            font_size,
+            font_family,
+            ..
+        } => {
+            let font = fonts.get(font_family, false)?;
+            let scale = px_scale(font, *font_size);
+            draw_text(
+                img,
+                font,
+                scale,
+                annotation.x,
+                annotation.y + font_size,
+                text,
+                color,
+            );
+        }
+        AnnotationKind::Number { number, radius } => {
+            fill_circle(img, start, *radius, color);
+            if annotation.border.width > 0.0 {
+                stroke_circle(img, start, *radius, annotation.border.width, color);
+            }
+
+            let font = fonts.get("Arial", true)?;
+            let label = number.to_string();
+            let scale = px_scale(font, radius * 1.2);
+            let scaled = font.as_scaled(scale);
+            let text_width = measure_text(font, scale, &label);
+            // Center on the em box like canvas `textBaseline = "middle"`
+            let baseline = annotation.y + (scaled.ascent() + scaled.descent()) / 2.0;
+            draw_text(
+                img,
+                font,
+                scale,
+                annotation.x - text_width / 2.0,
+                baseline,
+                &label,
+                Rgba([255, 255, 255, 255]),
+            );
+        }
+        AnnotationKind::Blur {
+            width,
+            height,
+            blur_amount,
+        } => {
+            let x = annotation.x.floor().max(0.0) as u32;
+            let y = annotation.y.floor().max(0.0) as u32;
+            if x >= img.width() || y >= img.height() {
+                return Ok(());
+            }
+            let width = (width.ceil().max(0.0) as u32).min(img.width() - x);
+            let height = (height.ceil().max(0.0) as u32).min(img.height() - y);
+            let radius = if *blur_amount > 0.0 {
+                blur_amount.round() as u32
+            } else {
+                DEFAULT_BLUR_AMOUNT
+            };
+
+            // The editor's dashed outline is only a selection hint and isn't exported
+            if width > 0 && height > 0 {
+                box_blur_region(img, x, y, width, height, radius);
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Draw annotations onto an image in list order
+pub fn flatten_annotations(img: &mut RgbaImage, annotations: &[Annotation]) -> AppResult<()> {
+    let mut fonts = FontCache::default();
+    for annotation in annotations {
+        draw_annotation(img, annotation, &mut fonts)?;
+    }
+    Ok(())
+}
+
+/// Open an image, flatten annotations onto it and save the result
+pub fn render_annotations(
+    image_path: &str,
+    annotations: &[Annotation],
+    save_dir: &str,
+    output: &OutputOptions,
+) -> AppResult<SavedImage> {
+    let mut img = image::open(image_path)
+        .map_err(|e| format!("Failed to open image: {}", e))?
+        .to_rgba8();
+
+    flatten_annotations(&mut img, annotations)?;
+
+    save_image(
+        &image::DynamicImage::ImageRgba8(img),
+        save_dir,
+        "annotated",
+        output,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WHITE: Rgba<u8> = Rgba([255, 255, 255, 255]);
+    const RED: Rgba<u8> = Rgba([255, 0, 0, 255]);
+
+    fn canvas() -> RgbaImage {
+        RgbaImage::from_pixel(100, 100, WHITE)
+    }
+
+    fn annotation(x: f32, y: f32, border_width: f32, kind: AnnotationKind) -> Annotation {
+        Annotation {
+            id: "test".to_string(),
+            x,
+            y,
+            fill: Color {
+                hex: "#ff0000".to_string(),
+                opacity: 100.0,
+            },
+            border: Border {
+                width: border_width,
+                color: Color {
+                    hex: "#000000".to_string(),
+                    opacity: 100.0,
+                },
+            },
+            alignment: Alignment::default(),
+            kind,
+        }
+    }
+
+    fn draw(img: &mut RgbaImage, annotation: Annotation) {
+        flatten_annotations(img, &[annotation]).unwrap();
+    }
+
+    mod model {
+        use super::*;
+
+        #[test]
+        fn test_deserialize_editor_arrow() {
+            let json = r##"{
+                "id": "a1",
+                "type": "arrow",
+                "x": 10,
+                "y": 20,
+                "endX": 80,
+                "endY": 90,
+                "lineType": "curved",
+                "arrowType": "thick",
+                "controlPoints": [{ "x": 50, "y": 10 }],
+                "fill": { "hex": "#ff0000", "opacity": 100 },
+                "border": { "width": 4, "color": { "hex": "#000000", "opacity": 100 } },
+                "alignment": { "horizontal": "center", "vertical": "middle" }
+            }"##;
+            let annotation: Annotation = serde_json::from_str(json).unwrap();
+
+            assert_eq!(annotation.x, 10.0);
+            assert_eq!(annotation.alignment.horizontal, HorizontalAlign::Center);
+            assert_eq!(
+                annotation.kind,
+                AnnotationKind::Arrow {
+                    end_x: 80.0,
+                    end_y: 90.0,
+                    line_type: LineType::Curved,
+                    arrow_type: ArrowType::Thick,
+                    control_points: vec![Point { x: 50.0, y: 10.0 }],
+                }
+            );
+        }
+
+        #[test]
+        fn test_deserialize_optional_fields() {
+            let json = r##"[
+                {
+                    "id": "l1", "type": "line", "x": 0, "y": 0, "endX": 5, "endY": 5,
+                    "lineType": "straight",
+                    "fill": { "hex": "#000000", "opacity": 50 },
+                    "border": { "width": 0, "color": { "hex": "#000000", "opacity": 100 } }
+                },
+                {
+                    "id": "b1", "type": "blur", "x": 1, "y": 2, "width": 30, "height": 40,
+                    "blurAmount": 12,
+                    "fill": { "hex": "#000000", "opacity": 100 },
+                    "border": { "width": 0, "color": { "hex": "#000000", "opacity": 100 } }
+                }
+            ]"##;
+            let annotations: Vec<Annotation> = serde_json::from_str(json).unwrap();
+
+            assert_eq!(annotations.len(), 2);
+            assert!(matches!(
+                &annotations[0].kind,
+                AnnotationKind::Line { control_points, .. } if control_points.is_empty()
+            ));
+            assert!(matches!(
+                annotations[1].kind,
+                AnnotationKind::Blur { blur_amount, .. } if blur_amount == 12.0
+            ));
+        }
+
+        #[test]
+        fn test_serialize_round_trip() {
+            let original = annotation(
+                5.0,
+                6.0,
+                2.0,
+                AnnotationKind::Number {
+                    number: 3,
+                    radius: 12.0,
+                },
+            );
+            let json = serde_json::to_string(&original).unwrap();
+
+            assert!(json.contains("\"type\":\"number\""));
+            let parsed: Annotation = serde_json::from_str(&json).unwrap();
+            assert_eq!(parsed, original);
+        }
+
+        #[test]
+        fn test_color_opacity() {
+            let color = Color {
+                hex: "#00ff00".to_string(),
+                opacity: 50.0,
+            };
+            assert_eq!(color.to_rgba(), Rgba([0, 255, 0, 128]));
+        }
+    }
+
+    mod shapes {
+        use super::*;
+
+        #[test]
+        fn test_circle_is_stroked_not_filled() {
+            let mut img = canvas();
+            draw(
+                &mut img,
+                annotation(50.0, 50.0, 4.0, AnnotationKind::Circle { radius: 20.0 }),
+            );
+
+            assert_eq!(*img.get_pixel(70, 50), RED);
+            assert_eq!(*img.get_pixel(50, 30), RED);
+            assert_eq!(*img.get_pixel(50, 50), WHITE);
+            assert_eq!(*img.get_pixel(80, 50), WHITE);
+        }
+
+        #[test]
+        fn test_rectangle_stroke_and_negative_size() {
+            let mut img = canvas();
+            draw(
+                &mut img,
+                annotation(
+                    80.0,
+                    80.0,
+                    2.0,
+                    AnnotationKind::Rectangle {
+                        width: -60.0,
+                        height: -60.0,
+                    },
+                ),
+            );
+
+            // Edges and square corners are painted, the inside is left alone
+            assert_eq!(*img.get_pixel(20, 50), RED);
+            assert_eq!(*img.get_pixel(79, 50), RED);
+            assert_eq!(*img.get_pixel(20, 20), RED);
+            assert_eq!(*img.get_pixel(50, 50), WHITE);
+            assert_eq!(*img.get_pixel(10, 10), WHITE);
+        }
+
+        #[test]
+        fn test_zero_border_uses_default_width() {
+            let mut img = canvas();
+            draw(
+                &mut img,
+                annotation(
+                    10.0,
+                    50.0,
+                    0.0,
+                    AnnotationKind::Line {
+                        end_x: 90.0,
+                        end_y: 50.0,
+                        line_type: LineType::Straight,
+                        control_points: vec![],
+                    },
+                ),
+            );
+
+            // Default width is 5, so 2 pixels on each side of the center row
+            assert_eq!(*img.get_pixel(50, 48), RED);
+            assert_eq!(*img.get_pixel(50, 51), RED);
+            assert_eq!(*img.get_pixel(50, 46), WHITE);
+            assert_eq!(*img.get_pixel(50, 53), WHITE);
+        }
+
+        #[test]
+        fn test_curved_line_follows_control_point() {
+            let mut img = canvas();
+            draw(
+                &mut img,
+                annotation(
+                    10.0,
+                    80.0,
+                    4.0,
+                    AnnotationKind::Line {
+                        end_x: 90.0,
+                        end_y: 80.0,
+                        line_type: LineType::Curved,
+                        control_points: vec![Point { x: 50.0, y: 0.0 }],
+                    },
+                ),
+            );
+
+            // The midpoint of the quadratic curve is halfway to the control point
+            assert_eq!(*img.get_pixel(50, 40), RED);
+            assert_eq!(*img.get_pixel(50, 80), WHITE);
+        }
+
+        #[test]
+        fn test_arrow_head_is_filled() {
+            let mut img = canvas();
+            draw(
+                &mut img,
+                annotation(
+                    10.0,
+                    50.0,
+                    2.0,
+                    AnnotationKind::Arrow {
+                        end_x: 90.0,
+                        end_y: 50.0,
+                        line_type: LineType::Straight,
+                        arrow_type: ArrowType::Thick,
+                        control_points: vec![],
+                    },
+                ),
+            );
+
+            // Thick head is 20px long with wings at +/-30 degrees
+            assert_eq!(*img.get_pixel(85, 50), RED);
+            assert_eq!(*img.get_pixel(80, 46), RED);
+            assert_eq!(*img.get_pixel(80, 54), RED);
+            // The shaft stays thin away from the head
+            assert_eq!(*img.get_pixel(40, 50), RED);
+            assert_eq!(*img.get_pixel(40, 46), WHITE);
+        }
+
+        #[test]
+        fn test_arrow_without_head() {
+            let mut img = canvas();
+            draw(
+                &mut img,
+                annotation(
+                    10.0,
+                    50.0,
+                    2.0,
+                    AnnotationKind::Arrow {
+                        end_x: 90.0,
+                        end_y: 50.0,
+                        line_type: LineType::Straight,
+                        arrow_type: ArrowType::None,
+                        control_points: vec![],
+                    },
+                ),
+            );
+
+            assert_eq!(*img.get_pixel(89, 50), RED);
+            assert_eq!(*img.get_pixel(80, 46), WHITE);
+        }
+
+        #[test]
+        fn test_number_badge_fill() {
+            let mut img = canvas();
+            draw(
+                &mut img,
+                annotation(
+                    50.0,
+                    50.0,
+                    0.0,
+                    AnnotationKind::Number {
+                        number: 7,
+                        radius: 20.0,
+                    },
+                ),
+            );
+
+            assert_eq!(*img.get_pixel(50, 33), RED);
+            assert_eq!(*img.get_pixel(50, 25), WHITE);
+            // The label is drawn in white inside the badge
+            let has_label = (40..60).any(|y| (40..60).any(|x| img.get_pixel(x, y)[1] > 200));
+            assert!(has_label);
+        }
+
+        #[test]
+        fn test_text_is_drawn_below_its_origin() {
+            let mut img = canvas();
+            draw(
+                &mut img,
+                annotation(
+                    10.0,
+                    20.0,
+                    0.0,
+                    AnnotationKind::Text {
+                        text: "HI".to_string(),
+                        font_size: 40.0,
+                        font_family: "No Such Font".to_string(),
+                        width: 80.0,
+                        height: 50.0,
+                    },
+                ),
+            );
+
+            // Glyphs sit on a baseline one font size below the origin
+            let inked = |xs: std::ops::Range<u32>, ys: std::ops::Range<u32>| {
+                ys.flat_map(|y| xs.clone().map(move |x| (x, y)))
+                    .filter(|&(x, y)| *img.get_pixel(x, y) == RED)
+                    .count()
+            };
+            assert!(inked(10..70, 20..60) > 100);
+            assert_eq!(inked(0..100, 0..20), 0);
+            assert_eq!(inked(0..100, 65..100), 0);
+            assert_eq!(inked(0..8, 0..100), 0);
+        }
+
+        #[test]
+        fn test_bundled_font_has_glyphs() {
+            let font = FontVec::try_from_vec(BUNDLED_FONT.to_vec()).unwrap();
+            assert_ne!(font.glyph_id('A').0, 0);
+            assert_ne!(font.glyph_id('7').0, 0);
+        }
+
+        #[test]
+        fn test_half_opacity_blends() {
+            let mut img = canvas();
+            let mut circle = annotation(50.0, 50.0, 6.0, AnnotationKind::Circle { radius: 20.0 });
+            circle.fill.opacity = 50.0;
+            draw(&mut img, circle);
+
+            assert_eq!(*img.get_pixel(70, 50), Rgba([255, 127, 127, 255]));
+        }
+
+        #[test]
+        fn test_shapes_outside_image_are_clipped() {
+            let mut img = canvas();
+            draw(
+                &mut img,
+                annotation(-50.0, -50.0, 4.0, AnnotationKind::Circle { radius: 10.0 }),
+            );
+            draw(
+                &mut img,
+                annotation(
+                    200.0,
+                    200.0,
+                    0.0,
+                    AnnotationKind::Blur {
+                        width: 50.0,
+                        height: 50.0,
+                        blur_amount: 5.0,
+                    },
+                ),
+            );
+
+            assert!(img.pixels().all(|p| *p == WHITE));
+        }
+    }
+
+    mod blur {
+        use super::*;
+
+        #[test]
+        fn test_blur_smooths_region_only() {
+            let mut img = RgbaImage::from_fn(100, 100, |x, _| {
+                if x % 2 == 0 {
+                    WHITE
+                } else {
+                    Rgba([0, 0, 0, 255])
+                }
+            });
+            let original = img.clone();
+            draw(
+                &mut img,
+                annotation(
+                    20.0,
+                    20.0,
+                    0.0,
+                    AnnotationKind::Blur {
+                        width: 40.0,
+                        height: 40.0,
+                        blur_amount: 3.0,
+                    },
+                ),
+            );
+
+            let inside = img.get_pixel(40, 40)[0];
+            assert!((100..=155).contains(&inside), "got {}", inside);
+            assert_eq!(img.get_pixel(10, 10), original.get_pixel(10, 10));
+            assert_eq!(img.get_pixel(70, 40), original.get_pixel(70, 40));
+        }
+    }
+}
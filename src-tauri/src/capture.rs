@@ -0,0 +1,962 @@
+//! Capture backends
+//!
+//! `CaptureBackend` abstracts over where pixels come from, so the capture
+//! code paths can be exercised without a display. `XcapBackend` talks to the
+//! real desktop through xcap; `MockBackend` returns deterministic synthetic
+//! images and is selected by setting `BETTERSHOT_CAPTURE_BACKEND=mock`.
+
+use image::imageops::{overlay, resize, FilterType};
+use image::{Rgba, RgbaImage};
+use serde::{Deserialize, Serialize};
+use xcap::{Monitor, Window};
+
+use crate::cursor::{self, CursorState};
+use crate::utils::AppResult;
+
+/// Environment variable used to select the capture backend
+pub const BACKEND_ENV: &str = "BETTERSHOT_CAPTURE_BACKEND";
+
+/// Largest width or height of a stitched image, in pixels
+const MAX_STITCHED_DIMENSION: u32 = 32768;
+
+/// A monitor, in global desktop coordinates
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct MonitorInfo {
+    pub id: u32,
+    pub name: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f32,
+    pub is_primary: bool,
+}
+
+/// A top-level window, in global desktop coordinates
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct WindowInfo {
+    pub id: u32,
+    pub app_name: String,
+    pub title: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub z: i32,
+    pub is_minimized: bool,
+}
+
+/// A source of monitors, windows and their pixels
+pub trait CaptureBackend: Send + Sync {
+    /// List the connected monitors
+    fn monitors(&self) -> AppResult<Vec<MonitorInfo>>;
+
+    /// List the capturable windows
+    fn windows(&self) -> AppResult<Vec<WindowInfo>>;
+
+    /// Read the mouse cursor's position and shape
+    fn cursor(&self) -> AppResult<CursorState>;
+
+    /// Capture a whole monitor at its native resolution
+    fn capture_monitor(&self, id: u32) -> AppResult<RgbaImage>;
+
+    /// Capture a single window
+    fn capture_window(&self, id: u32) -> AppResult<RgbaImage>;
+
+    /// Capture part of a monitor at its native resolution.
+    ///
+    /// The region is relative to the monitor's top-left corner, in the same
+    /// units as `MonitorInfo` geometry.
+    fn capture_monitor_region(
+        &self,
+        id: u32,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> AppResult<RgbaImage>;
+
+    /// Capture a rectangle in global desktop coordinates.
+    ///
+    /// Only the monitors intersecting the rectangle are captured. Their pieces
+    /// are resampled to the densest pixel density among them, so a region
+    /// spanning a Retina and a standard display comes out uniformly sharp.
+    /// Parts of the rectangle that no monitor covers are left transparent.
+    fn capture_rect(&self, x: i32, y: i32, width: u32, height: u32) -> AppResult<RgbaImage> {
+        if width == 0 || height == 0 {
+            return Err("Capture region is empty".to_string());
+        }
+
+        let rect = Rect {
+            x,
+            y,
+            width,
+            height,
+        };
+        let pieces = capture_pieces(self, &rect)?;
+
+        if pieces.is_empty() {
+            return Err(format!(
+                "No monitor intersects region ({}, {}, {}x{})",
+                x, y, width, height
+            ));
+        }
+
+        stitch(&rect, &pieces, None)
+    }
+
+    /// The primary monitor, or the first one if none is marked primary
+    fn primary_monitor(&self) -> AppResult<MonitorInfo> {
+        let monitors = self.monitors()?;
+        let primary = monitors.iter().position(|m| m.is_primary).unwrap_or(0);
+
+        monitors
+            .into_iter()
+            .nth(primary)
+            .ok_or_else(|| "No monitors available".to_string())
+    }
+}
+
+/// What a repeated or recorded capture grabs each frame
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum CaptureTarget {
+    /// A monitor by id, or the primary monitor
+    Monitor { id: Option<u32> },
+    /// A window by id
+    Window { id: u32 },
+    /// A rectangle in global desktop coordinates
+    Rect {
+        x: i32,
+        y: i32,
+        width: u32,
+        height: u32,
+    },
+}
+
+impl CaptureTarget {
+    /// Grab one frame of this target
+    pub fn capture(&self, backend: &dyn CaptureBackend) -> AppResult<RgbaImage> {
+        match *self {
+            CaptureTarget::Monitor { id: Some(id) } => backend.capture_monitor(id),
+            CaptureTarget::Monitor { id: None } => {
+                backend.capture_monitor(backend.primary_monitor()?.id)
+            }
+            CaptureTarget::Window { id } => backend.capture_window(id),
+            CaptureTarget::Rect {
+                x,
+                y,
+                width,
+                height,
+            } => backend.capture_rect(x, y, width, height),
+        }
+    }
+}
+
+/// Pick the backend for this process
+pub fn backend() -> Box<dyn CaptureBackend> {
+    match std::env::var(BACKEND_ENV) {
+        Ok(name) if name.eq_ignore_ascii_case("mock") => Box::new(MockBackend::default()),
+        _ => Box::new(XcapBackend),
+    }
+}
+
+/// An axis-aligned rectangle in global desktop coordinates
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Rect {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Rect {
+    fn of_monitor(monitor: &MonitorInfo) -> Self {
+        Self {
+            x: monitor.x,
+            y: monitor.y,
+            width: monitor.width,
+            height: monitor.height,
+        }
+    }
+
+    fn right(&self) -> i64 {
+        self.x as i64 + self.width as i64
+    }
+
+    fn bottom(&self) -> i64 {
+        self.y as i64 + self.height as i64
+    }
+
+    fn intersect(&self, other: &Rect) -> Option<Rect> {
+        let left = self.x.max(other.x);
+        let top = self.y.max(other.y);
+        let right = self.right().min(other.right());
+        let bottom = self.bottom().min(other.bottom());
+
+        if right <= left as i64 || bottom <= top as i64 {
+            return None;
+        }
+
+        Some(Rect {
+            x: left,
+            y: top,
+            width: (right - left as i64) as u32,
+            height: (bottom - top as i64) as u32,
+        })
+    }
+
+    /// The smallest rectangle containing every monitor
+    fn bounding(monitors: &[MonitorInfo]) -> Option<Rect> {
+        let left = monitors.iter().map(|m| m.x as i64).min()?;
+        let top = monitors.iter().map(|m| m.y as i64).min()?;
+        let right = monitors.iter().map(|m| Rect::of_monitor(m).right()).max()?;
+        let bottom = monitors
+            .iter()
+            .map(|m| Rect::of_monitor(m).bottom())
+            .max()?;
+
+        Some(Rect {
+            x: left as i32,
+            y: top as i32,
+            width: (right - left) as u32,
+            height: (bottom - top) as u32,
+        })
+    }
+}
+
+/// Capture the part of each monitor that `rect` covers
+fn capture_pieces<B: CaptureBackend + ?Sized>(
+    backend: &B,
+    rect: &Rect,
+) -> AppResult<Vec<(Rect, RgbaImage)>> {
+    let mut pieces = Vec::new();
+
+    for monitor in backend.monitors()? {
+        let bounds = Rect::of_monitor(&monitor);
+        let Some(part) = rect.intersect(&bounds) else {
+            continue;
+        };
+
+        let image = if part == bounds {
+            backend.capture_monitor(monitor.id)?
+        } else {
+            backend.capture_monitor_region(
+                monitor.id,
+                (part.x - monitor.x) as u32,
+                (part.y - monitor.y) as u32,
+                part.width,
+                part.height,
+            )?
+        };
+        pieces.push((part, image));
+    }
+
+    Ok(pieces)
+}
+
+/// Capture every monitor and compose them into one image of the whole desktop.
+///
+/// Monitors are placed according to their geometry and resampled to
+/// `target_scale` image pixels per desktop unit, or to the densest monitor
+/// when no scale is given. Gaps between monitors are left transparent.
+pub fn capture_desktop(
+    backend: &dyn CaptureBackend,
+    target_scale: Option<f32>,
+) -> AppResult<(Rect, RgbaImage)> {
+    let monitors = backend.monitors()?;
+    let bounds = Rect::bounding(&monitors).ok_or("No monitors available")?;
+
+    let pieces = capture_pieces(backend, &bounds)?;
+    let image = stitch(&bounds, &pieces, target_scale)?;
+
+    Ok((bounds, image))
+}
+
+/// Assemble captured pieces of `rect` into one image.
+///
+/// Without a `target_scale` the output density is the highest pixels-per-unit
+/// of any piece, measured from the captured images themselves since platforms
+/// disagree on whether monitor geometry is in logical or physical units.
+fn stitch(
+    rect: &Rect,
+    pieces: &[(Rect, RgbaImage)],
+    target_scale: Option<f32>,
+) -> AppResult<RgbaImage> {
+    let scale = match target_scale {
+        Some(scale) if scale.is_finite() && scale > 0.0 => scale,
+        Some(scale) => return Err(format!("Invalid target scale: {}", scale)),
+        None => pieces
+            .iter()
+            .map(|(part, image)| image.width() as f32 / part.width as f32)
+            .fold(1.0_f32, f32::max),
+    };
+    let to_px = |units: i64| (units as f32 * scale).round() as i64;
+
+    let out_width = to_px(rect.width as i64).max(1);
+    let out_height = to_px(rect.height as i64).max(1);
+    if out_width > MAX_STITCHED_DIMENSION as i64 || out_height > MAX_STITCHED_DIMENSION as i64 {
+        return Err(format!(
+            "Stitched image would be {}x{} pixels, exceeding the {} pixel limit",
+            out_width, out_height, MAX_STITCHED_DIMENSION
+        ));
+    }
+
+    let mut output = RgbaImage::new(out_width as u32, out_height as u32);
+
+    for (part, image) in pieces {
+        // Derive the size from both edges so neighbouring pieces meet without gaps
+        let left = to_px(part.x as i64 - rect.x as i64);
+        let top = to_px(part.y as i64 - rect.y as i64);
+        let width = (to_px(part.right() - rect.x as i64) - left).max(1) as u32;
+        let height = (to_px(part.bottom() - rect.y as i64) - top).max(1) as u32;
+
+        if image.dimensions() == (width, height) {
+            overlay(&mut output, image, left, top);
+        } else {
+            let scaled = resize(image, width, height, FilterType::Lanczos3);
+            overlay(&mut output, &scaled, left, top);
+        }
+    }
+
+    Ok(output)
+}
+
+/// Capture backend for the real desktop
+pub struct XcapBackend;
+
+impl XcapBackend {
+    fn find_monitor(id: u32) -> AppResult<Monitor> {
+        Monitor::all()
+            .map_err(|e| format!("Failed to get monitors: {}", e))?
+            .into_iter()
+            .find(|m| m.id().ok() == Some(id))
+            .ok_or_else(|| format!("Monitor {} not found", id))
+    }
+
+    fn find_window(id: u32) -> AppResult<Window> {
+        Window::all()
+            .map_err(|e| format!("Failed to get windows: {}", e))?
+            .into_iter()
+            .find(|w| w.id().ok() == Some(id))
+            .ok_or_else(|| format!("Window {} not found", id))
+    }
+
+    fn monitor_info(monitor: &Monitor) -> AppResult<MonitorInfo> {
+        let id = monitor
+            .id()
+            .map_err(|e| format!("Failed to get monitor id: {}", e))?;
+
+        Ok(MonitorInfo {
+            id,
+            name: monitor.name().unwrap_or_default(),
+            x: monitor
+                .x()
+                .map_err(|e| format!("Failed to get monitor x: {}", e))?,
+            y: monitor
+                .y()
+                .map_err(|e| format!("Failed to get monitor y: {}", e))?,
+            width: monitor
+                .width()
+                .map_err(|e| format!("Failed to get monitor width: {}", e))?,
+            height: monitor
+                .height()
+                .map_err(|e| format!("Failed to get monitor height: {}", e))?,
+            scale_factor: monitor
+                .scale_factor()
+                .map_err(|e| format!("Failed to get monitor scale factor: {}", e))?,
+            is_primary: monitor.is_primary().unwrap_or(false),
+        })
+    }
+
+    fn window_info(window: &Window) -> AppResult<WindowInfo> {
+        let id = window
+            .id()
+            .map_err(|e| format!("Failed to get window id: {}", e))?;
+
+        Ok(WindowInfo {
+            id,
+            app_name: window.app_name().unwrap_or_default(),
+            title: window.title().unwrap_or_default(),
+            x: window
+                .x()
+                .map_err(|e| format!("Failed to get window x: {}", e))?,
+            y: window
+                .y()
+                .map_err(|e| format!("Failed to get window y: {}", e))?,
+            width: window
+                .width()
+                .map_err(|e| format!("Failed to get window width: {}", e))?,
+            height: window
+                .height()
+                .map_err(|e| format!("Failed to get window height: {}", e))?,
+            z: window.z().unwrap_or(0),
+            is_minimized: window.is_minimized().unwrap_or(false),
+        })
+    }
+}
+
+impl CaptureBackend for XcapBackend {
+    fn monitors(&self) -> AppResult<Vec<MonitorInfo>> {
+        Monitor::all()
+            .map_err(|e| format!("Failed to get monitors: {}", e))?
+            .iter()
+            .map(Self::monitor_info)
+            .collect()
+    }
+
+    fn windows(&self) -> AppResult<Vec<WindowInfo>> {
+        // Windows that disappear while we query them are skipped
+        Ok(Window::all()
+            .map_err(|e| format!("Failed to get windows: {}", e))?
+            .iter()
+            .filter_map(|w| Self::window_info(w).ok())
+            .collect())
+    }
+
+    fn cursor(&self) -> AppResult<CursorState> {
+        cursor::current()
+    }
+
+    fn capture_monitor(&self, id: u32) -> AppResult<RgbaImage> {
+        Self::find_monitor(id)?
+            .capture_image()
+            .map_err(|e| format!("Failed to capture monitor {}: {}", id, e))
+    }
+
+    fn capture_window(&self, id: u32) -> AppResult<RgbaImage> {
+        Self::find_window(id)?
+            .capture_image()
+            .map_err(|e| format!("Failed to capture window {}: {}", id, e))
+    }
+
+    fn capture_monitor_region(
+        &self,
+        id: u32,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> AppResult<RgbaImage> {
+        Self::find_monitor(id)?
+            .capture_region(x, y, width, height)
+            .map_err(|e| format!("Failed to capture region of monitor {}: {}", id, e))
+    }
+}
+
+/// Deterministic colour of a point on the mock desktop
+///
+/// Red and green carry the low byte of x and y, blue packs the high nibbles,
+/// so every pixel of a capture can be traced back to its desktop position.
+pub fn mock_pixel(x: i32, y: i32) -> Rgba<u8> {
+    let x = x.rem_euclid(4096);
+    let y = y.rem_euclid(4096);
+    Rgba([
+        (x & 0xff) as u8,
+        (y & 0xff) as u8,
+        (((x >> 8) << 4) | (y >> 8)) as u8,
+        255,
+    ])
+}
+
+/// Synthetic backend for headless runs and tests
+///
+/// Every capture samples `mock_pixel` at the desktop position each output
+/// pixel covers, with monitor images rendered at `scale_factor`.
+#[derive(Clone, Debug)]
+pub struct MockBackend {
+    pub monitors: Vec<MonitorInfo>,
+    pub windows: Vec<WindowInfo>,
+    /// Cursor reported to overlays; `None` behaves like a platform without one
+    pub cursor: Option<CursorState>,
+}
+
+impl Default for MockBackend {
+    fn default() -> Self {
+        Self {
+            monitors: vec![
+                MonitorInfo {
+                    id: 1,
+                    name: "Mock Primary".to_string(),
+                    x: 0,
+                    y: 0,
+                    width: 320,
+                    height: 200,
+                    scale_factor: 1.0,
+                    is_primary: true,
+                },
+                MonitorInfo {
+                    id: 2,
+                    name: "Mock Secondary".to_string(),
+                    x: 320,
+                    y: 0,
+                    width: 240,
+                    height: 160,
+                    scale_factor: 1.0,
+                    is_primary: false,
+                },
+            ],
+            windows: vec![
+                WindowInfo {
+                    id: 101,
+                    app_name: "Browser".to_string(),
+                    title: "Example Domain".to_string(),
+                    x: 20,
+                    y: 30,
+                    width: 200,
+                    height: 120,
+                    z: 2,
+                    is_minimized: false,
+                },
+                WindowInfo {
+                    id: 102,
+                    app_name: "Terminal".to_string(),
+                    title: "~".to_string(),
+                    x: 340,
+                    y: 10,
+                    width: 160,
+                    height: 100,
+                    z: 1,
+                    is_minimized: false,
+                },
+                WindowInfo {
+                    id: 103,
+                    app_name: "Notes".to_string(),
+                    title: "Shopping list".to_string(),
+                    x: 0,
+                    y: 0,
+                    width: 100,
+                    height: 80,
+                    z: 0,
+                    is_minimized: true,
+                },
+            ],
+            cursor: None,
+        }
+    }
+}
+
+impl MockBackend {
+    fn render(x: i32, y: i32, width: u32, height: u32, scale: f32) -> RgbaImage {
+        let scale = if scale > 0.0 { scale } else { 1.0 };
+        let out_width = ((width as f32 * scale).round() as u32).max(1);
+        let out_height = ((height as f32 * scale).round() as u32).max(1);
+
+        RgbaImage::from_fn(out_width, out_height, |px, py| {
+            mock_pixel(
+                x + (px as f32 / scale).floor() as i32,
+                y + (py as f32 / scale).floor() as i32,
+            )
+        })
+    }
+}
+
+impl CaptureBackend for MockBackend {
+    fn monitors(&self) -> AppResult<Vec<MonitorInfo>> {
+        Ok(self.monitors.clone())
+    }
+
+    fn windows(&self) -> AppResult<Vec<WindowInfo>> {
+        Ok(self.windows.clone())
+    }
+
+    fn cursor(&self) -> AppResult<CursorState> {
+        self.cursor
+            .clone()
+            .ok_or_else(|| "Cursor is not available".to_string())
+    }
+
+    fn capture_monitor(&self, id: u32) -> AppResult<RgbaImage> {
+        let m = self
+            .monitors
+            .iter()
+            .find(|m| m.id == id)
+            .ok_or_else(|| format!("Monitor {} not found", id))?;
+
+        Ok(Self::render(m.x, m.y, m.width, m.height, m.scale_factor))
+    }
+
+    fn capture_window(&self, id: u32) -> AppResult<RgbaImage> {
+        let w = self
+            .windows
+            .iter()
+            .find(|w| w.id == id)
+            .ok_or_else(|| format!("Window {} not found", id))?;
+
+        Ok(Self::render(w.x, w.y, w.width, w.height, 1.0))
+    }
+
+    fn capture_monitor_region(
+        &self,
+        id: u32,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> AppResult<RgbaImage> {
+        let m = self
+            .monitors
+            .iter()
+            .find(|m| m.id == id)
+            .ok_or_else(|| format!("Monitor {} not found", id))?;
+
+        if x.saturating_add(width) > m.width || y.saturating_add(height) > m.height {
+            return Err(format!("Region is outside monitor {}", id));
+        }
+
+        Ok(Self::render(
+            m.x + x as i32,
+            m.y + y as i32,
+            width,
+            height,
+            m.scale_factor,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod mock {
+        use super::*;
+
+        #[test]
+        fn test_mock_pixel_is_traceable() {
+            assert_eq!(mock_pixel(0, 0), Rgba([0, 0, 0, 255]));
+            assert_eq!(mock_pixel(10, 20), Rgba([10, 20, 0, 255]));
+            assert_eq!(mock_pixel(300, 5), Rgba([44, 5, 0x10, 255]));
+            assert_ne!(mock_pixel(1, 0), mock_pixel(0, 1));
+        }
+
+        #[test]
+        fn test_primary_monitor() {
+            let backend = MockBackend::default();
+            assert_eq!(backend.primary_monitor().unwrap().id, 1);
+
+            let mut backend = MockBackend::default();
+            backend
+                .monitors
+                .iter_mut()
+                .for_each(|m| m.is_primary = false);
+            assert_eq!(backend.primary_monitor().unwrap().id, 1);
+
+            backend.monitors.clear();
+            assert!(backend.primary_monitor().is_err());
+        }
+
+        #[test]
+        fn test_capture_monitor_samples_its_desktop_area() {
+            let backend = MockBackend::default();
+            let image = backend.capture_monitor(2).unwrap();
+
+            assert_eq!(image.dimensions(), (240, 160));
+            assert_eq!(*image.get_pixel(0, 0), mock_pixel(320, 0));
+            assert_eq!(*image.get_pixel(10, 7), mock_pixel(330, 7));
+        }
+
+        #[test]
+        fn test_capture_monitor_is_deterministic() {
+            let backend = MockBackend::default();
+            assert_eq!(
+                backend.capture_monitor(1).unwrap(),
+                backend.capture_monitor(1).unwrap()
+            );
+        }
+
+        #[test]
+        fn test_capture_monitor_applies_scale_factor() {
+            let mut backend = MockBackend::default();
+            backend.monitors[1].scale_factor = 2.0;
+            let image = backend.capture_monitor(2).unwrap();
+
+            assert_eq!(image.dimensions(), (480, 320));
+            assert_eq!(*image.get_pixel(0, 0), mock_pixel(320, 0));
+            assert_eq!(*image.get_pixel(1, 1), mock_pixel(320, 0));
+            assert_eq!(*image.get_pixel(2, 2), mock_pixel(321, 1));
+        }
+
+        #[test]
+        fn test_capture_unknown_ids() {
+            let backend = MockBackend::default();
+            assert!(backend.capture_monitor(99).is_err());
+            assert!(backend.capture_window(99).is_err());
+        }
+
+        #[test]
+        fn test_capture_window() {
+            let backend = MockBackend::default();
+            let image = backend.capture_window(101).unwrap();
+
+            assert_eq!(image.dimensions(), (200, 120));
+            assert_eq!(*image.get_pixel(0, 0), mock_pixel(20, 30));
+        }
+    }
+
+    mod rect {
+        use super::*;
+
+        fn backend_with_retina_secondary() -> MockBackend {
+            let mut backend = MockBackend::default();
+            backend.monitors[1].scale_factor = 2.0;
+            backend
+        }
+
+        #[test]
+        fn test_rect_intersect() {
+            let a = Rect {
+                x: 0,
+                y: 0,
+                width: 100,
+                height: 100,
+            };
+            let b = Rect {
+                x: 50,
+                y: -20,
+                width: 100,
+                height: 40,
+            };
+
+            assert_eq!(
+                a.intersect(&b),
+                Some(Rect {
+                    x: 50,
+                    y: 0,
+                    width: 50,
+                    height: 20
+                })
+            );
+
+            let c = Rect {
+                x: 100,
+                y: 0,
+                width: 10,
+                height: 10,
+            };
+            assert_eq!(a.intersect(&c), None);
+        }
+
+        #[test]
+        fn test_capture_monitor_region() {
+            let backend = MockBackend::default();
+            let image = backend.capture_monitor_region(2, 30, 40, 50, 30).unwrap();
+
+            assert_eq!(image.dimensions(), (50, 30));
+            assert_eq!(*image.get_pixel(0, 0), mock_pixel(350, 40));
+            assert!(backend.capture_monitor_region(2, 200, 0, 50, 10).is_err());
+        }
+
+        #[test]
+        fn test_capture_rect_inside_monitor() {
+            let backend = MockBackend::default();
+            let image = backend.capture_rect(350, 40, 50, 30).unwrap();
+
+            assert_eq!(image.dimensions(), (50, 30));
+            assert_eq!(*image.get_pixel(0, 0), mock_pixel(350, 40));
+            assert_eq!(*image.get_pixel(49, 29), mock_pixel(399, 69));
+        }
+
+        #[test]
+        fn test_capture_rect_spans_monitors() {
+            let backend = MockBackend::default();
+            let image = backend.capture_rect(300, 20, 60, 40).unwrap();
+
+            assert_eq!(image.dimensions(), (60, 40));
+            for x in 0..60 {
+                assert_eq!(*image.get_pixel(x, 10), mock_pixel(300 + x as i32, 30));
+            }
+        }
+
+        #[test]
+        fn test_capture_rect_leaves_uncovered_area_transparent() {
+            let backend = MockBackend::default();
+            // The secondary monitor is only 160 units tall
+            let image = backend.capture_rect(300, 150, 40, 20).unwrap();
+
+            assert_eq!(*image.get_pixel(10, 15), mock_pixel(310, 165));
+            assert_eq!(*image.get_pixel(30, 5), mock_pixel(330, 155));
+            assert_eq!(image.get_pixel(30, 15)[3], 0);
+        }
+
+        #[test]
+        fn test_capture_rect_normalizes_to_densest_monitor() {
+            let backend = backend_with_retina_secondary();
+            let image = backend.capture_rect(300, 20, 60, 40).unwrap();
+
+            assert_eq!(image.dimensions(), (120, 80));
+            // The standard-density half is upscaled, so it only approximates
+            // the desktop pattern
+            let upscaled = image.get_pixel(20, 40);
+            let expected = mock_pixel(310, 40);
+            assert!((upscaled[0] as i32 - expected[0] as i32).abs() <= 2);
+            assert!((upscaled[1] as i32 - expected[1] as i32).abs() <= 2);
+            // The Retina half keeps its native pixels
+            assert_eq!(*image.get_pixel(40 + 40, 20), mock_pixel(340, 30));
+            assert_eq!(*image.get_pixel(40 + 41, 21), mock_pixel(340, 30));
+            assert_eq!(*image.get_pixel(40 + 42, 22), mock_pixel(341, 31));
+        }
+
+        #[test]
+        fn test_capture_rect_within_single_scaled_monitor() {
+            let backend = backend_with_retina_secondary();
+            let image = backend.capture_rect(330, 10, 20, 10).unwrap();
+
+            assert_eq!(image.dimensions(), (40, 20));
+            assert_eq!(
+                image,
+                backend.capture_monitor_region(2, 10, 10, 20, 10).unwrap()
+            );
+        }
+
+        #[test]
+        fn test_capture_rect_outside_desktop() {
+            let backend = MockBackend::default();
+            assert!(backend.capture_rect(-50, -50, 10, 10).is_err());
+            assert!(backend.capture_rect(400, 180, 10, 10).is_err());
+        }
+
+        #[test]
+        fn test_capture_rect_empty() {
+            let backend = MockBackend::default();
+            assert!(backend.capture_rect(10, 10, 0, 10).is_err());
+        }
+    }
+
+    mod desktop {
+        use super::*;
+
+        #[test]
+        fn test_desktop_covers_all_monitors() {
+            let backend = MockBackend::default();
+            let (bounds, image) = capture_desktop(&backend, None).unwrap();
+
+            assert_eq!(
+                bounds,
+                Rect {
+                    x: 0,
+                    y: 0,
+                    width: 560,
+                    height: 200
+                }
+            );
+            assert_eq!(image.dimensions(), (560, 200));
+            assert_eq!(*image.get_pixel(10, 10), mock_pixel(10, 10));
+            assert_eq!(*image.get_pixel(400, 100), mock_pixel(400, 100));
+            // Below the shorter secondary monitor
+            assert_eq!(image.get_pixel(400, 180)[3], 0);
+        }
+
+        #[test]
+        fn test_desktop_gap_between_monitors_is_transparent() {
+            let mut backend = MockBackend::default();
+            backend.monitors[1].x = 400;
+            let (bounds, image) = capture_desktop(&backend, None).unwrap();
+
+            assert_eq!(bounds.width, 640);
+            assert_eq!(image.get_pixel(350, 10)[3], 0);
+            assert_eq!(*image.get_pixel(400, 10), mock_pixel(400, 10));
+        }
+
+        #[test]
+        fn test_desktop_with_negative_origin() {
+            let mut backend = MockBackend::default();
+            backend.monitors[1].x = -240;
+            let (bounds, image) = capture_desktop(&backend, None).unwrap();
+
+            assert_eq!((bounds.x, bounds.width), (-240, 560));
+            assert_eq!(*image.get_pixel(0, 0), mock_pixel(-240, 0));
+            assert_eq!(*image.get_pixel(240, 0), mock_pixel(0, 0));
+        }
+
+        #[test]
+        fn test_desktop_defaults_to_densest_monitor() {
+            let mut backend = MockBackend::default();
+            backend.monitors[1].scale_factor = 2.0;
+            let (_, image) = capture_desktop(&backend, None).unwrap();
+
+            assert_eq!(image.dimensions(), (1120, 400));
+            assert_eq!(*image.get_pixel(640, 0), mock_pixel(320, 0));
+        }
+
+        #[test]
+        fn test_desktop_target_scale() {
+            let mut backend = MockBackend::default();
+            backend.monitors[1].scale_factor = 2.0;
+            let (_, image) = capture_desktop(&backend, Some(1.0)).unwrap();
+
+            assert_eq!(image.dimensions(), (560, 200));
+            // The standard-density monitor is copied without resampling
+            assert_eq!(*image.get_pixel(100, 50), mock_pixel(100, 50));
+
+            let (_, image) = capture_desktop(&backend, Some(0.5)).unwrap();
+            assert_eq!(image.dimensions(), (280, 100));
+        }
+
+        #[test]
+        fn test_desktop_rejects_bad_target_scale() {
+            let backend = MockBackend::default();
+
+            assert!(capture_desktop(&backend, Some(0.0)).is_err());
+            assert!(capture_desktop(&backend, Some(f32::NAN)).is_err());
+            assert!(capture_desktop(&backend, Some(1000.0)).is_err());
+        }
+
+        #[test]
+        fn test_desktop_without_monitors() {
+            let backend = MockBackend {
+                monitors: Vec::new(),
+                ..MockBackend::default()
+            };
+            assert!(capture_desktop(&backend, None).is_err());
+        }
+    }
+
+    mod target {
+        use super::*;
+
+        #[test]
+        fn test_target_deserialize() {
+            let target: CaptureTarget = serde_json::from_str(r#"{"type": "monitor"}"#).unwrap();
+            assert_eq!(target, CaptureTarget::Monitor { id: None });
+
+            let target: CaptureTarget =
+                serde_json::from_str(r#"{"type": "window", "id": 7}"#).unwrap();
+            assert_eq!(target, CaptureTarget::Window { id: 7 });
+
+            let target: CaptureTarget = serde_json::from_str(
+                r#"{"type": "rect", "x": -10, "y": 5, "width": 30, "height": 40}"#,
+            )
+            .unwrap();
+            assert_eq!(
+                target,
+                CaptureTarget::Rect {
+                    x: -10,
+                    y: 5,
+                    width: 30,
+                    height: 40
+                }
+            );
+        }
+
+        #[test]
+        fn test_target_capture() {
+            let backend = MockBackend::default();
+
+            let image = CaptureTarget::Monitor { id: None }
+                .capture(&backend)
+                .unwrap();
+            assert_eq!(image, backend.capture_monitor(1).unwrap());
+
+            let image = CaptureTarget::Window { id: 102 }.capture(&backend).unwrap();
+            assert_eq!(image, backend.capture_window(102).unwrap());
+
+            let rect = CaptureTarget::Rect {
+                x: 300,
+                y: 0,
+                width: 40,
+                height: 10,
+            };
+            assert_eq!(rect.capture(&backend).unwrap().dimensions(), (40, 10));
+        }
+    }
+}
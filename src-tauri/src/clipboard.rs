@@ -1,45 +1,948 @@
-//! Clipboard operations module
+//! Clipboard backends
+//!
+//! `ClipboardBackend` replaces the system clipboard with one or more
+//! representations of the same content, each tagged with a MIME type. An
+//! image copy can write several flavors at once (PNG data, a file URI, an
+//! HTML `<img>` fragment and a Markdown link) so each app pastes the one it
+//! understands; which flavors are written can be set per paste destination.
+//! Reading goes the other way, taking an image copied from another app as
+//! pixel data or a file reference.
+//! `SystemClipboard` uses NSPasteboard on macOS and X11 selections or Wayland
+//! data-control on Linux; `RecordingBackend` keeps what was written in memory
+//! and is selected by setting `BETTERSHOT_CLIPBOARD_BACKEND=recording`.
 
-use crate::utils::AppResult;
-use std::process::Command;
+use image::DynamicImage;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
 
-/// Copy an image file to the system clipboard using macOS native APIs
-/// This approach works with clipboard managers like Raycast
-pub fn copy_image_to_clipboard(image_path: &str) -> AppResult<()> {
-    let script = format!(
-        r#"set the clipboard to (read (POSIX file "{}") as «class PNGf»)"#,
-        image_path
-    );
+use crate::output::{ensure_decodable, OutputFormat};
+use crate::utils::{file_uri_to_path, AppResult};
 
-    let output = Command::new("osascript")
-        .arg("-e")
-        .arg(&script)
-        .output()
-        .map_err(|e| format!("Failed to execute osascript: {}", e))?;
+/// Environment variable used to select the clipboard backend
+pub const BACKEND_ENV: &str = "BETTERSHOT_CLIPBOARD_BACKEND";
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("Failed to copy image to clipboard: {}", stderr));
+/// MIME type of PNG image data
+pub const PNG_MIME: &str = "image/png";
+
+/// MIME type of TIFF image data
+pub const TIFF_MIME: &str = "image/tiff";
+
+/// MIME type of JPEG image data
+pub const JPEG_MIME: &str = "image/jpeg";
+
+/// Image types read from the clipboard, most preferred first
+const IMAGE_MIMES: [&str; 3] = [PNG_MIME, TIFF_MIME, JPEG_MIME];
+
+/// MIME type of plain text
+pub const TEXT_MIME: &str = "text/plain;charset=utf-8";
+
+/// MIME type of a list of file URIs
+pub const URI_LIST_MIME: &str = "text/uri-list";
+
+/// MIME type of an HTML fragment
+pub const HTML_MIME: &str = "text/html";
+
+/// Store key of the saved clipboard flavor preferences
+pub const CLIPBOARD_OPTIONS_SETTING: &str = "clipboardOptions";
+
+/// A representation written when copying an image
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ClipboardFlavor {
+    /// The pixels as PNG data
+    Png,
+    /// A `file://` URI to the image file
+    FileUri,
+    /// An `<img>` HTML fragment pointing at the image file
+    Html,
+    /// A Markdown image link, written as plain text
+    Markdown,
+}
+
+/// Which flavors an image copy writes
+#[derive(Deserialize, Clone, Debug, PartialEq)]
+pub struct ClipboardOptions {
+    /// Flavors for copies without a destination, or one not listed below
+    #[serde(default = "default_flavors")]
+    pub flavors: Vec<ClipboardFlavor>,
+    /// Flavors by destination name, such as "slack" or "markdown"
+    #[serde(default)]
+    pub destinations: BTreeMap<String, Vec<ClipboardFlavor>>,
+}
+
+fn default_flavors() -> Vec<ClipboardFlavor> {
+    vec![ClipboardFlavor::Png]
+}
+
+impl Default for ClipboardOptions {
+    fn default() -> Self {
+        Self {
+            flavors: default_flavors(),
+            destinations: BTreeMap::new(),
+        }
+    }
+}
+
+impl ClipboardOptions {
+    /// Flavors to write when pasting into `destination`
+    pub fn flavors_for(&self, destination: Option<&str>) -> &[ClipboardFlavor] {
+        destination
+            .and_then(|name| self.destinations.get(name))
+            .unwrap_or(&self.flavors)
+    }
+}
+
+/// One representation of the clipboard contents
+#[derive(Clone, Debug, PartialEq)]
+pub struct ClipboardEntry {
+    pub mime_type: String,
+    pub data: Vec<u8>,
+}
+
+impl ClipboardEntry {
+    pub fn new(mime_type: &str, data: Vec<u8>) -> Self {
+        Self {
+            mime_type: mime_type.to_string(),
+            data,
+        }
+    }
+}
+
+/// A place copied content goes
+pub trait ClipboardBackend: Send + Sync {
+    /// Replace the clipboard contents with all of `entries` at once
+    fn write(&self, entries: &[ClipboardEntry]) -> AppResult<()>;
+
+    /// The first of `mime_types` the clipboard holds, or `None` if it holds
+    /// none of them
+    fn read(&self, mime_types: &[&str]) -> AppResult<Option<ClipboardEntry>>;
+}
+
+/// The operating system's clipboard
+pub struct SystemClipboard;
+
+/// Pasteboard type a MIME type is stored under
+#[cfg(target_os = "macos")]
+fn pasteboard_type(mime_type: &str) -> &str {
+    match mime_type {
+        PNG_MIME => "public.png",
+        TIFF_MIME => "public.tiff",
+        JPEG_MIME => "public.jpeg",
+        TEXT_MIME => "public.utf8-plain-text",
+        URI_LIST_MIME => "public.file-url",
+        HTML_MIME => "public.html",
+        other => other,
+    }
+}
+
+#[cfg(target_os = "macos")]
+impl ClipboardBackend for SystemClipboard {
+    fn write(&self, entries: &[ClipboardEntry]) -> AppResult<()> {
+        use objc2_app_kit::NSPasteboard;
+        use objc2_foundation::{NSData, NSString};
+
+        let pasteboard = NSPasteboard::generalPasteboard();
+        pasteboard.clearContents();
+
+        for entry in entries {
+            let data = NSData::with_bytes(&entry.data);
+            let data_type = NSString::from_str(pasteboard_type(&entry.mime_type));
+            if !pasteboard.setData_forType(Some(&data), &data_type) {
+                return Err(format!("Failed to copy {} to clipboard", entry.mime_type));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn read(&self, mime_types: &[&str]) -> AppResult<Option<ClipboardEntry>> {
+        use objc2_app_kit::NSPasteboard;
+        use objc2_foundation::NSString;
+
+        let pasteboard = NSPasteboard::generalPasteboard();
+        for mime_type in mime_types {
+            let data_type = NSString::from_str(pasteboard_type(mime_type));
+            if let Some(data) = pasteboard.dataForType(&data_type) {
+                return Ok(Some(ClipboardEntry::new(mime_type, data.to_vec())));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+#[cfg(target_os = "linux")]
+impl ClipboardBackend for SystemClipboard {
+    fn write(&self, entries: &[ClipboardEntry]) -> AppResult<()> {
+        crate::linux_clipboard::write(entries)
+    }
+
+    fn read(&self, mime_types: &[&str]) -> AppResult<Option<ClipboardEntry>> {
+        crate::linux_clipboard::read(mime_types)
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+impl ClipboardBackend for SystemClipboard {
+    fn write(&self, _entries: &[ClipboardEntry]) -> AppResult<()> {
+        Err("Clipboard is not supported on this platform".to_string())
+    }
+
+    fn read(&self, _mime_types: &[&str]) -> AppResult<Option<ClipboardEntry>> {
+        Err("Clipboard is not supported on this platform".to_string())
+    }
+}
+
+/// In-memory clipboard for headless runs and tests
+#[derive(Default)]
+pub struct RecordingBackend {
+    writes: Mutex<Vec<Vec<ClipboardEntry>>>,
+}
+
+impl RecordingBackend {
+    pub const fn new() -> Self {
+        Self {
+            writes: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Every write so far, oldest first
+    #[cfg(test)]
+    pub fn writes(&self) -> Vec<Vec<ClipboardEntry>> {
+        self.writes
+            .lock()
+            .map(|writes| writes.clone())
+            .unwrap_or_default()
+    }
+}
+
+impl ClipboardBackend for RecordingBackend {
+    fn write(&self, entries: &[ClipboardEntry]) -> AppResult<()> {
+        self.writes
+            .lock()
+            .map_err(|e| format!("Failed to acquire lock: {}", e))?
+            .push(entries.to_vec());
+        Ok(())
+    }
+
+    /// Reads come from the most recent write
+    fn read(&self, mime_types: &[&str]) -> AppResult<Option<ClipboardEntry>> {
+        let writes = self
+            .writes
+            .lock()
+            .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+        let Some(entries) = writes.last() else {
+            return Ok(None);
+        };
+
+        Ok(mime_types.iter().find_map(|mime_type| {
+            entries
+                .iter()
+                .find(|entry| entry.mime_type == *mime_type)
+                .cloned()
+        }))
+    }
+}
+
+static SYSTEM_CLIPBOARD: SystemClipboard = SystemClipboard;
+static RECORDING_CLIPBOARD: RecordingBackend = RecordingBackend::new();
+
+/// Pick the backend for this process
+pub fn backend() -> &'static dyn ClipboardBackend {
+    match std::env::var(BACKEND_ENV) {
+        Ok(name) if name.eq_ignore_ascii_case("recording") => &RECORDING_CLIPBOARD,
+        _ => &SYSTEM_CLIPBOARD,
+    }
+}
+
+/// PNG bytes of an image, encoded from `pixels` when given and otherwise
+/// read from the file, converting it when saved in another format
+fn png_bytes(image_path: &str, pixels: Option<&DynamicImage>) -> AppResult<Vec<u8>> {
+    let opened;
+    let img = match pixels {
+        Some(img) => img,
+        None if OutputFormat::from_path(image_path) == Some(OutputFormat::Png) => {
+            return std::fs::read(image_path).map_err(|e| format!("Failed to read image: {}", e));
+        }
+        None => {
+            ensure_decodable(image_path)?;
+            opened = image::open(image_path).map_err(|e| format!("Failed to open image: {}", e))?;
+            &opened
+        }
+    };
+    let mut bytes = Vec::new();
+    img.write_to(
+        &mut std::io::Cursor::new(&mut bytes),
+        image::ImageFormat::Png,
+    )
+    .map_err(|e| format!("Failed to convert image for clipboard: {}", e))?;
+    Ok(bytes)
+}
+
+/// Percent-encode a path as a `file://` URI
+fn file_uri(path: &Path) -> String {
+    let mut uri = String::from("file://");
+    for &byte in path.to_string_lossy().as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                uri.push(byte as char)
+            }
+            _ => uri.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    uri
+}
+
+/// Escape text for an HTML attribute value
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Escape text for Markdown link text
+fn escape_markdown(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        if matches!(c, '\\' | '[' | ']') {
+            escaped.push('\\');
+        }
+        escaped.push(if c.is_control() { ' ' } else { c });
+    }
+    escaped
+}
+
+/// Clipboard entries for an image file, one per flavor in the given order.
+/// Pixel data and dimensions come from `pixels` when given, so files in
+/// formats that can't be decoded again can still be copied right after
+/// they are saved.
+pub fn image_entries(
+    image_path: &str,
+    pixels: Option<&DynamicImage>,
+    flavors: &[ClipboardFlavor],
+) -> AppResult<Vec<ClipboardEntry>> {
+    if flavors.is_empty() {
+        return Err("No clipboard flavors selected".to_string());
+    }
+
+    // References must keep working after the app's working directory changes
+    let path =
+        std::fs::canonicalize(image_path).map_err(|e| format!("Failed to read image: {}", e))?;
+    let uri = file_uri(&path);
+    let name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_default();
+
+    let mut entries: Vec<ClipboardEntry> = Vec::with_capacity(flavors.len());
+    for flavor in flavors {
+        let entry = match flavor {
+            ClipboardFlavor::Png => ClipboardEntry::new(PNG_MIME, png_bytes(image_path, pixels)?),
+            ClipboardFlavor::FileUri => {
+                ClipboardEntry::new(URI_LIST_MIME, uri.clone().into_bytes())
+            }
+            ClipboardFlavor::Html => {
+                let (width, height) = match pixels {
+                    Some(img) => (img.width(), img.height()),
+                    None => {
+                        ensure_decodable(image_path)?;
+                        image::image_dimensions(&path)
+                            .map_err(|e| format!("Failed to read image: {}", e))?
+                    }
+                };
+                let html = format!(
+                    "<img src=\"{}\" alt=\"{}\" width=\"{}\" height=\"{}\">",
+                    uri,
+                    escape_html(&name),
+                    width,
+                    height
+                );
+                ClipboardEntry::new(HTML_MIME, html.into_bytes())
+            }
+            ClipboardFlavor::Markdown => {
+                let markdown = format!("![{}]({})", escape_markdown(&name), uri);
+                ClipboardEntry::new(TEXT_MIME, markdown.into_bytes())
+            }
+        };
+        // A flavor listed twice would register the same type twice
+        if !entries.iter().any(|e| e.mime_type == entry.mime_type) {
+            entries.push(entry);
+        }
+    }
+
+    Ok(entries)
+}
+
+/// Copy an image file to `backend` in each of `flavors`, taking its pixels
+/// from `pixels` instead of reading the file when given
+pub fn copy_image_with(
+    backend: &dyn ClipboardBackend,
+    image_path: &str,
+    pixels: Option<&DynamicImage>,
+    flavors: &[ClipboardFlavor],
+) -> AppResult<()> {
+    let entries = image_entries(image_path, pixels, flavors)?;
+    backend
+        .write(&entries)
+        .map_err(|e| format!("Failed to copy image to clipboard: {}", e))
+}
+
+/// Copy text to `backend`
+pub fn copy_text_with(backend: &dyn ClipboardBackend, text: &str) -> AppResult<()> {
+    backend
+        .write(&[ClipboardEntry::new(TEXT_MIME, text.as_bytes().to_vec())])
+        .map_err(|e| format!("Failed to copy text to clipboard: {}", e))
+}
+
+/// Prefix of the file reference URLs Finder copies, which name a file by
+/// id rather than by path
+#[cfg(target_os = "macos")]
+const FILE_REFERENCE_PREFIX: &str = "file:///.file/";
+
+/// The path behind a Finder file reference URL, or `None` for ordinary
+/// file URLs and references that no longer resolve
+#[cfg(target_os = "macos")]
+fn file_reference_path(uri: &str) -> Option<PathBuf> {
+    use objc2_foundation::{NSString, NSURL};
+
+    if !uri.starts_with(FILE_REFERENCE_PREFIX) {
+        return None;
     }
+    let url = NSURL::URLWithString(&NSString::from_str(uri))?;
+    let path = url.filePathURL()?.path()?;
+    Some(PathBuf::from(path.to_string()))
+}
+
+#[cfg(not(target_os = "macos"))]
+fn file_reference_path(_uri: &str) -> Option<PathBuf> {
+    None
+}
+
+/// The local file a copied file reference points at, if there is one
+fn copied_file(backend: &dyn ClipboardBackend) -> AppResult<Option<PathBuf>> {
+    let Some(entry) = backend.read(&[URI_LIST_MIME])? else {
+        return Ok(None);
+    };
 
-    Ok(())
+    // Lines starting with `#` are comments; only the first file is used
+    let uris = String::from_utf8_lossy(&entry.data).into_owned();
+    let first = uris
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty() && !line.starts_with('#'));
+    match first {
+        Some(uri) if uri.starts_with("file://") => match file_reference_path(uri) {
+            Some(path) => Ok(Some(path)),
+            None => file_uri_to_path(uri).map(Some),
+        },
+        _ => Ok(None),
+    }
 }
 
-/// Copy text to the system clipboard using macOS native APIs
+/// Read an image from `backend`. A copied file is preferred over pixel
+/// data, since file managers put the file's icon alongside its reference;
+/// the pixels are used when the referenced file doesn't exist.
+pub fn read_image_with(backend: &dyn ClipboardBackend) -> AppResult<DynamicImage> {
+    if let Some(path) = copied_file(backend)?.filter(|path| path.exists()) {
+        return image::open(&path)
+            .map_err(|e| format!("Copied file {} is not an image: {}", path.display(), e));
+    }
+
+    let entry = backend
+        .read(&IMAGE_MIMES)?
+        .ok_or("Clipboard does not contain an image")?;
+    image::load_from_memory(&entry.data)
+        .map_err(|e| format!("Failed to decode clipboard image: {}", e))
+}
+
+/// Copy an image file to the system clipboard in each of `flavors`, taking
+/// its pixels from `pixels` instead of reading the file when given
+pub fn copy_image_to_clipboard(
+    image_path: &str,
+    pixels: Option<&DynamicImage>,
+    flavors: &[ClipboardFlavor],
+) -> AppResult<()> {
+    copy_image_with(backend(), image_path, pixels, flavors)
+}
+
+/// Copy text to the system clipboard
 pub fn copy_text_to_clipboard(text: &str) -> AppResult<()> {
-    let escaped_text = text.replace('"', "\\\"");
-    let script = format!(r#"set the clipboard to "{}""#, escaped_text);
+    copy_text_with(backend(), text)
+}
+
+/// Read an image from the system clipboard
+pub fn read_image_from_clipboard() -> AppResult<DynamicImage> {
+    read_image_with(backend())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{Rgba, RgbaImage};
+
+    fn temp_image(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("bettershot_clipboard_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join(name);
+        RgbaImage::from_pixel(6, 4, Rgba([200, 40, 10, 255]))
+            .save(&path)
+            .unwrap();
+        path
+    }
+
+    #[test]
+    fn test_png_is_copied_unchanged() {
+        let path = temp_image("shot.png");
+        let backend = RecordingBackend::new();
+
+        copy_image_with(
+            &backend,
+            path.to_str().unwrap(),
+            None,
+            &[ClipboardFlavor::Png],
+        )
+        .unwrap();
+
+        let writes = backend.writes();
+        assert_eq!(writes.len(), 1);
+        assert_eq!(
+            writes[0],
+            vec![ClipboardEntry::new(PNG_MIME, std::fs::read(&path).unwrap())]
+        );
+    }
+
+    #[test]
+    fn test_other_formats_are_converted_to_png() {
+        let path = temp_image("shot.tiff");
+        let backend = RecordingBackend::new();
+
+        copy_image_with(
+            &backend,
+            path.to_str().unwrap(),
+            None,
+            &[ClipboardFlavor::Png],
+        )
+        .unwrap();
+
+        let entry = &backend.writes()[0][0];
+        assert_eq!(entry.mime_type, PNG_MIME);
+        let decoded = image::load_from_memory_with_format(&entry.data, image::ImageFormat::Png)
+            .unwrap()
+            .to_rgba8();
+        assert_eq!(decoded.dimensions(), (6, 4));
+        assert_eq!(decoded.get_pixel(0, 0), &Rgba([200, 40, 10, 255]));
+    }
+
+    #[test]
+    fn test_avif_is_copied_from_its_pixels() {
+        use crate::image::{save_image, save_image_file};
+        use crate::output::OutputOptions;
+
+        let dir = temp_image("avif_source.png").parent().unwrap().join("avif");
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(6, 4, Rgba([200, 40, 10, 255])));
+        let output = OutputOptions {
+            format: OutputFormat::Avif,
+            ..OutputOptions::default()
+        };
+        let saved = save_image(&img, dir.to_str().unwrap(), "shot", &output).unwrap();
+        assert!(saved.path.ends_with(".avif"));
+
+        let backend = RecordingBackend::new();
+        let flavors = [ClipboardFlavor::Png, ClipboardFlavor::Html];
+        copy_image_with(&backend, &saved.path, Some(&img), &flavors).unwrap();
+
+        let entries = backend.writes().remove(0);
+        assert_eq!(entries[0].mime_type, PNG_MIME);
+        let decoded =
+            image::load_from_memory_with_format(&entries[0].data, image::ImageFormat::Png)
+                .unwrap()
+                .to_rgba8();
+        assert_eq!(&decoded, img.as_rgba8().unwrap());
+        assert!(String::from_utf8_lossy(&entries[1].data).ends_with("width=\"6\" height=\"4\">"));
+
+        // Without the pixels the file can't be read back, and says why
+        let error = copy_image_with(&backend, &saved.path, None, &flavors).unwrap_err();
+        assert!(error.contains("AVIF images can be saved but not opened again"));
+        let error = save_image_file(
+            &saved.path,
+            dir.to_str().unwrap(),
+            "copy",
+            &OutputOptions::default(),
+        )
+        .unwrap_err();
+        assert!(error.contains("AVIF images can be saved but not opened again"));
+    }
+
+    #[test]
+    fn test_missing_image_is_an_error() {
+        let backend = RecordingBackend::new();
+
+        assert!(copy_image_with(
+            &backend,
+            "/nonexistent/shot.png",
+            None,
+            &[ClipboardFlavor::Png]
+        )
+        .is_err());
+        assert!(backend.writes().is_empty());
+    }
+
+    /// Strings that would escape a quoted AppleScript or shell literal
+    const HOSTILE: [&str; 8] = [
+        "say \"hi\"",
+        "back\\slash\\\"",
+        "\" & (do shell script \"touch /tmp/bettershot_pwned\") & \"",
+        "'$(touch /tmp/bettershot_pwned)'",
+        "`id` ; rm -rf ~",
+        "line one\r\nend tell\n«data PNGf»",
+        "nul\0byte",
+        "\u{202e}gnp.exe 📋",
+    ];
+
+    #[test]
+    fn test_hostile_text_is_copied_verbatim() {
+        let backend = RecordingBackend::new();
+
+        for text in HOSTILE {
+            copy_text_with(&backend, text).unwrap();
+        }
+
+        let copied: Vec<_> = backend
+            .writes()
+            .into_iter()
+            .map(|entries| {
+                assert_eq!(entries.len(), 1);
+                assert_eq!(entries[0].mime_type, TEXT_MIME);
+                entries[0].data.clone()
+            })
+            .collect();
+        let expected: Vec<_> = HOSTILE
+            .iter()
+            .map(|text| text.as_bytes().to_vec())
+            .collect();
+        assert_eq!(copied, expected);
+    }
+
+    #[test]
+    fn test_hostile_file_names_are_read_as_paths() {
+        let backend = RecordingBackend::new();
+
+        // NUL can't appear in a path, and `/` would name a directory
+        for (index, name) in HOSTILE
+            .iter()
+            .filter(|name| !name.contains('\0'))
+            .enumerate()
+        {
+            let path = temp_image(&format!("{}_{}.png", index, name.replace('/', "_")));
+            copy_image_with(
+                &backend,
+                path.to_str().unwrap(),
+                None,
+                &[ClipboardFlavor::Png],
+            )
+            .unwrap();
 
-    let output = Command::new("osascript")
-        .arg("-e")
-        .arg(&script)
-        .output()
-        .map_err(|e| format!("Failed to execute osascript: {}", e))?;
+            let entry = backend.writes().pop().unwrap().remove(0);
+            assert_eq!(
+                entry,
+                ClipboardEntry::new(PNG_MIME, std::fs::read(&path).unwrap())
+            );
+        }
 
-    if !output.status.success() {
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        return Err(format!("Failed to copy text to clipboard: {}", stderr));
+        assert!(!std::path::Path::new("/tmp/bettershot_pwned").exists());
     }
 
-    Ok(())
+    #[test]
+    fn test_text_is_copied_as_utf8() {
+        let backend = RecordingBackend::new();
+
+        copy_text_with(&backend, "héllo\nwörld").unwrap();
+
+        assert_eq!(
+            backend.writes(),
+            vec![vec![ClipboardEntry::new(
+                TEXT_MIME,
+                "héllo\nwörld".as_bytes().to_vec()
+            )]]
+        );
+    }
+
+    #[test]
+    fn test_every_flavor_is_written_in_order() {
+        let path = temp_image("all flavors.png");
+        let backend = RecordingBackend::new();
+        let flavors = [
+            ClipboardFlavor::Markdown,
+            ClipboardFlavor::Png,
+            ClipboardFlavor::Html,
+            ClipboardFlavor::FileUri,
+        ];
+
+        copy_image_with(&backend, path.to_str().unwrap(), None, &flavors).unwrap();
+
+        let entries = backend.writes().remove(0);
+        let uri = file_uri(&std::fs::canonicalize(&path).unwrap());
+        assert!(uri.ends_with("/all%20flavors.png"));
+        assert_eq!(
+            entries,
+            vec![
+                ClipboardEntry::new(
+                    TEXT_MIME,
+                    format!("![all flavors.png]({})", uri).into_bytes()
+                ),
+                ClipboardEntry::new(PNG_MIME, std::fs::read(&path).unwrap()),
+                ClipboardEntry::new(
+                    HTML_MIME,
+                    format!(
+                        "<img src=\"{}\" alt=\"all flavors.png\" width=\"6\" height=\"4\">",
+                        uri
+                    )
+                    .into_bytes()
+                ),
+                ClipboardEntry::new(URI_LIST_MIME, uri.into_bytes()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_hostile_file_names_are_escaped_in_references() {
+        let path = temp_image("<b>\"x\"<b> [a](b) & 'c' $(id) `id` \\\n ü.png");
+        let backend = RecordingBackend::new();
+        let flavors = [
+            ClipboardFlavor::FileUri,
+            ClipboardFlavor::Html,
+            ClipboardFlavor::Markdown,
+        ];
+
+        copy_image_with(&backend, path.to_str().unwrap(), None, &flavors).unwrap();
+
+        let dir = file_uri(&std::fs::canonicalize(path.parent().unwrap()).unwrap());
+        let uri = format!(
+            "{}/%3Cb%3E%22x%22%3Cb%3E%20%5Ba%5D%28b%29%20%26%20%27c%27%20%24%28id%29%20%60id%60%20%5C%0A%20%C3%BC.png",
+            dir
+        );
+        assert_eq!(
+            backend.writes(),
+            vec![vec![
+                ClipboardEntry::new(URI_LIST_MIME, uri.clone().into_bytes()),
+                ClipboardEntry::new(
+                    HTML_MIME,
+                    format!(
+                        "<img src=\"{}\" alt=\"&lt;b&gt;&quot;x&quot;&lt;b&gt; [a](b) &amp; &#39;c&#39; $(id) `id` \\\n ü.png\" width=\"6\" height=\"4\">",
+                        uri
+                    )
+                    .into_bytes()
+                ),
+                ClipboardEntry::new(
+                    TEXT_MIME,
+                    format!("![<b>\"x\"<b> \\[a\\](b) & 'c' $(id) `id` \\\\  ü.png]({})", uri)
+                        .into_bytes()
+                ),
+            ]]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_and_missing_flavors() {
+        let path = temp_image("dupes.png");
+        let backend = RecordingBackend::new();
+
+        copy_image_with(
+            &backend,
+            path.to_str().unwrap(),
+            None,
+            &[ClipboardFlavor::Png, ClipboardFlavor::Png],
+        )
+        .unwrap();
+        assert_eq!(backend.writes()[0].len(), 1);
+
+        assert!(copy_image_with(&backend, path.to_str().unwrap(), None, &[]).is_err());
+        assert_eq!(backend.writes().len(), 1);
+    }
+
+    #[test]
+    fn test_flavors_for_destination() {
+        let options: ClipboardOptions = serde_json::from_value(serde_json::json!({
+            "destinations": {
+                "markdown": ["markdown"],
+                "ticket": ["png", "html", "file_uri"]
+            }
+        }))
+        .unwrap();
+
+        assert_eq!(options.flavors_for(None), &[ClipboardFlavor::Png]);
+        assert_eq!(options.flavors_for(Some("slack")), &[ClipboardFlavor::Png]);
+        assert_eq!(
+            options.flavors_for(Some("markdown")),
+            &[ClipboardFlavor::Markdown]
+        );
+        assert_eq!(
+            options.flavors_for(Some("ticket")),
+            &[
+                ClipboardFlavor::Png,
+                ClipboardFlavor::Html,
+                ClipboardFlavor::FileUri
+            ]
+        );
+    }
+
+    fn encoded(color: Rgba<u8>, format: image::ImageFormat) -> Vec<u8> {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(3, 2, color));
+        let img = if format == image::ImageFormat::Jpeg {
+            DynamicImage::ImageRgb8(img.to_rgb8())
+        } else {
+            img
+        };
+        let mut bytes = Vec::new();
+        img.write_to(&mut std::io::Cursor::new(&mut bytes), format)
+            .unwrap();
+        bytes
+    }
+
+    #[test]
+    fn test_copied_image_reads_back() {
+        let path = temp_image("round trip.png");
+        let backend = RecordingBackend::new();
+
+        copy_image_with(
+            &backend,
+            path.to_str().unwrap(),
+            None,
+            &[ClipboardFlavor::Png],
+        )
+        .unwrap();
+
+        let img = read_image_with(&backend).unwrap().to_rgba8();
+        assert_eq!(img, image::open(&path).unwrap().to_rgba8());
+    }
+
+    #[test]
+    fn test_image_data_is_read_in_preferred_formats() {
+        let backend = RecordingBackend::new();
+
+        backend
+            .write(&[
+                ClipboardEntry::new(
+                    JPEG_MIME,
+                    encoded(Rgba([0, 0, 0, 255]), image::ImageFormat::Jpeg),
+                ),
+                ClipboardEntry::new(
+                    TIFF_MIME,
+                    encoded(Rgba([0, 255, 0, 255]), image::ImageFormat::Tiff),
+                ),
+            ])
+            .unwrap();
+        let img = read_image_with(&backend).unwrap().to_rgba8();
+        assert_eq!(img.get_pixel(0, 0), &Rgba([0, 255, 0, 255]));
+
+        backend
+            .write(&[ClipboardEntry::new(
+                JPEG_MIME,
+                encoded(Rgba([255, 255, 255, 255]), image::ImageFormat::Jpeg),
+            )])
+            .unwrap();
+        let img = read_image_with(&backend).unwrap().to_rgba8();
+        assert_eq!(img.dimensions(), (3, 2));
+    }
+
+    #[test]
+    fn test_file_reference_is_preferred_over_pixels() {
+        let path = temp_image("reference.png");
+        let backend = RecordingBackend::new();
+        let uri = file_uri(&std::fs::canonicalize(&path).unwrap());
+
+        backend
+            .write(&[
+                ClipboardEntry::new(
+                    PNG_MIME,
+                    encoded(Rgba([0, 0, 255, 255]), image::ImageFormat::Png),
+                ),
+                ClipboardEntry::new(
+                    URI_LIST_MIME,
+                    format!("# copied by a file manager\r\n{}\r\n", uri).into_bytes(),
+                ),
+            ])
+            .unwrap();
+
+        let img = read_image_with(&backend).unwrap().to_rgba8();
+        assert_eq!(img.dimensions(), (6, 4));
+        assert_eq!(img.get_pixel(0, 0), &Rgba([200, 40, 10, 255]));
+    }
+
+    #[test]
+    fn test_remote_references_fall_back_to_pixels() {
+        let backend = RecordingBackend::new();
+
+        backend
+            .write(&[
+                ClipboardEntry::new(URI_LIST_MIME, b"https://example.com/a.png".to_vec()),
+                ClipboardEntry::new(
+                    PNG_MIME,
+                    encoded(Rgba([0, 0, 255, 255]), image::ImageFormat::Png),
+                ),
+            ])
+            .unwrap();
+
+        let img = read_image_with(&backend).unwrap().to_rgba8();
+        assert_eq!(img.get_pixel(0, 0), &Rgba([0, 0, 255, 255]));
+    }
+
+    #[test]
+    fn test_missing_referenced_files_fall_back_to_pixels() {
+        let backend = RecordingBackend::new();
+        let missing = std::env::temp_dir().join("bettershot_clipboard_missing.png");
+
+        for uri in [
+            file_uri(&missing),
+            "file:///.file/id=6571367.1180950".to_string(),
+        ] {
+            backend
+                .write(&[
+                    ClipboardEntry::new(URI_LIST_MIME, uri.into_bytes()),
+                    ClipboardEntry::new(
+                        PNG_MIME,
+                        encoded(Rgba([0, 0, 255, 255]), image::ImageFormat::Png),
+                    ),
+                ])
+                .unwrap();
+
+            let img = read_image_with(&backend).unwrap().to_rgba8();
+            assert_eq!(img.get_pixel(0, 0), &Rgba([0, 0, 255, 255]));
+        }
+    }
+
+    #[test]
+    fn test_clipboard_without_an_image_is_an_error() {
+        let backend = RecordingBackend::new();
+        assert!(read_image_with(&backend).is_err());
+
+        copy_text_with(&backend, "just text").unwrap();
+        assert!(read_image_with(&backend).is_err());
+
+        let dir = std::env::temp_dir().join(format!("bettershot_clipboard_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let notes = dir.join("notes.txt");
+        std::fs::write(&notes, "not an image").unwrap();
+        backend
+            .write(&[
+                ClipboardEntry::new(URI_LIST_MIME, file_uri(&notes).into_bytes()),
+                ClipboardEntry::new(
+                    PNG_MIME,
+                    encoded(Rgba([0, 0, 255, 255]), image::ImageFormat::Png),
+                ),
+            ])
+            .unwrap();
+        assert!(read_image_with(&backend).is_err());
+    }
 }
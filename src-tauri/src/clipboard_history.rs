@@ -0,0 +1,347 @@
+//! Clipboard history
+//!
+//! Every image or OCR text the app copies is remembered here, newest first,
+//! so a capture isn't lost when something else overwrites the clipboard.
+//! Only the most recent entries are kept. The history is saved as JSON in
+//! the app data folder after every change and loaded again at startup.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use crate::utils::{ensure_dir, get_timestamp, AppResult};
+
+/// Event emitted to the frontend with the new `HistoryEntry`
+pub const CLIPBOARD_HISTORY_EVENT: &str = "clipboard-history-added";
+
+/// Name of the history file in the app data folder
+pub const HISTORY_FILE: &str = "clipboard-history.json";
+
+/// Entries kept before the oldest is dropped
+const MAX_ENTRIES: usize = 25;
+
+/// What was placed on the clipboard
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum HistoryContent {
+    Image { path: String },
+    Text { text: String },
+}
+
+/// One remembered copy
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct HistoryEntry {
+    pub id: u64,
+    pub content: HistoryContent,
+    /// When it was copied, in milliseconds since the Unix epoch
+    pub timestamp: u64,
+    /// Command that copied it, such as "capture_once"
+    pub source: String,
+}
+
+struct History {
+    /// Where the history is saved, nothing is saved when `None`
+    file: Option<PathBuf>,
+    next_id: u64,
+    /// Newest first
+    entries: VecDeque<HistoryEntry>,
+}
+
+/// Remembers recent copies
+pub struct ClipboardHistory {
+    history: Mutex<History>,
+}
+
+/// The app-wide clipboard history
+pub static CLIPBOARD_HISTORY: ClipboardHistory = ClipboardHistory::new();
+
+impl History {
+    fn save(&self) -> AppResult<()> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        if let Some(dir) = file.parent() {
+            ensure_dir(&dir.to_path_buf())?;
+        }
+
+        let data = serde_json::to_string_pretty(&self.entries)
+            .map_err(|e| format!("Failed to serialize clipboard history: {}", e))?;
+        std::fs::write(file, data).map_err(|e| format!("Failed to save clipboard history: {}", e))
+    }
+}
+
+impl ClipboardHistory {
+    pub const fn new() -> Self {
+        Self {
+            history: Mutex::new(History {
+                file: None,
+                next_id: 1,
+                entries: VecDeque::new(),
+            }),
+        }
+    }
+
+    fn lock(&self) -> AppResult<std::sync::MutexGuard<'_, History>> {
+        self.history
+            .lock()
+            .map_err(|e| format!("Failed to acquire lock: {}", e))
+    }
+
+    /// Load the history saved in `file` and save future changes there.
+    /// Images deleted since it was saved are dropped. A missing file starts
+    /// an empty history; an unreadable one is reported and replaced.
+    pub fn load(&self, file: &Path) -> AppResult<()> {
+        let mut history = self.lock()?;
+        history.file = Some(file.to_path_buf());
+        history.entries.clear();
+
+        if !file.exists() {
+            return Ok(());
+        }
+
+        let data = std::fs::read_to_string(file)
+            .map_err(|e| format!("Failed to read clipboard history: {}", e))?;
+        let entries: VecDeque<HistoryEntry> = serde_json::from_str(&data)
+            .map_err(|e| format!("Failed to parse clipboard history: {}", e))?;
+
+        history.entries = entries
+            .into_iter()
+            .filter(|entry| match &entry.content {
+                HistoryContent::Image { path } => Path::new(path).exists(),
+                HistoryContent::Text { .. } => true,
+            })
+            .take(MAX_ENTRIES)
+            .collect();
+        history.next_id = history.entries.iter().map(|e| e.id).max().unwrap_or(0) + 1;
+        Ok(())
+    }
+
+    /// Remember a copy as the newest entry. Copying the same content again
+    /// moves it to the top instead of adding a duplicate.
+    pub fn record(&self, content: HistoryContent, source: &str) -> AppResult<HistoryEntry> {
+        let mut history = self.lock()?;
+
+        let entry = HistoryEntry {
+            id: history.next_id,
+            content,
+            timestamp: get_timestamp()?,
+            source: source.to_string(),
+        };
+        history.next_id += 1;
+
+        history.entries.retain(|e| e.content != entry.content);
+        history.entries.push_front(entry.clone());
+        history.entries.truncate(MAX_ENTRIES);
+
+        history.save()?;
+        Ok(entry)
+    }
+
+    /// All entries, newest first
+    pub fn list(&self) -> AppResult<Vec<HistoryEntry>> {
+        Ok(self.lock()?.entries.iter().cloned().collect())
+    }
+
+    /// The entry with `id`
+    pub fn get(&self, id: u64) -> AppResult<HistoryEntry> {
+        self.lock()?
+            .entries
+            .iter()
+            .find(|e| e.id == id)
+            .cloned()
+            .ok_or_else(|| format!("Clipboard history entry {} not found", id))
+    }
+
+    /// Forget one entry, returning whether it existed
+    pub fn remove(&self, id: u64) -> AppResult<bool> {
+        let mut history = self.lock()?;
+        let before = history.entries.len();
+        history.entries.retain(|e| e.id != id);
+        if history.entries.len() == before {
+            return Ok(false);
+        }
+
+        history.save()?;
+        Ok(true)
+    }
+
+    /// Forget every entry
+    pub fn clear(&self) -> AppResult<()> {
+        let mut history = self.lock()?;
+        history.entries.clear();
+        history.save()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "bettershot_clipboard_history_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn text(text: &str) -> HistoryContent {
+        HistoryContent::Text {
+            text: text.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_entries_are_listed_newest_first() {
+        let history = ClipboardHistory::new();
+
+        history
+            .record(text("first"), "native_capture_ocr_region")
+            .unwrap();
+        let second = history.record(text("second"), "capture_once").unwrap();
+
+        let entries = history.list().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], second);
+        assert_eq!(entries[1].content, text("first"));
+        assert_eq!(entries[1].source, "native_capture_ocr_region");
+        assert!(entries[0].id > entries[1].id);
+    }
+
+    #[test]
+    fn test_oldest_entries_are_dropped() {
+        let history = ClipboardHistory::new();
+
+        for i in 0..MAX_ENTRIES + 3 {
+            history.record(text(&i.to_string()), "test").unwrap();
+        }
+
+        let entries = history.list().unwrap();
+        assert_eq!(entries.len(), MAX_ENTRIES);
+        assert_eq!(entries[0].content, text(&(MAX_ENTRIES + 2).to_string()));
+        assert_eq!(entries[MAX_ENTRIES - 1].content, text("3"));
+    }
+
+    #[test]
+    fn test_copying_again_moves_entry_to_top() {
+        let history = ClipboardHistory::new();
+
+        history.record(text("a"), "test").unwrap();
+        history.record(text("b"), "test").unwrap();
+        let again = history.record(text("a"), "test").unwrap();
+
+        let entries = history.list().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0], again);
+        assert_eq!(history.get(again.id).unwrap(), again);
+    }
+
+    #[test]
+    fn test_remove_and_clear() {
+        let history = ClipboardHistory::new();
+        let kept = history.record(text("kept"), "test").unwrap();
+        let removed = history.record(text("removed"), "test").unwrap();
+
+        assert!(history.remove(removed.id).unwrap());
+        assert!(!history.remove(removed.id).unwrap());
+        assert!(history.get(removed.id).is_err());
+        assert_eq!(history.list().unwrap(), vec![kept]);
+
+        history.clear().unwrap();
+        assert!(history.list().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_history_survives_restart() {
+        let dir = temp_dir("restart");
+        let file = dir.join("data").join(HISTORY_FILE);
+        let image = dir.join("shot.png");
+        std::fs::write(&image, b"png").unwrap();
+
+        let history = ClipboardHistory::new();
+        history.load(&file).unwrap();
+        history
+            .record(text("ocr"), "native_capture_ocr_region")
+            .unwrap();
+        history
+            .record(
+                HistoryContent::Image {
+                    path: image.to_string_lossy().into_owned(),
+                },
+                "capture_once",
+            )
+            .unwrap();
+
+        let restarted = ClipboardHistory::new();
+        restarted.load(&file).unwrap();
+        assert_eq!(restarted.list().unwrap(), history.list().unwrap());
+
+        let newest = restarted.record(text("after restart"), "test").unwrap();
+        assert!(history.list().unwrap().iter().all(|e| e.id < newest.id));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_deleted_images_are_dropped_on_load() {
+        let dir = temp_dir("deleted");
+        let file = dir.join(HISTORY_FILE);
+        let image = dir.join("gone.png");
+        std::fs::write(&image, b"png").unwrap();
+
+        let history = ClipboardHistory::new();
+        history.load(&file).unwrap();
+        history.record(text("ocr"), "test").unwrap();
+        history
+            .record(
+                HistoryContent::Image {
+                    path: image.to_string_lossy().into_owned(),
+                },
+                "test",
+            )
+            .unwrap();
+        std::fs::remove_file(&image).unwrap();
+
+        let restarted = ClipboardHistory::new();
+        restarted.load(&file).unwrap();
+        let entries = restarted.list().unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].content, text("ocr"));
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_corrupt_history_is_reported() {
+        let dir = temp_dir("corrupt");
+        let file = dir.join(HISTORY_FILE);
+        std::fs::write(&file, "not json").unwrap();
+
+        let history = ClipboardHistory::new();
+        assert!(history.load(&file).is_err());
+        assert!(history.list().unwrap().is_empty());
+
+        history.record(text("fresh"), "test").unwrap();
+        let restarted = ClipboardHistory::new();
+        restarted.load(&file).unwrap();
+        assert_eq!(restarted.list().unwrap().len(), 1);
+
+        let _ = std::fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn test_content_is_tagged_by_type() {
+        let json = serde_json::to_value(HistoryContent::Image {
+            path: "/tmp/shot.png".to_string(),
+        })
+        .unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({ "type": "image", "path": "/tmp/shot.png" })
+        );
+    }
+}
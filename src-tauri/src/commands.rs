@@ -1,24 +1,194 @@
 //! Tauri commands module
 
+use image::DynamicImage;
 use std::path::PathBuf;
-use std::process::{Command, Stdio};
+use std::process::Command;
+#[cfg(not(target_os = "linux"))]
+use std::process::Stdio;
 use std::sync::Mutex;
-use tauri::{AppHandle, Manager};
+use tauri::{AppHandle, Emitter};
+use tauri_plugin_store::StoreExt;
+#[cfg(target_os = "macos")]
+use tauri::Manager;
 
 #[cfg(target_os = "macos")]
 use objc2::msg_send;
+#[cfg(target_os = "macos")]
 use objc2_app_kit::NSWindow;
 
-use crate::clipboard::{copy_image_to_clipboard, copy_text_to_clipboard};
-use crate::image::{copy_screenshot_to_dir, crop_image, render_image_with_effects, save_base64_image, CropRegion, RenderSettings};
+use crate::animation::{export_animation, frames_from_paths, AnimationFormat};
+use crate::annotations::{render_annotations, Annotation};
+use crate::capture::{self, WindowInfo};
+use crate::clipboard::{
+    self, copy_image_to_clipboard, copy_text_to_clipboard, read_image_from_clipboard,
+    ClipboardFlavor, ClipboardOptions, CLIPBOARD_OPTIONS_SETTING,
+};
+use crate::clipboard_history::{
+    HistoryContent, HistoryEntry, CLIPBOARD_HISTORY, CLIPBOARD_HISTORY_EVENT,
+};
+use crate::cursor::{with_cursor, CursorOptions, HIGHLIGHT_CLICKS_SETTING, SHOW_CURSOR_SETTING};
+#[cfg(target_os = "linux")]
+use crate::linux_capture;
+use crate::image::{
+    crop_image, load_base64_image, redact_image,
+    render_image_with_effects, save_base64_image, save_image, save_image_file, stitch_scrolling_capture as stitch_frames,
+    CropRegion, RedactionRegion, RenderSettings, IMAGE_SAVED_EVENT,
+};
 use crate::ocr::recognize_text_from_image;
+use crate::output::{OutputOptions, OUTPUT_OPTIONS_SETTING};
+use crate::recording::{RecordingConfig, RecordingStatus, RECORDER, RECORDING_STATUS_EVENT};
+use crate::render_cache::{RenderedImage, RENDER_CACHE};
 use crate::screenshot::{
-    capture_all_monitors as capture_monitors, capture_primary_monitor, MonitorShot,
+    capture_all_monitors as capture_monitors, capture_all_monitors_stitched as capture_stitched,
+    capture_desktop_region as capture_desktop_rect, capture_monitor as capture_single_monitor,
+    capture_primary_monitor, capture_window as capture_matching_window,
+    list_windows as list_capturable_windows, MonitorShot, RegionShot, WindowQuery, WindowShot,
 };
-use crate::utils::{generate_filename, get_desktop_path};
+use crate::sequence::{
+    change_speed, crop_sequence, delete_frames, load_sequence, render_sequence, trim_sequence,
+    FrameSequence,
+};
+use crate::session::{SessionConfig, SessionProgress, CAPTURE_SESSIONS, SESSION_PROGRESS_EVENT};
+use crate::timer::{CountdownTick, CAPTURE_TIMER, COUNTDOWN_EVENT};
+#[cfg(not(target_os = "linux"))]
+use crate::utils::generate_filename;
+use crate::utils::get_desktop_path;
 
 static SCREENCAPTURE_LOCK: Mutex<()> = Mutex::new(());
 
+/// Preferences file shared with the frontend
+const SETTINGS_STORE: &str = "settings.json";
+
+/// Resolve per-capture cursor choices, falling back to the saved preferences
+fn cursor_options(
+    app_handle: &AppHandle,
+    show_cursor: Option<bool>,
+    highlight_clicks: Option<bool>,
+) -> CursorOptions {
+    let store = app_handle.store(SETTINGS_STORE).ok();
+    let saved = |key: &str| {
+        store
+            .as_ref()
+            .and_then(|store| store.get(key))
+            .and_then(|value| value.as_bool())
+            .unwrap_or(false)
+    };
+
+    CursorOptions {
+        show_cursor: show_cursor.unwrap_or_else(|| saved(SHOW_CURSOR_SETTING)),
+        highlight_clicks: highlight_clicks.unwrap_or_else(|| saved(HIGHLIGHT_CLICKS_SETTING)),
+    }
+}
+
+/// Resolve per-call output options, falling back to the saved preferences
+fn output_options(app_handle: &AppHandle, output: Option<OutputOptions>) -> OutputOptions {
+    output.unwrap_or_else(|| {
+        app_handle
+            .store(SETTINGS_STORE)
+            .ok()
+            .and_then(|store| store.get(OUTPUT_OPTIONS_SETTING))
+            .and_then(|value| serde_json::from_value(value).ok())
+            .unwrap_or_default()
+    })
+}
+
+/// Clipboard flavors for a paste destination, from the saved preferences
+fn clipboard_flavors(app_handle: &AppHandle, destination: Option<&str>) -> Vec<ClipboardFlavor> {
+    let options: ClipboardOptions = app_handle
+        .store(SETTINGS_STORE)
+        .ok()
+        .and_then(|store| store.get(CLIPBOARD_OPTIONS_SETTING))
+        .and_then(|value| serde_json::from_value(value).ok())
+        .unwrap_or_default();
+    options.flavors_for(destination).to_vec()
+}
+
+/// Remember a copy in the clipboard history. The copy itself already
+/// succeeded, so failing to record it is only logged.
+fn remember_copy(app_handle: &AppHandle, content: HistoryContent, source: &str) {
+    match CLIPBOARD_HISTORY.record(content, source) {
+        Ok(entry) => {
+            let _ = app_handle.emit(CLIPBOARD_HISTORY_EVENT, &entry);
+        }
+        Err(e) => eprintln!("Failed to update clipboard history: {}", e),
+    }
+}
+
+/// Copy an image in the flavors saved for `destination` and remember it.
+/// `pixels` stands in for the file's contents when given.
+fn copy_image(
+    app_handle: &AppHandle,
+    path: &str,
+    pixels: Option<&DynamicImage>,
+    destination: Option<&str>,
+    source: &str,
+) -> Result<(), String> {
+    let flavors = clipboard_flavors(app_handle, destination);
+    copy_image_to_clipboard(path, pixels, &flavors)?;
+    remember_copy(
+        app_handle,
+        HistoryContent::Image {
+            path: path.to_string(),
+        },
+        source,
+    );
+    Ok(())
+}
+
+/// Pixels to copy along with an image just saved as `output`. Files in a
+/// format that can't be decoded again are copied from the lossless source
+/// they were saved from.
+fn pixels_for_copy(
+    output: &OutputOptions,
+    source: impl FnOnce() -> Result<DynamicImage, String>,
+) -> Result<Option<DynamicImage>, String> {
+    if output.format.can_decode() {
+        Ok(None)
+    } else {
+        source().map(Some)
+    }
+}
+
+/// Capture backend for a screenshot, drawing the cursor if requested.
+/// Click highlights only make sense in recordings, so they are never drawn.
+fn screenshot_backend(
+    app_handle: &AppHandle,
+    show_cursor: Option<bool>,
+) -> Box<dyn capture::CaptureBackend> {
+    with_cursor(
+        capture::backend(),
+        cursor_options(app_handle, show_cursor, Some(false)),
+    )
+}
+
+/// Wait out an optional capture delay, emitting countdown ticks to the frontend.
+/// The countdown sleeps, so it runs on a blocking thread rather than tying up
+/// an async runtime worker.
+async fn wait_for_capture_delay(
+    app_handle: &AppHandle,
+    delay_seconds: Option<u32>,
+) -> Result<(), String> {
+    let seconds = match delay_seconds {
+        Some(seconds) if seconds > 0 => seconds,
+        _ => return Ok(()),
+    };
+
+    let app_handle = app_handle.clone();
+    tauri::async_runtime::spawn_blocking(move || {
+        CAPTURE_TIMER.run(seconds, |remaining| {
+            let _ = app_handle.emit(COUNTDOWN_EVENT, CountdownTick { remaining });
+        })
+    })
+    .await
+    .map_err(|e| format!("Capture countdown failed: {}", e))?
+}
+
+/// Cancel a running capture countdown, returning whether one was running
+#[tauri::command]
+pub async fn cancel_capture_timer() -> Result<bool, String> {
+    Ok(CAPTURE_TIMER.cancel())
+}
+
 #[tauri::command]
 pub async fn move_window_to_active_space(app_handle: AppHandle) -> Result<(), String> {
     #[cfg(target_os = "macos")]
@@ -42,40 +212,335 @@ pub async fn move_window_to_active_space(app_handle: AppHandle) -> Result<(), St
             })
             .map_err(|e| e.to_string())?;
     }
+
+    #[cfg(not(target_os = "macos"))]
+    let _ = app_handle;
+
     Ok(())
 }
 
+/// Copy an image file in the flavors saved for `destination`
 #[tauri::command]
-pub async fn copy_image_file_to_clipboard(path: String) -> Result<(), String> {
-    copy_image_to_clipboard(&path).map_err(|e| e.to_string())
+pub async fn copy_image_file_to_clipboard(
+    app_handle: AppHandle,
+    path: String,
+    destination: Option<String>,
+) -> Result<(), String> {
+    copy_image(
+        &app_handle,
+        &path,
+        None,
+        destination.as_deref(),
+        "copy_image_file_to_clipboard",
+    )
 }
 
-/// Quick capture of primary monitor
+/// Recent copies, newest first
+#[tauri::command]
+pub async fn list_clipboard_history() -> Result<Vec<HistoryEntry>, String> {
+    CLIPBOARD_HISTORY.list()
+}
+
+/// Put a history entry back on the clipboard, moving it to the top
+#[tauri::command]
+pub async fn recopy_clipboard_history_entry(
+    app_handle: AppHandle,
+    id: u64,
+    destination: Option<String>,
+) -> Result<(), String> {
+    let entry = CLIPBOARD_HISTORY.get(id)?;
+    match entry.content {
+        HistoryContent::Image { path } => {
+            copy_image(&app_handle, &path, None, destination.as_deref(), &entry.source)
+        }
+        HistoryContent::Text { text } => {
+            copy_text_to_clipboard(&text)?;
+            remember_copy(&app_handle, HistoryContent::Text { text }, &entry.source);
+            Ok(())
+        }
+    }
+}
+
+/// Forget one history entry, returning whether it existed
+#[tauri::command]
+pub async fn remove_clipboard_history_entry(id: u64) -> Result<bool, String> {
+    CLIPBOARD_HISTORY.remove(id)
+}
+
+/// Forget every history entry
+#[tauri::command]
+pub async fn clear_clipboard_history() -> Result<(), String> {
+    CLIPBOARD_HISTORY.clear()
+}
+
+/// Save the image on the clipboard to the temp dir as PNG, so images copied
+/// from other apps can be edited like a fresh capture
+#[tauri::command]
+pub async fn paste_image_from_clipboard() -> Result<String, String> {
+    let img = read_image_from_clipboard()?;
+
+    let temp_dir = std::env::temp_dir();
+    let temp_dir = temp_dir.canonicalize().unwrap_or(temp_dir);
+    let saved = save_image(
+        &img,
+        &temp_dir.to_string_lossy(),
+        "clipboard",
+        &OutputOptions::default(),
+    )?;
+    Ok(saved.path)
+}
+
+/// Quick capture of primary monitor, emitting the saved file's size report
 #[tauri::command]
 pub async fn capture_once(
     app_handle: AppHandle,
     save_dir: String,
     copy_to_clip: bool,
+    output: Option<OutputOptions>,
+    destination: Option<String>,
+    show_cursor: Option<bool>,
+    delay_seconds: Option<u32>,
 ) -> Result<String, String> {
-    let screenshot_path = capture_primary_monitor(app_handle).await?;
-    let screenshot_path_str = screenshot_path.to_string_lossy().to_string();
-
-    let saved_path = copy_screenshot_to_dir(&screenshot_path_str, &save_dir)?;
+    wait_for_capture_delay(&app_handle, delay_seconds).await?;
+
+    let output = output_options(&app_handle, output);
+    let flavors = copy_to_clip.then(|| clipboard_flavors(&app_handle, destination.as_deref()));
+    let clipboard = flavors
+        .as_deref()
+        .map(|flavors| (clipboard::backend(), flavors));
+
+    let saved = capture_primary_monitor(
+        screenshot_backend(&app_handle, show_cursor).as_ref(),
+        &save_dir,
+        &output,
+        clipboard,
+    )?;
+    let _ = app_handle.emit(IMAGE_SAVED_EVENT, &saved);
 
     if copy_to_clip {
-        copy_image_to_clipboard(&saved_path)?;
+        remember_copy(
+            &app_handle,
+            HistoryContent::Image {
+                path: saved.path.clone(),
+            },
+            "capture_once",
+        );
     }
 
-    Ok(saved_path)
+    Ok(saved.path)
 }
 
 /// Capture all monitors with geometry info
 #[tauri::command]
 pub async fn capture_all_monitors(
-    _app_handle: AppHandle,
+    app_handle: AppHandle,
     save_dir: String,
+    show_cursor: Option<bool>,
 ) -> Result<Vec<MonitorShot>, String> {
-    capture_monitors(&save_dir)
+    capture_monitors(screenshot_backend(&app_handle, show_cursor).as_ref(), &save_dir)
+}
+
+/// Capture all monitors into one image of the whole desktop, resampled to
+/// `target_scale` pixels per desktop unit (the densest monitor's by default)
+#[tauri::command]
+pub async fn capture_all_monitors_stitched(
+    app_handle: AppHandle,
+    save_dir: String,
+    target_scale: Option<f32>,
+    show_cursor: Option<bool>,
+) -> Result<RegionShot, String> {
+    let backend = screenshot_backend(&app_handle, show_cursor);
+    capture_stitched(backend.as_ref(), &save_dir, target_scale)
+}
+
+/// Capture a single monitor by id (the primary monitor when no id is given)
+#[tauri::command]
+pub async fn capture_monitor(
+    app_handle: AppHandle,
+    save_dir: String,
+    monitor_id: Option<u32>,
+    delay_seconds: Option<u32>,
+    show_cursor: Option<bool>,
+) -> Result<MonitorShot, String> {
+    wait_for_capture_delay(&app_handle, delay_seconds).await?;
+    let backend = screenshot_backend(&app_handle, show_cursor);
+    capture_single_monitor(backend.as_ref(), &save_dir, monitor_id)
+}
+
+/// Capture a rectangle in global desktop coordinates, stitching across monitors
+#[tauri::command]
+#[allow(clippy::too_many_arguments)]
+pub async fn capture_desktop_region(
+    app_handle: AppHandle,
+    save_dir: String,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+    delay_seconds: Option<u32>,
+    show_cursor: Option<bool>,
+) -> Result<RegionShot, String> {
+    wait_for_capture_delay(&app_handle, delay_seconds).await?;
+    let backend = screenshot_backend(&app_handle, show_cursor);
+    capture_desktop_rect(backend.as_ref(), &save_dir, x, y, width, height)
+}
+
+/// List capturable windows, frontmost first
+#[tauri::command]
+pub async fn list_windows() -> Result<Vec<WindowInfo>, String> {
+    list_capturable_windows(capture::backend().as_ref())
+}
+
+/// Capture a window by id, or the frontmost window whose app name and/or title
+/// contain the given text
+#[tauri::command]
+pub async fn capture_window(
+    app_handle: AppHandle,
+    save_dir: String,
+    window_id: Option<u32>,
+    app_name: Option<String>,
+    title: Option<String>,
+    delay_seconds: Option<u32>,
+    show_cursor: Option<bool>,
+) -> Result<WindowShot, String> {
+    wait_for_capture_delay(&app_handle, delay_seconds).await?;
+    let query = WindowQuery {
+        id: window_id,
+        app_name,
+        title,
+    };
+    let backend = screenshot_backend(&app_handle, show_cursor);
+    capture_matching_window(backend.as_ref(), &save_dir, &query)
+}
+
+/// Start capturing a monitor, window or rectangle at a fixed interval in the
+/// background, emitting progress after every frame. Returns the session id.
+#[tauri::command]
+pub async fn start_capture_session(
+    app_handle: AppHandle,
+    config: SessionConfig,
+) -> Result<u32, String> {
+    CAPTURE_SESSIONS.start(capture::backend(), config, move |progress| {
+        let _ = app_handle.emit(SESSION_PROGRESS_EVENT, progress);
+    })
+}
+
+/// Stop a capture session, returning its final progress
+#[tauri::command]
+pub async fn stop_capture_session(session_id: u32) -> Result<SessionProgress, String> {
+    CAPTURE_SESSIONS.stop(session_id)
+}
+
+/// List the ids of running capture sessions
+#[tauri::command]
+pub async fn list_capture_sessions() -> Result<Vec<u32>, String> {
+    CAPTURE_SESSIONS.active()
+}
+
+/// Start recording a monitor, window or rectangle to an animated GIF, APNG
+/// or WebP, emitting status updates while it runs
+#[tauri::command]
+pub async fn start_recording(
+    app_handle: AppHandle,
+    config: RecordingConfig,
+) -> Result<(), String> {
+    let options = cursor_options(&app_handle, config.show_cursor, config.highlight_clicks);
+    let backend = with_cursor(capture::backend(), options);
+    RECORDER.start(backend, config, move |status| {
+        let _ = app_handle.emit(RECORDING_STATUS_EVENT, status);
+    })
+}
+
+/// Pause the current recording
+#[tauri::command]
+pub async fn pause_recording() -> Result<(), String> {
+    RECORDER.pause()
+}
+
+/// Resume a paused recording
+#[tauri::command]
+pub async fn resume_recording() -> Result<(), String> {
+    RECORDER.resume()
+}
+
+/// Stop the current recording and return the saved animation's status
+#[tauri::command]
+pub async fn stop_recording() -> Result<RecordingStatus, String> {
+    RECORDER.stop()
+}
+
+/// Export a sequence of frame images as an animated GIF, APNG or WebP.
+/// `delays_ms` holds one delay per frame or a single delay for all frames.
+#[tauri::command]
+pub async fn export_frame_sequence(
+    frame_paths: Vec<String>,
+    delays_ms: Vec<u32>,
+    format: AnimationFormat,
+    save_dir: String,
+) -> Result<String, String> {
+    let frames = frames_from_paths(&frame_paths, &delays_ms)?;
+    export_animation(&frames, format, &save_dir)
+}
+
+/// Load a folder of frames as a sequence, in playback order
+#[tauri::command]
+pub async fn load_frame_sequence(sequence_dir: String) -> Result<FrameSequence, String> {
+    load_sequence(&sequence_dir)
+}
+
+/// Keep frames from `start` up to but not including `end`, deleting the rest
+#[tauri::command]
+pub async fn trim_frame_sequence(
+    sequence_dir: String,
+    start: usize,
+    end: usize,
+) -> Result<FrameSequence, String> {
+    trim_sequence(&sequence_dir, start, end)
+}
+
+/// Delete individual frames from a sequence
+#[tauri::command]
+pub async fn delete_sequence_frames(
+    sequence_dir: String,
+    indices: Vec<usize>,
+) -> Result<FrameSequence, String> {
+    delete_frames(&sequence_dir, &indices)
+}
+
+/// Change a sequence's playback speed; a factor of 2 plays twice as fast
+#[tauri::command]
+pub async fn set_frame_sequence_speed(
+    sequence_dir: String,
+    factor: f64,
+) -> Result<FrameSequence, String> {
+    change_speed(&sequence_dir, factor)
+}
+
+/// Crop every frame of a sequence to the same rectangle
+#[tauri::command]
+pub async fn crop_frame_sequence(
+    sequence_dir: String,
+    x: u32,
+    y: u32,
+    width: u32,
+    height: u32,
+) -> Result<FrameSequence, String> {
+    let region = CropRegion {
+        x,
+        y,
+        width,
+        height,
+    };
+    crop_sequence(&sequence_dir, region)
+}
+
+/// Apply background, padding and effects to every frame of a sequence
+#[tauri::command]
+pub async fn render_frame_sequence(
+    sequence_dir: String,
+    settings: RenderSettings,
+) -> Result<FrameSequence, String> {
+    render_sequence(&sequence_dir, &settings)
 }
 
 /// Crop a region from a screenshot
@@ -106,20 +571,128 @@ pub async fn render_image_with_effects_rust(
     render_image_with_effects(&image_path, settings)
 }
 
-/// Save an edited image from base64 data
+/// Render image with effects to a temp file and return a handle to it,
+/// avoiding a base64 round trip for large previews
+#[tauri::command]
+pub async fn render_image_with_effects_to_file(
+    image_path: String,
+    settings: RenderSettings,
+) -> Result<RenderedImage, String> {
+    RENDER_CACHE.render(&image_path, &settings)
+}
+
+/// Delete a rendered file once the frontend no longer shows it
+#[tauri::command]
+pub async fn release_rendered_image(handle: u32) -> Result<bool, String> {
+    RENDER_CACHE.release(handle)
+}
+
+/// Flatten editor annotations onto an image and save the result, emitting
+/// its size before and after optimization
+#[tauri::command]
+pub async fn render_annotations_rust(
+    app_handle: AppHandle,
+    image_path: String,
+    annotations: Vec<Annotation>,
+    save_dir: String,
+    output: Option<OutputOptions>,
+) -> Result<String, String> {
+    let output = output_options(&app_handle, output);
+    let saved = render_annotations(&image_path, &annotations, &save_dir, &output)?;
+    let _ = app_handle.emit(IMAGE_SAVED_EVENT, &saved);
+    Ok(saved.path)
+}
+
+/// Irreversibly blur, pixelate or fill regions of an image and save the
+/// result, emitting its size before and after optimization
+#[tauri::command]
+pub async fn redact_image_regions(
+    app_handle: AppHandle,
+    image_path: String,
+    regions: Vec<RedactionRegion>,
+    save_dir: String,
+    output: Option<OutputOptions>,
+) -> Result<String, String> {
+    let output = output_options(&app_handle, output);
+    let saved = redact_image(&image_path, &regions, &save_dir, &output)?;
+    let _ = app_handle.emit(IMAGE_SAVED_EVENT, &saved);
+    Ok(saved.path)
+}
+
+/// Stitch overlapping frames of a region captured while scrolling into one
+/// tall image and save it, emitting its size before and after optimization
+#[tauri::command]
+pub async fn stitch_scrolling_capture(
+    app_handle: AppHandle,
+    frame_paths: Vec<String>,
+    save_dir: String,
+    output: Option<OutputOptions>,
+) -> Result<String, String> {
+    let output = output_options(&app_handle, output);
+    let saved = stitch_frames(&frame_paths, &save_dir, &output)?;
+    let _ = app_handle.emit(IMAGE_SAVED_EVENT, &saved);
+    Ok(saved.path)
+}
+
+/// Save an edited image from base64 data in the output format, emitting its
+/// size before and after optimization
 #[tauri::command]
 pub async fn save_edited_image(
+    app_handle: AppHandle,
     image_data: String,
     save_dir: String,
     copy_to_clip: bool,
+    output: Option<OutputOptions>,
+    destination: Option<String>,
+) -> Result<String, String> {
+    let output = output_options(&app_handle, output);
+    let saved = save_base64_image(&image_data, &save_dir, "bettershot", &output)?;
+    let _ = app_handle.emit(IMAGE_SAVED_EVENT, &saved);
+
+    if copy_to_clip {
+        let pixels = pixels_for_copy(&output, || load_base64_image(&image_data))?;
+        copy_image(
+            &app_handle,
+            &saved.path,
+            pixels.as_ref(),
+            destination.as_deref(),
+            "save_edited_image",
+        )?;
+    }
+
+    Ok(saved.path)
+}
+
+/// Save a rendered image by handle in the output format, emitting its size
+/// before and after optimization
+#[tauri::command]
+pub async fn save_rendered_image(
+    app_handle: AppHandle,
+    handle: u32,
+    save_dir: String,
+    copy_to_clip: bool,
+    output: Option<OutputOptions>,
+    destination: Option<String>,
 ) -> Result<String, String> {
-    let saved_path = save_base64_image(&image_data, &save_dir, "bettershot")?;
+    let output = output_options(&app_handle, output);
+    let render_path = RENDER_CACHE.path(handle)?;
+    let saved = save_image_file(&render_path, &save_dir, "bettershot", &output)?;
+    let _ = app_handle.emit(IMAGE_SAVED_EVENT, &saved);
 
     if copy_to_clip {
-        copy_image_to_clipboard(&saved_path)?;
+        let pixels = pixels_for_copy(&output, || {
+            image::open(&render_path).map_err(|e| format!("Failed to open render: {}", e))
+        })?;
+        copy_image(
+            &app_handle,
+            &saved.path,
+            pixels.as_ref(),
+            destination.as_deref(),
+            "save_rendered_image",
+        )?;
     }
 
-    Ok(saved_path)
+    Ok(saved.path)
 }
 
 /// Get the user's Desktop directory path (cross-platform)
@@ -142,6 +715,7 @@ pub async fn get_temp_directory() -> Result<String, String> {
 }
 
 /// Check if screencapture is already running
+#[cfg(not(target_os = "linux"))]
 fn is_screencapture_running() -> bool {
     let output = Command::new("pgrep")
         .arg("-x")
@@ -156,6 +730,7 @@ fn is_screencapture_running() -> bool {
 
 /// Check screen recording permission by attempting a minimal test
 /// This helps macOS recognize the permission is already granted
+#[cfg(not(target_os = "linux"))]
 fn check_and_activate_permission() -> Result<(), String> {
     let test_path = std::env::temp_dir().join(format!("bs_test_{}.png", std::process::id()));
 
@@ -196,10 +771,24 @@ fn check_and_activate_permission() -> Result<(), String> {
     }
 }
 
-/// Capture screenshot using macOS native screencapture with interactive selection
-/// This properly handles Screen Recording permissions through the system
-#[tauri::command]
-pub async fn native_capture_interactive(save_dir: String) -> Result<String, String> {
+/// screencapture flags, adding `-C` when the cursor should be drawn.
+/// screencapture draws the cursor itself, so no backend wrapping is needed.
+#[cfg(not(target_os = "linux"))]
+fn screencapture_flags<'a>(
+    app_handle: &AppHandle,
+    show_cursor: Option<bool>,
+    flags: &[&'a str],
+) -> Vec<&'a str> {
+    let mut flags = flags.to_vec();
+    if cursor_options(app_handle, show_cursor, Some(false)).show_cursor {
+        flags.push("-C");
+    }
+    flags
+}
+
+/// Run macOS screencapture with the given flags, saving to a new file in `save_dir`
+#[cfg(not(target_os = "linux"))]
+fn run_screencapture(flags: &[&str], save_dir: &str, prefix: &str) -> Result<String, String> {
     let _lock = SCREENCAPTURE_LOCK
         .lock()
         .map_err(|e| format!("Failed to acquire lock: {}", e))?;
@@ -212,14 +801,13 @@ pub async fn native_capture_interactive(save_dir: String) -> Result<String, Stri
         format!("Permission check failed: {}. Please ensure Screen Recording permission is granted in System Settings > Privacy & Security > Screen Recording.", e)
     })?;
 
-    let filename = generate_filename("screenshot", "png")?;
-    let save_path = PathBuf::from(&save_dir);
+    let filename = generate_filename(prefix, "png")?;
+    let save_path = PathBuf::from(save_dir);
     let screenshot_path = save_path.join(&filename);
     let path_str = screenshot_path.to_string_lossy().to_string();
 
     let child = Command::new("screencapture")
-        .arg("-i")
-        .arg("-x")
+        .args(flags)
         .arg(&path_str)
         .stdin(Stdio::null())
         .stdout(Stdio::piped())
@@ -252,46 +840,73 @@ pub async fn native_capture_interactive(save_dir: String) -> Result<String, Stri
     }
 }
 
-/// Capture full screen using macOS native screencapture
-#[tauri::command]
-pub async fn native_capture_fullscreen(save_dir: String) -> Result<String, String> {
+/// Run a Linux capture while holding the capture lock
+#[cfg(target_os = "linux")]
+fn run_linux_capture(
+    capture: impl FnOnce(&str) -> Result<String, String>,
+    save_dir: &str,
+) -> Result<String, String> {
     let _lock = SCREENCAPTURE_LOCK
-        .lock()
-        .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+        .try_lock()
+        .map_err(|_| "Another screenshot capture is already in progress".to_string())?;
 
-    if is_screencapture_running() {
-        return Err("Another screenshot capture is already in progress".to_string());
-    }
+    capture(save_dir)
+}
 
-    check_and_activate_permission().map_err(|e| {
-        format!("Permission check failed: {}. Please ensure Screen Recording permission is granted in System Settings > Privacy & Security > Screen Recording.", e)
-    })?;
+/// Capture a user-selected region
+/// On macOS this uses native screencapture, which properly handles Screen Recording
+/// permissions through the system; on Linux it uses the desktop portal or slop
+#[tauri::command]
+pub async fn native_capture_interactive(
+    app_handle: AppHandle,
+    save_dir: String,
+    show_cursor: Option<bool>,
+) -> Result<String, String> {
+    #[cfg(target_os = "linux")]
+    {
+        let backend = screenshot_backend(&app_handle, show_cursor);
+        run_linux_capture(
+            |dir| linux_capture::capture_interactive(backend.as_ref(), dir),
+            &save_dir,
+        )
+    }
 
-    let filename = generate_filename("screenshot", "png")?;
-    let save_path = PathBuf::from(&save_dir);
-    let screenshot_path = save_path.join(&filename);
-    let path_str = screenshot_path.to_string_lossy().to_string();
+    #[cfg(not(target_os = "linux"))]
+    {
+        let flags = screencapture_flags(&app_handle, show_cursor, &["-i", "-x"]);
+        run_screencapture(&flags, &save_dir, "screenshot")
+    }
+}
 
-    let status = Command::new("screencapture")
-        .arg("-x")
-        .arg(&path_str)
-        .status()
-        .map_err(|e| format!("Failed to run screencapture: {}", e))?;
+/// Capture the full screen (the primary monitor on Linux)
+#[tauri::command]
+pub async fn native_capture_fullscreen(
+    app_handle: AppHandle,
+    save_dir: String,
+    delay_seconds: Option<u32>,
+    show_cursor: Option<bool>,
+) -> Result<String, String> {
+    wait_for_capture_delay(&app_handle, delay_seconds).await?;
 
-    if !status.success() {
-        return Err("Screenshot failed".to_string());
+    #[cfg(target_os = "linux")]
+    {
+        let backend = screenshot_backend(&app_handle, show_cursor);
+        run_linux_capture(
+            |dir| linux_capture::capture_fullscreen(backend.as_ref(), dir),
+            &save_dir,
+        )
     }
 
-    if screenshot_path.exists() {
-        Ok(path_str)
-    } else {
-        Err("Screenshot failed".to_string())
+    #[cfg(not(target_os = "linux"))]
+    {
+        let flags = screencapture_flags(&app_handle, show_cursor, &["-x"]);
+        run_screencapture(&flags, &save_dir, "screenshot")
     }
 }
 
 /// Play the macOS screenshot sound using CoreAudio
 /// This uses AudioServicesPlaySystemSound which is non-blocking and works
-/// even when other audio/video is playing. Falls back to osascript if CoreAudio fails.
+/// even when other audio/video is playing. Falls back to afplay if CoreAudio fails.
 #[tauri::command]
 pub async fn play_screenshot_sound() -> Result<(), String> {
     #[cfg(target_os = "macos")]
@@ -352,9 +967,9 @@ pub async fn play_screenshot_sound() -> Result<(), String> {
 fn fallback_sound_playback() {
     let sound_path = "/System/Library/Components/CoreAudio.component/Contents/SharedSupport/SystemSounds/system/Screen Capture.aif";
     
-    let _ = Command::new("osascript")
-        .arg("-e")
-        .arg(format!("do shell script \"afplay '{}' &\"", sound_path))
+    // Pass the path as an argument rather than splicing it into a script
+    let _ = Command::new("afplay")
+        .arg(sound_path)
         .stdout(Stdio::null())
         .stderr(Stdio::null())
         .spawn();
@@ -391,114 +1006,46 @@ pub async fn get_mouse_position() -> Result<(f64, f64), String> {
     Ok((x, y))
 }
 
-/// Capture specific window using macOS native screencapture
+/// Capture a user-selected window
 #[tauri::command]
-pub async fn native_capture_window(save_dir: String) -> Result<String, String> {
-    let _lock = SCREENCAPTURE_LOCK
-        .lock()
-        .map_err(|e| format!("Failed to acquire lock: {}", e))?;
-
-    if is_screencapture_running() {
-        return Err("Another screenshot capture is already in progress".to_string());
-    }
-
-    check_and_activate_permission().map_err(|e| {
-        format!("Permission check failed: {}. Please ensure Screen Recording permission is granted in System Settings > Privacy & Security > Screen Recording.", e)
-    })?;
-
-    let filename = generate_filename("screenshot", "png")?;
-    let save_path = PathBuf::from(&save_dir);
-    let screenshot_path = save_path.join(&filename);
-    let path_str = screenshot_path.to_string_lossy().to_string();
-
-    let child = Command::new("screencapture")
-        .arg("-w")
-        .arg("-x")
-        .arg(&path_str)
-        .stdin(Stdio::null())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("Failed to run screencapture: {}", e))?;
-
-    let output = child
-        .wait_with_output()
-        .map_err(|e| format!("Failed to wait for screencapture: {}", e))?;
-
-    if !output.status.success() {
-        if screenshot_path.exists() {
-            let _ = std::fs::remove_file(&screenshot_path);
-        }
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        if stderr.contains("permission")
-            || stderr.contains("denied")
-            || stderr.contains("not authorized")
-        {
-            return Err("Screen Recording permission required. Please grant permission in System Settings > Privacy & Security > Screen Recording and restart the app.".to_string());
-        }
-        return Err("Screenshot was cancelled or failed".to_string());
+pub async fn native_capture_window(
+    app_handle: AppHandle,
+    save_dir: String,
+    show_cursor: Option<bool>,
+) -> Result<String, String> {
+    #[cfg(target_os = "linux")]
+    {
+        let backend = screenshot_backend(&app_handle, show_cursor);
+        run_linux_capture(
+            |dir| linux_capture::capture_window(backend.as_ref(), dir),
+            &save_dir,
+        )
     }
 
-    if screenshot_path.exists() {
-        Ok(path_str)
-    } else {
-        Err("Screenshot was cancelled or failed".to_string())
+    #[cfg(not(target_os = "linux"))]
+    {
+        let flags = screencapture_flags(&app_handle, show_cursor, &["-w", "-x"]);
+        run_screencapture(&flags, &save_dir, "screenshot")
     }
 }
 
 /// Capture region and perform OCR, copying text to clipboard
 #[tauri::command]
-pub async fn native_capture_ocr_region(save_dir: String) -> Result<String, String> {
-    {
-        let _lock = SCREENCAPTURE_LOCK
-            .lock()
-            .map_err(|e| format!("Failed to acquire lock: {}", e))?;
-
-        if is_screencapture_running() {
-            return Err("Another screenshot capture is already in progress".to_string());
-        }
-
-        check_and_activate_permission().map_err(|e| {
-            format!("Permission check failed: {}. Please ensure Screen Recording permission is granted in System Settings > Privacy & Security > Screen Recording.", e)
-        })?;
-    }
-
-    let filename = generate_filename("ocr_temp", "png")?;
-    let save_path = PathBuf::from(&save_dir);
-    let screenshot_path = save_path.join(&filename);
-    let path_str = screenshot_path.to_string_lossy().to_string();
-
-    let child = Command::new("screencapture")
-        .arg("-i")
-        .arg("-x")
-        .arg(&path_str)
-        .stdin(Stdio::null())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .spawn()
-        .map_err(|e| format!("Failed to run screencapture: {}", e))?;
-
-    let output = child
-        .wait_with_output()
-        .map_err(|e| format!("Failed to wait for screencapture: {}", e))?;
+pub async fn native_capture_ocr_region(
+    app_handle: AppHandle,
+    save_dir: String,
+) -> Result<String, String> {
+    // Never draw the cursor over text to be recognized
+    #[cfg(target_os = "linux")]
+    let path_str = run_linux_capture(
+        |dir| linux_capture::capture_interactive(capture::backend().as_ref(), dir),
+        &save_dir,
+    )?;
 
-    if !output.status.success() {
-        if screenshot_path.exists() {
-            let _ = std::fs::remove_file(&screenshot_path);
-        }
-        let stderr = String::from_utf8_lossy(&output.stderr);
-        if stderr.contains("permission")
-            || stderr.contains("denied")
-            || stderr.contains("not authorized")
-        {
-            return Err("Screen Recording permission required. Please grant permission in System Settings > Privacy & Security > Screen Recording and restart the app.".to_string());
-        }
-        return Err("Screenshot was cancelled or failed".to_string());
-    }
+    #[cfg(not(target_os = "linux"))]
+    let path_str = run_screencapture(&["-i", "-x"], &save_dir, "ocr_temp")?;
 
-    if !screenshot_path.exists() {
-        return Err("Screenshot was cancelled or failed".to_string());
-    }
+    let screenshot_path = PathBuf::from(&path_str);
 
     play_screenshot_sound().await.ok();
 
@@ -507,6 +1054,13 @@ pub async fn native_capture_ocr_region(save_dir: String) -> Result<String, Strin
 
     copy_text_to_clipboard(&recognized_text)
         .map_err(|e| format!("Failed to copy text to clipboard: {}", e))?;
+    remember_copy(
+        &app_handle,
+        HistoryContent::Text {
+            text: recognized_text.clone(),
+        },
+        "native_capture_ocr_region",
+    );
 
     let _ = std::fs::remove_file(&screenshot_path);
 
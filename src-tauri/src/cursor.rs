@@ -0,0 +1,536 @@
+//! Mouse cursor compositing
+//!
+//! Screen capture APIs leave the pointer out of their images, so it is drawn
+//! back in afterwards: `CursorOverlay` wraps a capture backend and paints the
+//! cursor, at its position and shape when the frame was grabbed, onto every
+//! capture. Recordings can also ring the cursor while the mouse button is
+//! held, so clicks are visible in playback.
+
+use image::imageops::{overlay, resize, FilterType};
+use image::{Pixel, Rgba, RgbaImage};
+
+use crate::capture::{CaptureBackend, MonitorInfo, Rect, WindowInfo};
+use crate::utils::AppResult;
+
+/// Store key of the saved "show cursor" preference
+pub const SHOW_CURSOR_SETTING: &str = "showCursor";
+
+/// Store key of the saved "highlight clicks" preference
+pub const HIGHLIGHT_CLICKS_SETTING: &str = "highlightClicks";
+
+/// Radius of the click highlight ring, in desktop units
+const CLICK_RING_RADIUS: f64 = 18.0;
+
+/// Stroke width of the click highlight ring, in desktop units
+const CLICK_RING_WIDTH: f64 = 4.0;
+
+/// Color of the click highlight ring
+const CLICK_RING_COLOR: Rgba<u8> = Rgba([255, 196, 0, 190]);
+
+/// Outline of the fallback arrow cursor, in desktop units with the tip at 0,0
+const ARROW_OUTLINE: [(f64, f64); 7] = [
+    (0.0, 0.0),
+    (0.0, 17.0),
+    (4.0, 13.0),
+    (7.0, 20.0),
+    (10.0, 19.0),
+    (7.0, 12.0),
+    (12.0, 12.0),
+];
+
+/// Which cursor decorations to draw into captures
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct CursorOptions {
+    pub show_cursor: bool,
+    /// Ring the cursor while the primary button is held
+    pub highlight_clicks: bool,
+}
+
+/// Bitmap of a cursor's shape
+#[derive(Clone, Debug, PartialEq)]
+pub struct CursorImage {
+    pub image: RgbaImage,
+    /// Position of the pointer tip within `image`, in pixels
+    pub hotspot_x: f64,
+    pub hotspot_y: f64,
+    /// Pixels of `image` per desktop unit
+    pub scale: f64,
+}
+
+/// The pointer at one moment, in global desktop coordinates
+#[derive(Clone, Debug, PartialEq)]
+pub struct CursorState {
+    pub x: f64,
+    pub y: f64,
+    /// Whether the primary mouse button is down
+    pub pressed: bool,
+    /// Current cursor shape, when the platform exposes it
+    pub image: Option<CursorImage>,
+}
+
+/// Read the system cursor
+#[cfg(target_os = "macos")]
+pub fn current() -> AppResult<CursorState> {
+    use objc2_core_graphics::{CGEvent, CGEventSource, CGEventSourceStateID, CGMouseButton};
+
+    let event = CGEvent::new(None).ok_or("Failed to read the cursor position")?;
+    let location = CGEvent::location(Some(&event));
+    let pressed = CGEventSource::button_state(
+        CGEventSourceStateID::CombinedSessionState,
+        CGMouseButton::Left,
+    );
+
+    Ok(CursorState {
+        x: location.x,
+        y: location.y,
+        pressed,
+        image: system_cursor_image(),
+    })
+}
+
+/// Shape of the cursor currently shown by any application
+#[cfg(target_os = "macos")]
+#[allow(deprecated)]
+fn system_cursor_image() -> Option<CursorImage> {
+    use objc2_app_kit::NSCursor;
+
+    let cursor = NSCursor::currentSystemCursor()?;
+    let image = cursor.image();
+    let size = image.size();
+    let hotspot = cursor.hotSpot();
+
+    let tiff = image.TIFFRepresentation()?;
+    let bitmap = image::load_from_memory(&tiff.to_vec()).ok()?.to_rgba8();
+    if size.width <= 0.0 {
+        return None;
+    }
+    let scale = bitmap.width() as f64 / size.width;
+
+    Some(CursorImage {
+        image: bitmap,
+        hotspot_x: hotspot.x * scale,
+        hotspot_y: hotspot.y * scale,
+        scale,
+    })
+}
+
+/// Read the system cursor from the X server (through XWayland on Wayland,
+/// where it only tracks the pointer over X11 windows)
+#[cfg(target_os = "linux")]
+pub fn current() -> AppResult<CursorState> {
+    use x11rb::connection::Connection;
+    use x11rb::protocol::xfixes::ConnectionExt as _;
+    use x11rb::protocol::xproto::{ConnectionExt as _, KeyButMask};
+
+    let (conn, screen) =
+        x11rb::connect(None).map_err(|e| format!("Failed to connect to X server: {}", e))?;
+    let root = conn.setup().roots[screen].root;
+
+    let pointer = conn
+        .query_pointer(root)
+        .map_err(|e| e.to_string())
+        .and_then(|cookie| cookie.reply().map_err(|e| e.to_string()))
+        .map_err(|e| format!("Failed to read the cursor position: {}", e))?;
+
+    // XFixes is optional; without it the fallback arrow is drawn
+    let image = conn
+        .xfixes_query_version(4, 0)
+        .ok()
+        .and_then(|cookie| cookie.reply().ok())
+        .and_then(|_| conn.xfixes_get_cursor_image().ok())
+        .and_then(|cookie| cookie.reply().ok())
+        .and_then(|reply| {
+            let pixels = reply
+                .cursor_image
+                .iter()
+                .flat_map(|&argb| unpremultiply(argb))
+                .collect();
+            let image = RgbaImage::from_raw(reply.width as u32, reply.height as u32, pixels)?;
+
+            Some(CursorImage {
+                image,
+                hotspot_x: reply.xhot as f64,
+                hotspot_y: reply.yhot as f64,
+                scale: 1.0,
+            })
+        });
+
+    Ok(CursorState {
+        x: pointer.root_x as f64,
+        y: pointer.root_y as f64,
+        pressed: pointer.mask.contains(KeyButMask::BUTTON1),
+        image,
+    })
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux")))]
+pub fn current() -> AppResult<CursorState> {
+    Err("Cursor capture is not supported on this platform".to_string())
+}
+
+/// Convert a premultiplied ARGB pixel, as returned by XFixes, to RGBA
+#[cfg(any(target_os = "linux", test))]
+fn unpremultiply(argb: u32) -> [u8; 4] {
+    let [a, r, g, b] = argb.to_be_bytes();
+    let channel = |c: u8| match a {
+        0 => 0,
+        _ => ((c as u32 * 255 + a as u32 / 2) / a as u32).min(255) as u8,
+    };
+    [channel(r), channel(g), channel(b), a]
+}
+
+/// Whether a point lies inside a polygon, by ray casting
+fn inside_polygon(x: f64, y: f64, points: &[(f64, f64)]) -> bool {
+    let mut inside = false;
+    let mut j = points.len() - 1;
+    for i in 0..points.len() {
+        let (xi, yi) = points[i];
+        let (xj, yj) = points[j];
+        if (yi > y) != (yj > y) && x < (xj - xi) * (y - yi) / (yj - yi) + xi {
+            inside = !inside;
+        }
+        j = i;
+    }
+    inside
+}
+
+/// Distance from a point to the nearest edge of a polygon
+fn distance_to_outline(x: f64, y: f64, points: &[(f64, f64)]) -> f64 {
+    let mut nearest = f64::MAX;
+    let mut j = points.len() - 1;
+    for i in 0..points.len() {
+        let (ax, ay) = points[j];
+        let (bx, by) = points[i];
+        let (dx, dy) = (bx - ax, by - ay);
+        let t = (((x - ax) * dx + (y - ay) * dy) / (dx * dx + dy * dy)).clamp(0.0, 1.0);
+        let (px, py) = (ax + t * dx, ay + t * dy);
+        nearest = nearest.min(((x - px).powi(2) + (y - py).powi(2)).sqrt());
+        j = i;
+    }
+    nearest
+}
+
+/// The classic white arrow with a black outline, drawn at `scale` pixels per
+/// desktop unit. Used when the platform doesn't expose the cursor's shape.
+pub fn default_arrow(scale: f64) -> CursorImage {
+    let scale = if scale > 0.0 { scale } else { 1.0 };
+    let width = (13.0 * scale).ceil() as u32;
+    let height = (21.0 * scale).ceil() as u32;
+
+    let image = RgbaImage::from_fn(width, height, |px, py| {
+        let x = (px as f64 + 0.5) / scale;
+        let y = (py as f64 + 0.5) / scale;
+        if !inside_polygon(x, y, &ARROW_OUTLINE) {
+            Rgba([0, 0, 0, 0])
+        } else if distance_to_outline(x, y, &ARROW_OUTLINE) <= 1.0 {
+            Rgba([0, 0, 0, 255])
+        } else {
+            Rgba([255, 255, 255, 255])
+        }
+    });
+
+    CursorImage {
+        image,
+        hotspot_x: 0.0,
+        hotspot_y: 0.0,
+        scale,
+    }
+}
+
+/// Blend a ring centered on `cx`,`cy` into `img`, all in pixels
+fn draw_ring(img: &mut RgbaImage, cx: f64, cy: f64, radius: f64, width: f64) {
+    let outer = radius + width / 2.0 + 1.0;
+    let x0 = (cx - outer).floor().max(0.0) as u32;
+    let y0 = (cy - outer).floor().max(0.0) as u32;
+    let x1 = ((cx + outer).ceil().max(0.0) as u32).min(img.width());
+    let y1 = ((cy + outer).ceil().max(0.0) as u32).min(img.height());
+
+    for y in y0..y1 {
+        for x in x0..x1 {
+            let distance = ((x as f64 + 0.5 - cx).powi(2) + (y as f64 + 0.5 - cy).powi(2)).sqrt();
+            let coverage = (width / 2.0 + 0.5 - (distance - radius).abs()).clamp(0.0, 1.0);
+            if coverage > 0.0 {
+                let mut color = CLICK_RING_COLOR;
+                color[3] = (color[3] as f64 * coverage).round() as u8;
+                img.get_pixel_mut(x, y).blend(&color);
+            }
+        }
+    }
+}
+
+/// Draw `cursor` into `img`, which shows the desktop area `bounds`
+pub fn draw_cursor(img: &mut RgbaImage, bounds: &Rect, cursor: &CursorState, highlight: bool) {
+    if bounds.width == 0 || bounds.height == 0 {
+        return;
+    }
+
+    // Pixels per desktop unit of this capture
+    let density = img.width() as f64 / bounds.width as f64;
+    let x = (cursor.x - bounds.x as f64) * density;
+    let y = (cursor.y - bounds.y as f64) * density;
+
+    if highlight && cursor.pressed {
+        draw_ring(
+            img,
+            x,
+            y,
+            CLICK_RING_RADIUS * density,
+            CLICK_RING_WIDTH * density,
+        );
+    }
+
+    let sprite = match &cursor.image {
+        Some(image) => image.clone(),
+        None => default_arrow(density),
+    };
+    let ratio = density / sprite.scale;
+    let image = if (ratio - 1.0).abs() > 0.01 {
+        let width = ((sprite.image.width() as f64 * ratio).round() as u32).max(1);
+        let height = ((sprite.image.height() as f64 * ratio).round() as u32).max(1);
+        resize(&sprite.image, width, height, FilterType::Triangle)
+    } else {
+        sprite.image
+    };
+
+    overlay(
+        img,
+        &image,
+        (x - sprite.hotspot_x * ratio).round() as i64,
+        (y - sprite.hotspot_y * ratio).round() as i64,
+    );
+}
+
+/// Capture backend that draws the cursor into everything it captures
+pub struct CursorOverlay {
+    inner: Box<dyn CaptureBackend>,
+    highlight_clicks: bool,
+}
+
+/// Wrap `backend` so captures include the cursor, if `options` ask for it
+pub fn with_cursor(
+    backend: Box<dyn CaptureBackend>,
+    options: CursorOptions,
+) -> Box<dyn CaptureBackend> {
+    if options.show_cursor {
+        Box::new(CursorOverlay {
+            inner: backend,
+            highlight_clicks: options.highlight_clicks,
+        })
+    } else {
+        backend
+    }
+}
+
+impl CursorOverlay {
+    fn monitor(&self, id: u32) -> Option<MonitorInfo> {
+        self.inner.monitors().ok()?.into_iter().find(|m| m.id == id)
+    }
+
+    /// A missing cursor never fails the capture; it is just left out
+    fn draw(&self, img: &mut RgbaImage, bounds: Option<Rect>) {
+        if let (Some(bounds), Ok(cursor)) = (bounds, self.inner.cursor()) {
+            draw_cursor(img, &bounds, &cursor, self.highlight_clicks);
+        }
+    }
+}
+
+impl CaptureBackend for CursorOverlay {
+    fn monitors(&self) -> AppResult<Vec<MonitorInfo>> {
+        self.inner.monitors()
+    }
+
+    fn windows(&self) -> AppResult<Vec<WindowInfo>> {
+        self.inner.windows()
+    }
+
+    fn cursor(&self) -> AppResult<CursorState> {
+        self.inner.cursor()
+    }
+
+    fn capture_monitor(&self, id: u32) -> AppResult<RgbaImage> {
+        let mut img = self.inner.capture_monitor(id)?;
+        let bounds = self.monitor(id).map(|m| Rect {
+            x: m.x,
+            y: m.y,
+            width: m.width,
+            height: m.height,
+        });
+        self.draw(&mut img, bounds);
+        Ok(img)
+    }
+
+    fn capture_window(&self, id: u32) -> AppResult<RgbaImage> {
+        let mut img = self.inner.capture_window(id)?;
+        let bounds = self.inner.windows().ok().and_then(|windows| {
+            windows.into_iter().find(|w| w.id == id).map(|w| Rect {
+                x: w.x,
+                y: w.y,
+                width: w.width,
+                height: w.height,
+            })
+        });
+        self.draw(&mut img, bounds);
+        Ok(img)
+    }
+
+    fn capture_monitor_region(
+        &self,
+        id: u32,
+        x: u32,
+        y: u32,
+        width: u32,
+        height: u32,
+    ) -> AppResult<RgbaImage> {
+        let mut img = self.inner.capture_monitor_region(id, x, y, width, height)?;
+        let bounds = self.monitor(id).map(|m| Rect {
+            x: m.x + x as i32,
+            y: m.y + y as i32,
+            width,
+            height,
+        });
+        self.draw(&mut img, bounds);
+        Ok(img)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::{mock_pixel, CaptureTarget, MockBackend};
+
+    fn cursor_at(x: f64, y: f64, pressed: bool) -> CursorState {
+        CursorState {
+            x,
+            y,
+            pressed,
+            image: None,
+        }
+    }
+
+    fn mock_with_cursor(cursor: CursorState) -> Box<dyn CaptureBackend> {
+        Box::new(MockBackend {
+            cursor: Some(cursor),
+            ..MockBackend::default()
+        })
+    }
+
+    fn shown(highlight_clicks: bool) -> CursorOptions {
+        CursorOptions {
+            show_cursor: true,
+            highlight_clicks,
+        }
+    }
+
+    #[test]
+    fn test_unpremultiply() {
+        assert_eq!(unpremultiply(0xFF10_2030), [0x10, 0x20, 0x30, 0xFF]);
+        assert_eq!(unpremultiply(0x8040_0000), [0x80, 0, 0, 0x80]);
+        assert_eq!(unpremultiply(0x0000_0000), [0, 0, 0, 0]);
+    }
+
+    #[test]
+    fn test_default_arrow_shape() {
+        let arrow = default_arrow(1.0);
+        assert_eq!(arrow.image.dimensions(), (13, 21));
+        // Black outline at the tip, white body, transparent outside
+        assert_eq!(*arrow.image.get_pixel(0, 1), Rgba([0, 0, 0, 255]));
+        assert_eq!(*arrow.image.get_pixel(3, 10), Rgba([255, 255, 255, 255]));
+        assert_eq!(arrow.image.get_pixel(11, 2)[3], 0);
+
+        let retina = default_arrow(2.0);
+        assert_eq!(retina.image.dimensions(), (26, 42));
+    }
+
+    #[test]
+    fn test_hidden_cursor_leaves_backend_alone() {
+        let backend = with_cursor(
+            mock_with_cursor(cursor_at(10.0, 10.0, false)),
+            CursorOptions::default(),
+        );
+        let img = backend.capture_monitor(1).unwrap();
+        assert_eq!(*img.get_pixel(13, 20), mock_pixel(13, 20));
+    }
+
+    #[test]
+    fn test_cursor_drawn_at_position() {
+        let backend = with_cursor(mock_with_cursor(cursor_at(10.0, 10.0, false)), shown(false));
+        let img = backend.capture_monitor(1).unwrap();
+
+        assert_eq!(*img.get_pixel(13, 20), Rgba([255, 255, 255, 255]));
+        assert_eq!(*img.get_pixel(10, 11), Rgba([0, 0, 0, 255]));
+        // Pixels away from the cursor are untouched
+        assert_eq!(*img.get_pixel(100, 100), mock_pixel(100, 100));
+    }
+
+    #[test]
+    fn test_cursor_scaled_on_retina_monitor() {
+        let mut mock = MockBackend {
+            cursor: Some(cursor_at(330.0, 10.0, false)),
+            ..MockBackend::default()
+        };
+        mock.monitors[1].scale_factor = 2.0;
+        let backend = with_cursor(Box::new(mock), shown(false));
+
+        let img = backend.capture_monitor(2).unwrap();
+        // 10 units right of the monitor's left edge, at 2 pixels per unit
+        assert_eq!(*img.get_pixel(26, 40), Rgba([255, 255, 255, 255]));
+        assert_eq!(*img.get_pixel(60, 100), mock_pixel(350, 50));
+    }
+
+    #[test]
+    fn test_cursor_in_window_and_region_captures() {
+        let backend = with_cursor(mock_with_cursor(cursor_at(30.0, 40.0, false)), shown(false));
+
+        // Window 101 starts at 20,30
+        let img = backend.capture_window(101).unwrap();
+        assert_eq!(*img.get_pixel(13, 20), Rgba([255, 255, 255, 255]));
+
+        let target = CaptureTarget::Rect {
+            x: 25,
+            y: 35,
+            width: 40,
+            height: 40,
+        };
+        let img = target.capture(backend.as_ref()).unwrap();
+        assert_eq!(*img.get_pixel(8, 15), Rgba([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn test_cursor_uses_platform_shape() {
+        let mut cursor = cursor_at(50.0, 50.0, false);
+        cursor.image = Some(CursorImage {
+            image: RgbaImage::from_pixel(4, 4, Rgba([0, 0, 255, 255])),
+            hotspot_x: 2.0,
+            hotspot_y: 2.0,
+            scale: 1.0,
+        });
+        let backend = with_cursor(mock_with_cursor(cursor), shown(false));
+
+        let img = backend.capture_monitor(1).unwrap();
+        assert_eq!(*img.get_pixel(48, 48), Rgba([0, 0, 255, 255]));
+        assert_eq!(*img.get_pixel(51, 51), Rgba([0, 0, 255, 255]));
+        assert_eq!(*img.get_pixel(52, 52), mock_pixel(52, 52));
+    }
+
+    #[test]
+    fn test_click_ring_only_while_pressed() {
+        let ring_pixel = |pressed: bool, highlight: bool| {
+            let backend = with_cursor(
+                mock_with_cursor(cursor_at(100.0, 100.0, pressed)),
+                shown(highlight),
+            );
+            *backend.capture_monitor(1).unwrap().get_pixel(82, 100)
+        };
+
+        assert_ne!(ring_pixel(true, true), mock_pixel(82, 100));
+        assert_eq!(ring_pixel(false, true), mock_pixel(82, 100));
+        assert_eq!(ring_pixel(true, false), mock_pixel(82, 100));
+    }
+
+    #[test]
+    fn test_missing_cursor_does_not_fail_capture() {
+        let backend = with_cursor(Box::new(MockBackend::default()), shown(true));
+        let img = backend.capture_monitor(1).unwrap();
+        assert_eq!(*img.get_pixel(0, 0), mock_pixel(0, 0));
+    }
+}
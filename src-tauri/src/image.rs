@@ -1,11 +1,14 @@
 //! Image processing module
 
 use base64::{engine::general_purpose, Engine as _};
-use image::{DynamicImage, ImageFormat, Rgba, RgbaImage};
+use color_quant::NeuQuant;
+use image::imageops::{overlay, resize, FilterType};
+use image::{DynamicImage, GrayImage, Luma, Rgba, RgbaImage};
 use imageproc::filter::gaussian_blur_f32;
 use std::fs;
 use std::path::PathBuf;
 
+use crate::output::{encode_image, ensure_decodable, OutputFormat, OutputOptions};
 use crate::utils::{ensure_dir, generate_filename, AppResult};
 
 /// Region coordinates for cropping
@@ -72,64 +75,298 @@ pub fn crop_image(source_path: &str, region: CropRegion, save_dir: &str) -> AppR
 
     let cropped = img.crop_imm(region.x, region.y, region.width, region.height);
 
-    save_image(&cropped, save_dir, "region")
+    // Regions are editor sources, so they stay lossless PNG
+    save_image(&cropped, save_dir, "region", &OutputOptions::default()).map(|saved| saved.path)
 }
 
-/// Save a DynamicImage to a directory with a generated filename
-pub fn save_image(img: &DynamicImage, save_dir: &str, prefix: &str) -> AppResult<String> {
+/// Event emitted with a `SavedImage` when an image is saved to the user's folder
+pub const IMAGE_SAVED_EVENT: &str = "image-saved";
+
+/// Default quality floor for palette quantization
+const DEFAULT_MIN_QUANTIZE_QUALITY: u8 = 80;
+
+/// PSNR, in dB, at which a quantized image scores 0 and 100 quality
+const QUANTIZE_PSNR_RANGE: (f64, f64) = (20.0, 50.0);
+
+/// NeuQuant sampling factor; 10 is the usual speed/quality compromise
+const QUANTIZE_SAMPLE_FACTOR: i32 = 10;
+
+/// oxipng preset used for lossless recompression; higher presets are much
+/// slower on full-screen captures for little gain
+const RECOMPRESS_PRESET: u8 = 2;
+
+/// Size optimizations applied when saving PNGs
+#[derive(Debug, Clone, Copy, PartialEq, serde::Deserialize)]
+pub struct PngOptimization {
+    /// Losslessly recompress and reduce the PNG with oxipng
+    #[serde(default)]
+    pub recompress: bool,
+    /// Reduce the image to a 256-color palette when that scores at least
+    /// `min_quality`; otherwise the full-color image is kept
+    #[serde(default)]
+    pub quantize: bool,
+    /// 0-100 quality floor for palette quantization
+    #[serde(default = "default_min_quantize_quality")]
+    pub min_quality: u8,
+    /// Drop chunks that don't affect display, such as text and EXIF
+    #[serde(default)]
+    pub strip_metadata: bool,
+}
+
+fn default_min_quantize_quality() -> u8 {
+    DEFAULT_MIN_QUANTIZE_QUALITY
+}
+
+impl Default for PngOptimization {
+    fn default() -> Self {
+        Self {
+            recompress: false,
+            quantize: false,
+            min_quality: DEFAULT_MIN_QUANTIZE_QUALITY,
+            strip_metadata: false,
+        }
+    }
+}
+
+/// A saved image and how much the save pipeline shrank it
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct SavedImage {
+    pub path: String,
+    /// Size as first encoded, before optimization
+    pub original_bytes: u64,
+    /// Size written to disk
+    pub saved_bytes: u64,
+    /// Whether the image was reduced to a lossy palette
+    pub quantized: bool,
+}
+
+/// Score how closely a quantized image matches the original, from 0 to 100
+fn quantization_quality(original: &RgbaImage, quantized: &RgbaImage) -> u8 {
+    let squared_error: f64 = original
+        .as_raw()
+        .iter()
+        .zip(quantized.as_raw())
+        .map(|(&a, &b)| (a as f64 - b as f64).powi(2))
+        .sum();
+    let mse = squared_error / original.as_raw().len().max(1) as f64;
+    if mse == 0.0 {
+        return 100;
+    }
+
+    let psnr = 10.0 * (255.0 * 255.0 / mse).log10();
+    let (low, high) = QUANTIZE_PSNR_RANGE;
+    ((psnr - low) / (high - low) * 100.0)
+        .clamp(0.0, 100.0)
+        .round() as u8
+}
+
+/// Reduce an image to at most 256 colors, returning the indexed PNG and the
+/// image it decodes to
+fn quantize_png(img: &RgbaImage) -> AppResult<(Vec<u8>, RgbaImage)> {
+    let quantizer = NeuQuant::new(QUANTIZE_SAMPLE_FACTOR, 256, img.as_raw());
+    let colors = quantizer.color_map_rgba();
+
+    // Only write the colors the image uses, so small images don't carry a
+    // full 256-entry palette
+    let mut remap = [None; 256];
+    let mut palette: Vec<[u8; 4]> = Vec::new();
+    let mut indices = Vec::with_capacity(img.width() as usize * img.height() as usize);
+    let mut quantized = img.clone();
+    for pixel in quantized.pixels_mut() {
+        let index = quantizer.index_of(&pixel.0);
+        let color = [
+            colors[index * 4],
+            colors[index * 4 + 1],
+            colors[index * 4 + 2],
+            colors[index * 4 + 3],
+        ];
+        let entry = *remap[index].get_or_insert_with(|| {
+            palette.push(color);
+            (palette.len() - 1) as u8
+        });
+        indices.push(entry);
+        pixel.0 = color;
+    }
+
+    let mut bytes = Vec::new();
+    let mut encoder = png::Encoder::new(&mut bytes, img.width(), img.height());
+    encoder.set_color(png::ColorType::Indexed);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_palette(
+        palette
+            .iter()
+            .flat_map(|c| [c[0], c[1], c[2]])
+            .collect::<Vec<_>>(),
+    );
+    if palette.iter().any(|c| c[3] < 255) {
+        encoder.set_trns(palette.iter().map(|c| c[3]).collect::<Vec<_>>());
+    }
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&indices))
+        .map_err(|e| format!("Failed to encode quantized PNG: {}", e))?;
+
+    Ok((bytes, quantized))
+}
+
+/// Apply the requested optimizations to an encoded PNG, returning the new
+/// bytes and whether they were quantized
+fn optimize_png(bytes: Vec<u8>, optimization: &PngOptimization) -> AppResult<(Vec<u8>, bool)> {
+    let mut bytes = bytes;
+    let mut quantized = false;
+
+    if optimization.quantize {
+        let img = image::load_from_memory_with_format(&bytes, image::ImageFormat::Png)
+            .map_err(|e| format!("Failed to decode PNG: {}", e))?
+            .to_rgba8();
+        let (palette_bytes, palette_img) = quantize_png(&img)?;
+        if palette_bytes.len() < bytes.len()
+            && quantization_quality(&img, &palette_img) >= optimization.min_quality
+        {
+            bytes = palette_bytes;
+            quantized = true;
+        }
+    }
+
+    if optimization.recompress || optimization.strip_metadata {
+        let mut options = oxipng::Options::from_preset(if optimization.recompress {
+            RECOMPRESS_PRESET
+        } else {
+            0
+        });
+        options.strip = if optimization.strip_metadata {
+            oxipng::StripChunks::Safe
+        } else {
+            oxipng::StripChunks::None
+        };
+        bytes = oxipng::optimize_from_memory(&bytes, &options)
+            .map_err(|e| format!("Failed to optimize PNG: {}", e))?;
+    }
+
+    Ok((bytes, quantized))
+}
+
+/// Run encoded image bytes through the optimization step and write them to
+/// a directory with a generated filename
+fn write_encoded(
+    bytes: Vec<u8>,
+    save_dir: &str,
+    prefix: &str,
+    format: OutputFormat,
+    output: &OutputOptions,
+) -> AppResult<SavedImage> {
+    let original_bytes = bytes.len() as u64;
+    let (bytes, quantized) = match format {
+        OutputFormat::Png => optimize_png(bytes, &output.png)?,
+        _ => (bytes, false),
+    };
+
     let dest_path = PathBuf::from(save_dir);
     ensure_dir(&dest_path)?;
 
-    let filename = generate_filename(prefix, "png")?;
+    let filename = generate_filename(prefix, format.extension())?;
     let file_path = dest_path.join(&filename);
 
-    img.save(&file_path)
-        .map_err(|e| format!("Failed to save image: {}", e))?;
+    fs::write(&file_path, &bytes).map_err(|e| format!("Failed to save image: {}", e))?;
+
+    Ok(SavedImage {
+        path: file_path.to_string_lossy().into_owned(),
+        original_bytes,
+        saved_bytes: bytes.len() as u64,
+        quantized,
+    })
+}
 
-    Ok(file_path.to_string_lossy().into_owned())
+/// Save a DynamicImage to a directory with a generated filename
+pub fn save_image(
+    img: &DynamicImage,
+    save_dir: &str,
+    prefix: &str,
+    output: &OutputOptions,
+) -> AppResult<SavedImage> {
+    let bytes = encode_image(img, output)?;
+    write_encoded(bytes, save_dir, prefix, output.format, output)
 }
 
-/// Save base64-encoded image data to a file
-pub fn save_base64_image(image_data: &str, save_dir: &str, prefix: &str) -> AppResult<String> {
-    let base64_data = image_data
-        .strip_prefix("data:image/png;base64,")
-        .ok_or("Invalid image data format: expected data:image/png;base64, prefix")?;
+/// Split a `data:image/...;base64,` URL into its format and decoded bytes
+fn decode_data_url(image_data: &str) -> AppResult<(OutputFormat, Vec<u8>)> {
+    let invalid = || "Invalid image data format: expected a data:image/...;base64, URL".to_string();
+
+    let (header, base64_data) = image_data.split_once(',').ok_or_else(invalid)?;
+    let mime_type = header
+        .strip_prefix("data:")
+        .and_then(|header| header.strip_suffix(";base64"))
+        .ok_or_else(invalid)?;
+    let format = OutputFormat::from_mime_type(mime_type)
+        .ok_or_else(|| format!("Unsupported image type: {}", mime_type))?;
 
     let image_bytes = general_purpose::STANDARD
         .decode(base64_data)
         .map_err(|e| format!("Failed to decode base64: {}", e))?;
 
-    let dest_path = PathBuf::from(save_dir);
-    ensure_dir(&dest_path)?;
+    Ok((format, image_bytes))
+}
 
-    let filename = generate_filename(prefix, "png")?;
-    let file_path = dest_path.join(&filename);
+/// Decode image bytes from a data URL in the given format
+fn decode_image_bytes(format: OutputFormat, image_bytes: &[u8]) -> AppResult<DynamicImage> {
+    if !format.can_decode() {
+        return Err(format!(
+            "{} images can be saved but not opened again",
+            format.extension().to_ascii_uppercase()
+        ));
+    }
+    image::load_from_memory(image_bytes).map_err(|e| format!("Failed to decode image: {}", e))
+}
+
+/// Decode base64-encoded image data
+pub fn load_base64_image(image_data: &str) -> AppResult<DynamicImage> {
+    let (format, image_bytes) = decode_data_url(image_data)?;
+    decode_image_bytes(format, &image_bytes)
+}
 
-    fs::write(&file_path, image_bytes).map_err(|e| format!("Failed to save image: {}", e))?;
+/// Save base64-encoded image data to a file, re-encoding it unless it is
+/// already in the requested format
+pub fn save_base64_image(
+    image_data: &str,
+    save_dir: &str,
+    prefix: &str,
+    output: &OutputOptions,
+) -> AppResult<SavedImage> {
+    let (format, image_bytes) = decode_data_url(image_data)?;
+
+    if output.keeps_encoded(format) {
+        return write_encoded(image_bytes, save_dir, prefix, format, output);
+    }
 
-    Ok(file_path.to_string_lossy().into_owned())
+    let img = decode_image_bytes(format, &image_bytes)?;
+    save_image(&img, save_dir, prefix, output)
 }
 
-/// Copy a screenshot file to a destination directory
-pub fn copy_screenshot_to_dir(source_path: &str, save_dir: &str) -> AppResult<String> {
+/// Save an image file to a directory in the requested format, copying its
+/// bytes when it is already in that format
+pub fn save_image_file(
+    source_path: &str,
+    save_dir: &str,
+    prefix: &str,
+    output: &OutputOptions,
+) -> AppResult<SavedImage> {
     let src_path = PathBuf::from(source_path);
     if !src_path.exists() {
-        return Err(format!("Screenshot file not found: {}", source_path));
+        return Err(format!("Image file not found: {}", source_path));
     }
 
-    let dest_path = PathBuf::from(save_dir);
-    ensure_dir(&dest_path)?;
-
-    let filename = generate_filename("shot", "png")?;
-    let file_path = dest_path.join(&filename);
-
-    fs::copy(&src_path, &file_path).map_err(|e| format!("Failed to copy screenshot: {}", e))?;
+    let source_format = OutputFormat::from_path(source_path);
+    if source_format.is_some_and(|format| output.keeps_encoded(format)) {
+        let bytes = fs::read(&src_path).map_err(|e| format!("Failed to copy image: {}", e))?;
+        return write_encoded(bytes, save_dir, prefix, output.format, output);
+    }
 
-    Ok(file_path.to_string_lossy().into_owned())
+    ensure_decodable(source_path)?;
+    let img = image::open(&src_path).map_err(|e| format!("Failed to open image: {}", e))?;
+    save_image(&img, save_dir, prefix, output)
 }
 
 #[derive(Debug, Clone, serde::Deserialize)]
-#[allow(dead_code)]
 pub struct RenderSettings {
     pub background_type: String,
     pub custom_color: String,
@@ -144,9 +381,95 @@ pub struct RenderSettings {
     pub shadow_offset_x: f32,
     pub shadow_offset_y: f32,
     pub shadow_opacity: f32,
+    /// Path of the wallpaper used by the `image` background type
+    /// (and by `gradient` when no gradient definition is given)
+    #[serde(default)]
+    pub background_image: Option<String>,
+    #[serde(default)]
+    pub background_fit: BackgroundFit,
+    /// Gradient definition used by the `gradient` background type
+    #[serde(default)]
+    pub gradient: Option<Gradient>,
+    /// Width of the outline drawn along the inside edge of the screenshot; 0 disables it
+    #[serde(default)]
+    pub border_width: f32,
+    #[serde(default = "default_border_color")]
+    pub border_color: String,
+    #[serde(default = "default_border_opacity")]
+    pub border_opacity: f32,
+}
+
+/// How a background image is scaled onto the background canvas
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackgroundFit {
+    /// Stretch to the canvas size, like the editor's canvas renderer
+    #[default]
+    Stretch,
+    /// Scale to fill the canvas, cropping the overflow
+    Cover,
+    /// Scale to fit inside the canvas, leaving the rest transparent
+    Contain,
+    /// Repeat at the image's native size
+    Tile,
+}
+
+/// A color stop of a linear or radial gradient
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct GradientStop {
+    /// Position along the gradient, from 0.0 to 1.0
+    pub offset: f32,
+    pub color: String,
+}
+
+/// A colored control point of a mesh gradient, in relative (0.0-1.0) coordinates
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct MeshPoint {
+    pub x: f32,
+    pub y: f32,
+    pub color: String,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum Gradient {
+    /// CSS-style linear gradient; `angle` is in degrees, 0 pointing up and 90 pointing right
+    Linear {
+        #[serde(default)]
+        angle: f32,
+        stops: Vec<GradientStop>,
+    },
+    /// Radial gradient around a relative center; a `radius` of 1.0 reaches the farthest corner
+    Radial {
+        #[serde(default = "default_center")]
+        center_x: f32,
+        #[serde(default = "default_center")]
+        center_y: f32,
+        #[serde(default = "default_radius")]
+        radius: f32,
+        stops: Vec<GradientStop>,
+    },
+    /// Smooth blend between freely placed color points
+    Mesh { points: Vec<MeshPoint> },
+}
+
+fn default_border_color() -> String {
+    "#ffffff".to_string()
+}
+
+fn default_border_opacity() -> f32 {
+    100.0
+}
+
+fn default_center() -> f32 {
+    0.5
+}
+
+fn default_radius() -> f32 {
+    1.0
 }
 
-fn hex_to_rgba(hex: &str) -> Result<Rgba<u8>, String> {
+pub(crate) fn hex_to_rgba(hex: &str) -> Result<Rgba<u8>, String> {
     let hex = hex.trim_start_matches('#');
     if hex.len() != 6 {
         return Err("Invalid hex color".to_string());
@@ -159,49 +482,244 @@ fn hex_to_rgba(hex: &str) -> Result<Rgba<u8>, String> {
     Ok(Rgba([r, g, b, 255]))
 }
 
-fn create_background(
+fn fill(img: &mut RgbaImage, color: Rgba<u8>) {
+    for pixel in img.pixels_mut() {
+        *pixel = color;
+    }
+}
+
+fn lerp_color(a: Rgba<u8>, b: Rgba<u8>, t: f32) -> Rgba<u8> {
+    let mut out = [0u8; 4];
+    for (c, value) in out.iter_mut().enumerate() {
+        let mixed = a[c] as f32 + (b[c] as f32 - a[c] as f32) * t;
+        *value = mixed.round().clamp(0.0, 255.0) as u8;
+    }
+    Rgba(out)
+}
+
+/// Parse and sort gradient stops
+fn parse_stops(stops: &[GradientStop]) -> AppResult<Vec<(f32, Rgba<u8>)>> {
+    if stops.is_empty() {
+        return Err("Gradient needs at least one color stop".to_string());
+    }
+
+    let mut parsed = stops
+        .iter()
+        .map(|stop| Ok((stop.offset.clamp(0.0, 1.0), hex_to_rgba(&stop.color)?)))
+        .collect::<AppResult<Vec<_>>>()?;
+    parsed.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    Ok(parsed)
+}
+
+/// Sample sorted gradient stops at position `t`
+fn sample_stops(stops: &[(f32, Rgba<u8>)], t: f32) -> Rgba<u8> {
+    let (first_offset, first_color) = stops[0];
+    if t <= first_offset {
+        return first_color;
+    }
+
+    for pair in stops.windows(2) {
+        let (start_offset, start_color) = pair[0];
+        let (end_offset, end_color) = pair[1];
+        if t <= end_offset {
+            let span = end_offset - start_offset;
+            if span <= f32::EPSILON {
+                return end_color;
+            }
+            return lerp_color(start_color, end_color, (t - start_offset) / span);
+        }
+    }
+
+    stops[stops.len() - 1].1
+}
+
+fn render_gradient(width: u32, height: u32, gradient: &Gradient) -> AppResult<RgbaImage> {
+    let mut img = RgbaImage::new(width, height);
+    let w = width as f32;
+    let h = height as f32;
+
+    match gradient {
+        Gradient::Linear { angle, stops } => {
+            let stops = parse_stops(stops)?;
+            let (sin, cos) = angle.to_radians().sin_cos();
+            // Gradient line length as defined by CSS, so corners get the end colors
+            let length = (w * sin).abs() + (h * cos).abs();
+
+            for (x, y, pixel) in img.enumerate_pixels_mut() {
+                let dx = x as f32 + 0.5 - w / 2.0;
+                let dy = y as f32 + 0.5 - h / 2.0;
+                let t = if length > 0.0 {
+                    (dx * sin - dy * cos) / length + 0.5
+                } else {
+                    0.0
+                };
+                *pixel = sample_stops(&stops, t);
+            }
+        }
+        Gradient::Radial {
+            center_x,
+            center_y,
+            radius,
+            stops,
+        } => {
+            let stops = parse_stops(stops)?;
+            let cx = center_x * w;
+            let cy = center_y * h;
+            let farthest_x = cx.max(w - cx);
+            let farthest_y = cy.max(h - cy);
+            let extent = (farthest_x * farthest_x + farthest_y * farthest_y).sqrt() * radius;
+
+            for (x, y, pixel) in img.enumerate_pixels_mut() {
+                let dx = x as f32 + 0.5 - cx;
+                let dy = y as f32 + 0.5 - cy;
+                let t = if extent > 0.0 {
+                    (dx * dx + dy * dy).sqrt() / extent
+                } else {
+                    1.0
+                };
+                *pixel = sample_stops(&stops, t);
+            }
+        }
+        Gradient::Mesh { points } => {
+            if points.is_empty() {
+                return Err("Mesh gradient needs at least one point".to_string());
+            }
+
+            let points = points
+                .iter()
+                .map(|p| Ok((p.x * w, p.y * h, hex_to_rgba(&p.color)?)))
+                .collect::<AppResult<Vec<_>>>()?;
+            // Distances are normalized to the diagonal so the blend is resolution independent
+            let diagonal_sq = (w * w + h * h).max(1.0);
+
+            for (x, y, pixel) in img.enumerate_pixels_mut() {
+                let px = x as f32 + 0.5;
+                let py = y as f32 + 0.5;
+                let mut sum = [0.0f32; 4];
+                let mut total_weight = 0.0;
+
+                for (mx, my, color) in &points {
+                    let dist_sq = ((px - mx).powi(2) + (py - my).powi(2)) / diagonal_sq;
+                    // Inverse distance weighting, squared for a soft but distinct falloff
+                    let weight = 1.0 / (dist_sq * dist_sq + 1e-6);
+                    for c in 0..4 {
+                        sum[c] += color[c] as f32 * weight;
+                    }
+                    total_weight += weight;
+                }
+
+                let mut out = [0u8; 4];
+                for c in 0..4 {
+                    out[c] = (sum[c] / total_weight).round().clamp(0.0, 255.0) as u8;
+                }
+                *pixel = Rgba(out);
+            }
+        }
+    }
+
+    Ok(img)
+}
+
+/// Scale a background image onto a canvas of the given size
+fn fit_background_image(
+    source: &RgbaImage,
     width: u32,
     height: u32,
-    background_type: &str,
-    custom_color: &str,
+    fit: BackgroundFit,
 ) -> RgbaImage {
+    let (src_width, src_height) = source.dimensions();
+    if src_width == 0 || src_height == 0 || width == 0 || height == 0 {
+        return RgbaImage::new(width, height);
+    }
+
+    match fit {
+        BackgroundFit::Stretch => resize(source, width, height, FilterType::Triangle),
+        BackgroundFit::Cover | BackgroundFit::Contain => {
+            let scale_x = width as f32 / src_width as f32;
+            let scale_y = height as f32 / src_height as f32;
+            let scale = if fit == BackgroundFit::Cover {
+                scale_x.max(scale_y)
+            } else {
+                scale_x.min(scale_y)
+            };
+
+            let scaled_width = ((src_width as f32 * scale).round() as u32).max(1);
+            let scaled_height = ((src_height as f32 * scale).round() as u32).max(1);
+            let scaled = resize(source, scaled_width, scaled_height, FilterType::Triangle);
+
+            let mut canvas = RgbaImage::new(width, height);
+            let offset_x = (width as i64 - scaled_width as i64) / 2;
+            let offset_y = (height as i64 - scaled_height as i64) / 2;
+            overlay(&mut canvas, &scaled, offset_x, offset_y);
+            canvas
+        }
+        BackgroundFit::Tile => RgbaImage::from_fn(width, height, |x, y| {
+            *source.get_pixel(x % src_width, y % src_height)
+        }),
+    }
+}
+
+/// Load the configured background image, falling back to white when none is set
+fn load_background_image(
+    width: u32,
+    height: u32,
+    settings: &RenderSettings,
+) -> AppResult<RgbaImage> {
+    match &settings.background_image {
+        Some(path) => {
+            let source = image::open(path)
+                .map_err(|e| format!("Failed to open background image: {}", e))?
+                .to_rgba8();
+            Ok(fit_background_image(
+                &source,
+                width,
+                height,
+                settings.background_fit,
+            ))
+        }
+        None => Ok(RgbaImage::from_pixel(
+            width,
+            height,
+            Rgba([255, 255, 255, 255]),
+        )),
+    }
+}
+
+fn create_background(width: u32, height: u32, settings: &RenderSettings) -> AppResult<RgbaImage> {
     let mut img = RgbaImage::new(width, height);
 
-    match background_type {
+    match settings.background_type.as_str() {
         "transparent" => {
-            for pixel in img.pixels_mut() {
-                *pixel = Rgba([0, 0, 0, 0]);
-            }
+            fill(&mut img, Rgba([0, 0, 0, 0]));
         }
         "white" => {
-            for pixel in img.pixels_mut() {
-                *pixel = Rgba([255, 255, 255, 255]);
-            }
+            fill(&mut img, Rgba([255, 255, 255, 255]));
         }
         "black" => {
-            for pixel in img.pixels_mut() {
-                *pixel = Rgba([0, 0, 0, 255]);
-            }
+            fill(&mut img, Rgba([0, 0, 0, 255]));
         }
         "gray" => {
-            for pixel in img.pixels_mut() {
-                *pixel = Rgba([245, 245, 245, 255]);
-            }
+            fill(&mut img, Rgba([245, 245, 245, 255]));
         }
         "custom" => {
-            let color = hex_to_rgba(custom_color).unwrap_or(Rgba([255, 255, 255, 255]));
-            for pixel in img.pixels_mut() {
-                *pixel = color;
-            }
+            let color = hex_to_rgba(&settings.custom_color).unwrap_or(Rgba([255, 255, 255, 255]));
+            fill(&mut img, color);
+        }
+        "image" => {
+            img = load_background_image(width, height, settings)?;
         }
+        "gradient" => match &settings.gradient {
+            Some(gradient) => img = render_gradient(width, height, gradient)?,
+            // Gradients shipped as pre-rendered images
+            None => img = load_background_image(width, height, settings)?,
+        },
         _ => {
-            for pixel in img.pixels_mut() {
-                *pixel = Rgba([255, 255, 255, 255]);
-            }
+            fill(&mut img, Rgba([255, 255, 255, 255]));
         }
     }
 
-    img
+    Ok(img)
 }
 
 fn apply_noise(img: &mut RgbaImage, amount: f32) {
@@ -222,20 +740,175 @@ fn apply_noise(img: &mut RgbaImage, amount: f32) {
     }
 }
 
-pub fn render_image_with_effects(image_path: &str, settings: RenderSettings) -> AppResult<String> {
-    let img = image::open(image_path).map_err(|e| format!("Failed to open image: {}", e))?;
+/// Signed distance from a point to the edge of a rounded rect anchored at the origin.
+/// Negative inside, positive outside.
+fn rounded_rect_distance(px: f32, py: f32, width: f32, height: f32, radius: f32) -> f32 {
+    let half_width = width / 2.0;
+    let half_height = height / 2.0;
+    let qx = (px - half_width).abs() - (half_width - radius);
+    let qy = (py - half_height).abs() - (half_height - radius);
+
+    let outside = (qx.max(0.0).powi(2) + qy.max(0.0).powi(2)).sqrt();
+    let inside = qx.max(qy).min(0.0);
+    outside + inside - radius
+}
 
-    let img_width = img.width();
-    let img_height = img.height();
+/// Fraction of a pixel covered by a shape, given the signed distance at its center
+pub(crate) fn coverage_from_distance(distance: f32) -> f32 {
+    (0.5 - distance).clamp(0.0, 1.0)
+}
+
+/// Like canvas `roundRect`, the radius can't exceed half of the shorter side
+fn clamp_radius(width: u32, height: u32, border_radius: f32) -> f32 {
+    border_radius.max(0.0).min(width.min(height) as f32 / 2.0)
+}
+
+/// Build an anti-aliased coverage mask for the rounded screenshot rect
+fn rounded_rect_mask(width: u32, height: u32, border_radius: f32) -> GrayImage {
+    let radius = clamp_radius(width, height, border_radius);
+    let (w, h) = (width as f32, height as f32);
+
+    GrayImage::from_fn(width, height, |x, y| {
+        let distance = rounded_rect_distance(x as f32 + 0.5, y as f32 + 0.5, w, h, radius);
+        Luma([(coverage_from_distance(distance) * 255.0).round() as u8])
+    })
+}
+
+/// Build an anti-aliased coverage mask for a stroke running along the inside
+/// edge of the rounded screenshot rect
+fn rounded_rect_stroke_mask(
+    width: u32,
+    height: u32,
+    border_radius: f32,
+    stroke_width: f32,
+) -> GrayImage {
+    let radius = clamp_radius(width, height, border_radius);
+    let (w, h) = (width as f32, height as f32);
+
+    GrayImage::from_fn(width, height, |x, y| {
+        let distance = rounded_rect_distance(x as f32 + 0.5, y as f32 + 0.5, w, h, radius);
+        let outer = coverage_from_distance(distance);
+        let inner = coverage_from_distance(distance + stroke_width);
+        Luma([((outer - inner) * 255.0).round() as u8])
+    })
+}
+
+/// Composite `src` over `dst` with an extra coverage factor in 0..=1
+pub(crate) fn blend_pixel(dst: &mut Rgba<u8>, src: &Rgba<u8>, coverage: f32) {
+    let src_a = (src[3] as f32 / 255.0) * coverage;
+    if src_a <= 0.0 {
+        return;
+    }
+
+    let dst_a = dst[3] as f32 / 255.0;
+    let out_a = src_a + dst_a * (1.0 - src_a);
+    if out_a <= 0.0 {
+        *dst = Rgba([0, 0, 0, 0]);
+        return;
+    }
+
+    for c in 0..3 {
+        let value = (src[c] as f32 * src_a + dst[c] as f32 * dst_a * (1.0 - src_a)) / out_a;
+        dst[c] = value.round().clamp(0.0, 255.0) as u8;
+    }
+    dst[3] = (out_a * 255.0).round().clamp(0.0, 255.0) as u8;
+}
+
+/// Draw a soft drop shadow of the (masked) screenshot onto the background.
+///
+/// Mirrors the canvas `shadowBlur`/`shadowOffset*` semantics: the shadow
+/// takes the shape of the clipped screenshot's alpha and is blurred with a
+/// gaussian whose standard deviation is half of `shadow_blur`.
+fn draw_shadow(
+    background: &mut RgbaImage,
+    img_rgba: &RgbaImage,
+    mask: &GrayImage,
+    settings: &RenderSettings,
+) {
+    let opacity = (settings.shadow_opacity / 100.0).clamp(0.0, 1.0);
+    if opacity <= 0.0 {
+        return;
+    }
+
+    let sigma = settings.shadow_blur.max(0.0) / 2.0;
+    // Extra room so the blur isn't clamped at the background edges
+    let margin = (sigma * 3.0).ceil() as i64;
+
+    let (bg_width, bg_height) = background.dimensions();
+    let layer_width = bg_width as i64 + margin * 2;
+    let layer_height = bg_height as i64 + margin * 2;
+    let mut layer = GrayImage::new(layer_width as u32, layer_height as u32);
+
+    let origin_x = settings.padding_left as i64 + settings.shadow_offset_x.round() as i64 + margin;
+    let origin_y = settings.padding_top as i64 + settings.shadow_offset_y.round() as i64 + margin;
+
+    for (x, y, pixel) in img_rgba.enumerate_pixels() {
+        let lx = origin_x + x as i64;
+        let ly = origin_y + y as i64;
+        if lx < 0 || ly < 0 || lx >= layer_width || ly >= layer_height {
+            continue;
+        }
+
+        let coverage = mask.get_pixel(x, y)[0] as u32 * pixel[3] as u32 / 255;
+        layer.put_pixel(lx as u32, ly as u32, Luma([coverage as u8]));
+    }
+
+    if sigma > 0.0 {
+        layer = gaussian_blur_f32(&layer, sigma);
+    }
+
+    let shadow_color = Rgba([0, 0, 0, 255]);
+    for (x, y, pixel) in background.enumerate_pixels_mut() {
+        let alpha = layer.get_pixel(x + margin as u32, y + margin as u32)[0];
+        if alpha > 0 {
+            blend_pixel(pixel, &shadow_color, alpha as f32 / 255.0 * opacity);
+        }
+    }
+}
+
+/// Stroke the inside edge of the rounded screenshot rect with the border settings
+fn draw_border(
+    background: &mut RgbaImage,
+    img_width: u32,
+    img_height: u32,
+    settings: &RenderSettings,
+) {
+    let opacity = (settings.border_opacity / 100.0).clamp(0.0, 1.0);
+    if settings.border_width <= 0.0 || opacity <= 0.0 {
+        return;
+    }
+
+    let color = hex_to_rgba(&settings.border_color).unwrap_or(Rgba([255, 255, 255, 255]));
+    let stroke = rounded_rect_stroke_mask(
+        img_width,
+        img_height,
+        settings.border_radius,
+        settings.border_width,
+    );
+
+    for (img_x, img_y, coverage) in stroke.enumerate_pixels() {
+        if coverage[0] == 0 {
+            continue;
+        }
+
+        let bg_pixel =
+            background.get_pixel_mut(img_x + settings.padding_left, img_y + settings.padding_top);
+        blend_pixel(bg_pixel, &color, coverage[0] as f32 / 255.0 * opacity);
+    }
+}
+
+/// Background for an image of `img_width`x`img_height`, padded, blurred and
+/// with noise applied. Noise is random, so callers rendering several images
+/// onto the same background render it once and reuse it.
+pub fn render_background(
+    img_width: u32,
+    img_height: u32,
+    settings: &RenderSettings,
+) -> AppResult<RgbaImage> {
     let bg_width = img_width + settings.padding_left + settings.padding_right;
     let bg_height = img_height + settings.padding_top + settings.padding_bottom;
 
-    let mut background = create_background(
-        bg_width,
-        bg_height,
-        &settings.background_type,
-        &settings.custom_color,
-    );
+    let mut background = create_background(bg_width, bg_height, settings)?;
 
     if settings.blur_amount > 0.0 {
         background = gaussian_blur_f32(&background, settings.blur_amount);
@@ -245,106 +918,442 @@ pub fn render_image_with_effects(image_path: &str, settings: RenderSettings) ->
         apply_noise(&mut background, settings.noise_amount);
     }
 
-    let img_rgba = img.to_rgba8();
-    let mut final_img = RgbaImage::new(bg_width, bg_height);
-
-    for y in 0..bg_height {
-        for x in 0..bg_width {
-            if x >= settings.padding_left
-                && x < settings.padding_left + img_width
-                && y >= settings.padding_top
-                && y < settings.padding_top + img_height
-            {
-                let img_x = x - settings.padding_left;
-                let img_y = y - settings.padding_top;
-
-                let corner_x = if img_x < settings.border_radius as u32 {
-                    img_x
-                } else if img_x >= img_width.saturating_sub(settings.border_radius as u32) {
-                    img_width - img_x - 1
-                } else {
-                    u32::MAX
-                };
-
-                let corner_y = if img_y < settings.border_radius as u32 {
-                    img_y
-                } else if img_y >= img_height.saturating_sub(settings.border_radius as u32) {
-                    img_height - img_y - 1
-                } else {
-                    u32::MAX
-                };
+    Ok(background)
+}
 
-                let in_corner = corner_x < settings.border_radius as u32
-                    && corner_y < settings.border_radius as u32;
+/// Render the screenshot onto its background with all effects applied
+pub fn render_effects(img: &DynamicImage, settings: &RenderSettings) -> AppResult<RgbaImage> {
+    let background = render_background(img.width(), img.height(), settings)?;
+    Ok(render_onto_background(img, settings, background))
+}
 
-                if in_corner {
-                    let dist_x = corner_x as f32;
-                    let dist_y = corner_y as f32;
-                    let corner_dist = (dist_x * dist_x + dist_y * dist_y).sqrt();
+/// Render the screenshot onto a background from `render_background` with
+/// the remaining effects applied
+pub fn render_onto_background(
+    img: &DynamicImage,
+    settings: &RenderSettings,
+    mut background: RgbaImage,
+) -> RgbaImage {
+    let img_width = img.width();
+    let img_height = img.height();
+    let img_rgba = img.to_rgba8();
+    let mask = rounded_rect_mask(img_width, img_height, settings.border_radius);
+
+    // Like the canvas renderer, a shadow only makes sense when there is padding to cast it on
+    let total_padding = settings.padding_top
+        + settings.padding_bottom
+        + settings.padding_left
+        + settings.padding_right;
+    if total_padding > 0 {
+        draw_shadow(&mut background, &img_rgba, &mask, settings);
+    }
 
-                    if corner_dist <= settings.border_radius {
-                        let pixel = img_rgba.get_pixel(img_x, img_y);
-                        final_img.put_pixel(x, y, *pixel);
-                    } else {
-                        let bg_pixel = background.get_pixel(x, y);
-                        final_img.put_pixel(x, y, *bg_pixel);
-                    }
-                } else {
-                    let pixel = img_rgba.get_pixel(img_x, img_y);
-                    final_img.put_pixel(x, y, *pixel);
-                }
-            } else {
-                let bg_pixel = background.get_pixel(x, y);
-                final_img.put_pixel(x, y, *bg_pixel);
-            }
+    for (img_x, img_y, pixel) in img_rgba.enumerate_pixels() {
+        let coverage = mask.get_pixel(img_x, img_y)[0];
+        if coverage == 0 {
+            continue;
         }
+
+        let bg_pixel =
+            background.get_pixel_mut(img_x + settings.padding_left, img_y + settings.padding_top);
+        blend_pixel(bg_pixel, pixel, coverage as f32 / 255.0);
     }
 
-    let mut buffer = Vec::new();
-    let mut cursor = std::io::Cursor::new(&mut buffer);
-    DynamicImage::ImageRgba8(final_img)
-        .write_to(&mut cursor, ImageFormat::Png)
-        .map_err(|e| format!("Failed to encode image: {}", e))?;
+    draw_border(&mut background, img_width, img_height, settings);
+
+    background
+}
+
+/// Render an image with effects and return it as a PNG data URL. Previews
+/// stay lossless; the output format is applied once, when they are saved.
+pub fn render_image_with_effects(image_path: &str, settings: RenderSettings) -> AppResult<String> {
+    let img = image::open(image_path).map_err(|e| format!("Failed to open image: {}", e))?;
+
+    let final_img = render_effects(&img, &settings)?;
+
+    let buffer = encode_image(
+        &DynamicImage::ImageRgba8(final_img),
+        &OutputOptions::default(),
+    )?;
 
     let base64_data = general_purpose::STANDARD.encode(&buffer);
-    Ok(format!("data:image/png;base64,{}", base64_data))
+    Ok(format!(
+        "data:{};base64,{}",
+        OutputFormat::Png.mime_type(),
+        base64_data
+    ))
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+/// Smallest mosaic block used when redacting; smaller blocks leave text legible
+const MIN_REDACTION_BLOCK: u32 = 8;
 
-    mod crop_region {
-        use super::*;
+/// Smallest gaussian sigma used when redacting
+const MIN_REDACTION_SIGMA: f32 = 8.0;
 
-        #[test]
-        fn test_crop_region_clamped_within_bounds() {
-            let region = CropRegion::clamped(100, 100, 200, 200, 1920, 1080);
+/// Shape of a redacted area within its bounding box
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RedactionShape {
+    #[default]
+    Rectangle,
+    Ellipse,
+}
 
-            assert_eq!(region.x, 100);
-            assert_eq!(region.y, 100);
-            assert_eq!(region.width, 200);
-            assert_eq!(region.height, 200);
-        }
+/// How the pixels of a redacted area are replaced
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+#[serde(tag = "method", rename_all = "lowercase")]
+pub enum RedactionMethod {
+    /// Gaussian blur over a coarse mosaic; `radius` is raised to a safe minimum
+    Blur { radius: f32 },
+    /// Mosaic of flat blocks; `block_size` is raised to a safe minimum
+    Pixelate { block_size: u32 },
+    /// Solid color, accepts `#rrggbb`
+    Fill { color: String },
+}
 
-        #[test]
-        fn test_crop_region_clamped_exceeds_bounds() {
-            // Region that exceeds image bounds
-            let region = CropRegion::clamped(1800, 1000, 500, 500, 1920, 1080);
+/// An area of an image to redact, in image pixel coordinates
+#[derive(Debug, Clone, PartialEq, serde::Deserialize)]
+pub struct RedactionRegion {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    #[serde(default)]
+    pub shape: RedactionShape,
+    #[serde(flatten)]
+    pub method: RedactionMethod,
+}
 
-            assert_eq!(region.x, 1800);
-            assert_eq!(region.y, 1000);
-            assert_eq!(region.width, 120); // 1920 - 1800 = 120
-            assert_eq!(region.height, 80); // 1080 - 1000 = 80
+/// Replace every pixel with the average of its block, aligned to the region origin
+fn mosaic(img: &RgbaImage, block_size: u32) -> RgbaImage {
+    let (width, height) = img.dimensions();
+    let mut out = RgbaImage::new(width, height);
+
+    for block_y in (0..height).step_by(block_size as usize) {
+        for block_x in (0..width).step_by(block_size as usize) {
+            let block_width = block_size.min(width - block_x);
+            let block_height = block_size.min(height - block_y);
+
+            let mut sum = [0u64; 4];
+            for y in block_y..block_y + block_height {
+                for x in block_x..block_x + block_width {
+                    let pixel = img.get_pixel(x, y);
+                    for c in 0..4 {
+                        sum[c] += pixel[c] as u64;
+                    }
+                }
+            }
+
+            let count = (block_width * block_height) as u64;
+            let average = Rgba(sum.map(|v| ((v + count / 2) / count) as u8));
+            for y in block_y..block_y + block_height {
+                for x in block_x..block_x + block_width {
+                    out.put_pixel(x, y, average);
+                }
+            }
         }
+    }
 
-        #[test]
-        fn test_crop_region_clamped_x_y_exceed_bounds() {
-            // X and Y exceed image dimensions
-            let region = CropRegion::clamped(2000, 2000, 100, 100, 1920, 1080);
+    out
+}
 
-            assert_eq!(region.x, 1919); // Clamped to img_width - 1
-            assert_eq!(region.y, 1079); // Clamped to img_height - 1
+/// Apply redactions to an image in place.
+///
+/// Redacted pixels are derived only from per-block averages (or a fixed
+/// color), never from individual source pixels, so the result can't be
+/// deconvolved back to the original the way a plain low-radius blur can.
+/// Ellipse edges use a hard mask for the same reason: a soft edge would mix
+/// original pixels into the output.
+pub fn apply_redactions(img: &mut RgbaImage, regions: &[RedactionRegion]) -> AppResult<()> {
+    for region in regions {
+        let x0 = region.x.max(0) as u32;
+        let y0 = region.y.max(0) as u32;
+        let x1 = (region.x as i64 + region.width as i64).clamp(0, img.width() as i64) as u32;
+        let y1 = (region.y as i64 + region.height as i64).clamp(0, img.height() as i64) as u32;
+        if x0 >= x1 || y0 >= y1 {
+            continue;
+        }
+
+        let area = image::imageops::crop_imm(img, x0, y0, x1 - x0, y1 - y0).to_image();
+        let replacement = match &region.method {
+            RedactionMethod::Fill { color } => {
+                RgbaImage::from_pixel(area.width(), area.height(), hex_to_rgba(color)?)
+            }
+            RedactionMethod::Pixelate { block_size } => {
+                mosaic(&area, (*block_size).max(MIN_REDACTION_BLOCK))
+            }
+            RedactionMethod::Blur { radius } => {
+                let sigma = radius.max(MIN_REDACTION_SIGMA);
+                // Blocks about as wide as the blur keep the look smooth while discarding detail
+                let block_size = (sigma.round() as u32).max(MIN_REDACTION_BLOCK);
+                gaussian_blur_f32(&mosaic(&area, block_size), sigma)
+            }
+        };
+
+        let center_x = (region.x as f32) + region.width as f32 / 2.0;
+        let center_y = (region.y as f32) + region.height as f32 / 2.0;
+        let radius_x = region.width as f32 / 2.0;
+        let radius_y = region.height as f32 / 2.0;
+
+        for (rx, ry, pixel) in replacement.enumerate_pixels() {
+            let x = x0 + rx;
+            let y = y0 + ry;
+            if region.shape == RedactionShape::Ellipse {
+                let dx = (x as f32 + 0.5 - center_x) / radius_x;
+                let dy = (y as f32 + 0.5 - center_y) / radius_y;
+                if dx * dx + dy * dy > 1.0 {
+                    continue;
+                }
+            }
+            img.put_pixel(x, y, *pixel);
+        }
+    }
+
+    Ok(())
+}
+
+/// Open an image, redact the given regions and save the result
+pub fn redact_image(
+    image_path: &str,
+    regions: &[RedactionRegion],
+    save_dir: &str,
+    output: &OutputOptions,
+) -> AppResult<SavedImage> {
+    let mut img = image::open(image_path)
+        .map_err(|e| format!("Failed to open image: {}", e))?
+        .to_rgba8();
+
+    apply_redactions(&mut img, regions)?;
+
+    save_image(&DynamicImage::ImageRgba8(img), save_dir, "redacted", output)
+}
+
+/// Rows two consecutive scrolling frames must share to be stitched
+const MIN_SCROLL_OVERLAP: u32 = 8;
+
+/// Mean per-pixel luma difference above which an overlap is rejected
+const MAX_SCROLL_MATCH_ERROR: f64 = 8.0;
+
+/// Rows and columns sampled per overlap when matching scrolling frames
+const SCROLL_MATCH_SAMPLES: usize = 256;
+
+/// Grayscale copy of an image as one byte vector per row
+fn luma_rows(img: &RgbaImage) -> Vec<Vec<u8>> {
+    let gray = DynamicImage::ImageRgba8(img.clone()).to_luma8();
+    gray.rows()
+        .map(|row| row.map(|p| p.0[0]).collect())
+        .collect()
+}
+
+/// Count rows that are identical at the top and bottom of two frames
+fn sticky_rows(a: &RgbaImage, b: &RgbaImage) -> (u32, u32) {
+    let stride = a.width() as usize * 4;
+    let rows_a = a.as_raw().chunks_exact(stride);
+    let rows_b = b.as_raw().chunks_exact(stride);
+
+    let header = rows_a
+        .clone()
+        .zip(rows_b.clone())
+        .take_while(|(ra, rb)| ra == rb)
+        .count() as u32;
+    let footer = rows_a
+        .rev()
+        .zip(rows_b.rev())
+        .take_while(|(ra, rb)| ra == rb)
+        .count() as u32;
+
+    (header, footer)
+}
+
+/// Evenly spaced indices covering `0..len`, at most `samples` of them
+fn sample_indices(len: usize, samples: usize) -> Vec<usize> {
+    let step = len.div_ceil(samples).max(1);
+    (0..len).step_by(step).collect()
+}
+
+/// Find how many rows `next` scrolled past `prev`.
+///
+/// Both slices are the scrolling body of a frame. Returns the scroll offset
+/// with the lowest mean difference over the overlapping rows, preferring
+/// smaller offsets on ties, or `None` if nothing overlaps well enough.
+fn find_scroll_offset(prev: &[Vec<u8>], next: &[Vec<u8>]) -> Option<usize> {
+    let height = prev.len().min(next.len());
+    let width = prev.first().map_or(0, |r| r.len());
+    let min_overlap = MIN_SCROLL_OVERLAP as usize;
+    if height < min_overlap || width == 0 {
+        return None;
+    }
+
+    let columns = sample_indices(width, SCROLL_MATCH_SAMPLES);
+    let mut best: Option<(usize, f64)> = None;
+
+    for offset in 0..=height - min_overlap {
+        let overlap = height - offset;
+        let rows = sample_indices(overlap, SCROLL_MATCH_SAMPLES);
+        let budget = best.map_or(f64::MAX, |(_, e)| e) * (rows.len() * columns.len()) as f64;
+
+        let mut total = 0.0;
+        for &y in &rows {
+            let (a, b) = (&prev[offset + y], &next[y]);
+            total += columns
+                .iter()
+                .map(|&x| a[x].abs_diff(b[x]) as f64)
+                .sum::<f64>();
+            if total > budget {
+                break;
+            }
+        }
+
+        let error = total / (rows.len() * columns.len()) as f64;
+        if best.is_none_or(|(_, e)| error < e) {
+            best = Some((offset, error));
+        }
+    }
+
+    best.filter(|&(_, error)| error <= MAX_SCROLL_MATCH_ERROR)
+        .map(|(offset, _)| offset)
+}
+
+/// Stitch frames of a region captured while scrolling down into one tall image.
+///
+/// Consecutive frames must overlap. Rows that stay put between frames, such as
+/// sticky headers and footers, are detected and kept only once, at the top and
+/// bottom of the result. Frames that didn't scroll are skipped.
+pub fn stitch_scrolling_frames(frames: &[RgbaImage]) -> AppResult<RgbaImage> {
+    let first = frames.first().ok_or("No frames to stitch")?;
+    let (width, height) = first.dimensions();
+    if frames.iter().any(|f| f.dimensions() != (width, height)) {
+        return Err("All scrolling frames must have the same size".to_string());
+    }
+
+    let mut distinct: Vec<&RgbaImage> = vec![first];
+    for frame in &frames[1..] {
+        if frame.as_raw() != distinct[distinct.len() - 1].as_raw() {
+            distinct.push(frame);
+        }
+    }
+    if distinct.len() == 1 {
+        return Ok(first.clone());
+    }
+
+    // Sticky bars stay in place in every frame; cap them so a mostly static
+    // page still leaves room to match scrolling content
+    let (mut header, mut footer) = distinct
+        .windows(2)
+        .map(|pair| sticky_rows(pair[0], pair[1]))
+        .fold((height, height), |(h, f), (ph, pf)| (h.min(ph), f.min(pf)));
+    if header + footer + MIN_SCROLL_OVERLAP * 2 > height {
+        header = 0;
+        footer = 0;
+    }
+    let body = header as usize..(height - footer) as usize;
+
+    // Offsets are relative to the previous distinct frame's body
+    let luma: Vec<Vec<Vec<u8>>> = distinct.iter().map(|f| luma_rows(f)).collect();
+    let mut offsets = Vec::with_capacity(distinct.len() - 1);
+    for (i, pair) in luma.windows(2).enumerate() {
+        let offset = find_scroll_offset(&pair[0][body.clone()], &pair[1][body.clone()])
+            .ok_or_else(|| {
+                format!(
+                    "Could not find the overlap between frames {} and {}",
+                    i + 1,
+                    i + 2
+                )
+            })?;
+        offsets.push(offset as u32);
+    }
+
+    let body_height = height - header - footer;
+    let total_height = height + offsets.iter().sum::<u32>();
+    let mut output = RgbaImage::new(width, total_height);
+
+    // Header and the whole body of the first frame
+    overlay(
+        &mut output,
+        &image::imageops::crop_imm(first, 0, 0, width, header + body_height).to_image(),
+        0,
+        0,
+    );
+
+    // Each later frame contributes the rows that scrolled into view
+    let mut y = header + body_height;
+    for (frame, &offset) in distinct[1..].iter().zip(&offsets) {
+        if offset == 0 {
+            continue;
+        }
+        let fresh =
+            image::imageops::crop_imm(*frame, 0, header + body_height - offset, width, offset);
+        overlay(&mut output, &fresh.to_image(), 0, y as i64);
+        y += offset;
+    }
+
+    // Footer from the last frame
+    let last = distinct[distinct.len() - 1];
+    let footer_rows = image::imageops::crop_imm(last, 0, height - footer, width, footer);
+    overlay(&mut output, &footer_rows.to_image(), 0, y as i64);
+
+    Ok(output)
+}
+
+/// Load scrolling frames from disk, stitch them and save the result
+pub fn stitch_scrolling_capture(
+    frame_paths: &[String],
+    save_dir: &str,
+    output: &OutputOptions,
+) -> AppResult<SavedImage> {
+    let frames = frame_paths
+        .iter()
+        .map(|path| {
+            image::open(path)
+                .map(|img| img.to_rgba8())
+                .map_err(|e| format!("Failed to open image: {}", e))
+        })
+        .collect::<AppResult<Vec<_>>>()?;
+
+    let stitched = stitch_scrolling_frames(&frames)?;
+    save_image(
+        &DynamicImage::ImageRgba8(stitched),
+        save_dir,
+        "scrolling",
+        output,
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    mod crop_region {
+        use super::*;
+
+        #[test]
+        fn test_crop_region_clamped_within_bounds() {
+            let region = CropRegion::clamped(100, 100, 200, 200, 1920, 1080);
+
+            assert_eq!(region.x, 100);
+            assert_eq!(region.y, 100);
+            assert_eq!(region.width, 200);
+            assert_eq!(region.height, 200);
+        }
+
+        #[test]
+        fn test_crop_region_clamped_exceeds_bounds() {
+            // Region that exceeds image bounds
+            let region = CropRegion::clamped(1800, 1000, 500, 500, 1920, 1080);
+
+            assert_eq!(region.x, 1800);
+            assert_eq!(region.y, 1000);
+            assert_eq!(region.width, 120); // 1920 - 1800 = 120
+            assert_eq!(region.height, 80); // 1080 - 1000 = 80
+        }
+
+        #[test]
+        fn test_crop_region_clamped_x_y_exceed_bounds() {
+            // X and Y exceed image dimensions
+            let region = CropRegion::clamped(2000, 2000, 100, 100, 1920, 1080);
+
+            assert_eq!(region.x, 1919); // Clamped to img_width - 1
+            assert_eq!(region.y, 1079); // Clamped to img_height - 1
             assert_eq!(region.width, 1); // Only 1 pixel available
             assert_eq!(region.height, 1); // Only 1 pixel available
         }
@@ -399,6 +1408,983 @@ mod tests {
         }
     }
 
+    mod render {
+        use super::*;
+
+        pub(super) fn settings(padding: u32) -> RenderSettings {
+            RenderSettings {
+                background_type: "white".to_string(),
+                custom_color: "#ffffff".to_string(),
+                blur_amount: 0.0,
+                noise_amount: 0.0,
+                border_radius: 0.0,
+                padding_top: padding,
+                padding_bottom: padding,
+                padding_left: padding,
+                padding_right: padding,
+                shadow_blur: 0.0,
+                shadow_offset_x: 0.0,
+                shadow_offset_y: 0.0,
+                shadow_opacity: 0.0,
+                background_image: None,
+                background_fit: BackgroundFit::Stretch,
+                gradient: None,
+                border_width: 0.0,
+                border_color: "#ffffff".to_string(),
+                border_opacity: 100.0,
+            }
+        }
+
+        fn solid_image(width: u32, height: u32, color: Rgba<u8>) -> DynamicImage {
+            DynamicImage::ImageRgba8(RgbaImage::from_pixel(width, height, color))
+        }
+
+        #[test]
+        fn test_render_without_shadow_keeps_background() {
+            let img = solid_image(20, 20, Rgba([255, 0, 0, 255]));
+            let out = render_effects(&img, &settings(10)).unwrap();
+
+            assert_eq!(out.dimensions(), (40, 40));
+            assert_eq!(*out.get_pixel(5, 5), Rgba([255, 255, 255, 255]));
+            assert_eq!(*out.get_pixel(35, 35), Rgba([255, 255, 255, 255]));
+            assert_eq!(*out.get_pixel(20, 20), Rgba([255, 0, 0, 255]));
+        }
+
+        #[test]
+        fn test_render_hard_shadow_follows_offset() {
+            let img = solid_image(20, 20, Rgba([255, 0, 0, 255]));
+            let mut s = settings(10);
+            s.shadow_offset_x = 5.0;
+            s.shadow_offset_y = 5.0;
+            s.shadow_opacity = 50.0;
+
+            let out = render_effects(&img, &s).unwrap();
+
+            // Below-right of the screenshot is shadowed at half opacity
+            assert_eq!(*out.get_pixel(32, 32), Rgba([128, 128, 128, 255]));
+            // Above-left of the screenshot stays untouched
+            assert_eq!(*out.get_pixel(8, 8), Rgba([255, 255, 255, 255]));
+            // The screenshot itself is drawn on top of the shadow
+            assert_eq!(*out.get_pixel(25, 25), Rgba([255, 0, 0, 255]));
+        }
+
+        #[test]
+        fn test_render_soft_shadow_fades_out() {
+            let img = solid_image(40, 40, Rgba([255, 0, 0, 255]));
+            let mut s = settings(40);
+            s.shadow_blur = 20.0;
+            s.shadow_opacity = 100.0;
+
+            let out = render_effects(&img, &s).unwrap();
+
+            let near = out.get_pixel(81, 60)[0];
+            let far = out.get_pixel(95, 60)[0];
+            let outside = out.get_pixel(119, 60)[0];
+            assert!(near < far, "shadow should be darker next to the image");
+            assert!(far < outside, "shadow should fade with distance");
+            assert_eq!(outside, 255);
+        }
+
+        #[test]
+        fn test_render_shadow_follows_rounded_corners() {
+            let img = solid_image(40, 40, Rgba([255, 0, 0, 255]));
+            let mut s = settings(20);
+            s.border_radius = 16.0;
+            s.shadow_offset_x = 10.0;
+            s.shadow_offset_y = 10.0;
+            s.shadow_opacity = 100.0;
+
+            let out = render_effects(&img, &s).unwrap();
+
+            // Bottom-right corner of the offset shadow is rounded off
+            assert_eq!(*out.get_pixel(69, 69), Rgba([255, 255, 255, 255]));
+            // ...while its straight edge is fully shadowed
+            assert_eq!(*out.get_pixel(69, 50), Rgba([0, 0, 0, 255]));
+        }
+
+        #[test]
+        fn test_render_skips_shadow_without_padding() {
+            let img = solid_image(10, 10, Rgba([255, 0, 0, 255]));
+            let mut s = settings(0);
+            s.background_type = "transparent".to_string();
+            s.border_radius = 4.0;
+            s.shadow_blur = 10.0;
+            s.shadow_opacity = 100.0;
+
+            let out = render_effects(&img, &s).unwrap();
+
+            assert_eq!(out.dimensions(), (10, 10));
+            assert_eq!(*out.get_pixel(0, 0), Rgba([0, 0, 0, 0]));
+            assert_eq!(*out.get_pixel(5, 5), Rgba([255, 0, 0, 255]));
+        }
+    }
+
+    mod corners {
+        use super::*;
+
+        #[test]
+        fn test_mask_without_radius_is_solid() {
+            let mask = rounded_rect_mask(8, 6, 0.0);
+            assert!(mask.pixels().all(|p| p[0] == 255));
+        }
+
+        #[test]
+        fn test_mask_corner_is_cut_and_edges_are_solid() {
+            let mask = rounded_rect_mask(100, 100, 20.0);
+
+            assert_eq!(mask.get_pixel(0, 0)[0], 0);
+            assert_eq!(mask.get_pixel(99, 99)[0], 0);
+            assert_eq!(mask.get_pixel(50, 0)[0], 255);
+            assert_eq!(mask.get_pixel(0, 50)[0], 255);
+            assert_eq!(mask.get_pixel(20, 20)[0], 255);
+        }
+
+        #[test]
+        fn test_mask_corner_is_anti_aliased() {
+            let mask = rounded_rect_mask(200, 200, 60.0);
+
+            // Walk the diagonal into the top-left corner: coverage ramps up
+            // through partial values instead of jumping from 0 to 255
+            let ramp: Vec<u8> = (10..25).map(|i| mask.get_pixel(i, i)[0]).collect();
+            assert!(ramp.windows(2).all(|w| w[0] <= w[1]));
+            assert!(ramp.iter().any(|&c| c > 0 && c < 255));
+            assert_eq!(ramp[0], 0);
+            assert_eq!(ramp[ramp.len() - 1], 255);
+        }
+
+        #[test]
+        fn test_mask_is_symmetric() {
+            let mask = rounded_rect_mask(64, 48, 16.0);
+
+            for y in 0..48 {
+                for x in 0..64 {
+                    let value = mask.get_pixel(x, y)[0];
+                    assert_eq!(value, mask.get_pixel(63 - x, y)[0]);
+                    assert_eq!(value, mask.get_pixel(x, 47 - y)[0]);
+                }
+            }
+        }
+
+        #[test]
+        fn test_mask_radius_is_clamped_to_half_side() {
+            let huge = rounded_rect_mask(40, 20, 1000.0);
+            let half = rounded_rect_mask(40, 20, 10.0);
+            assert_eq!(huge, half);
+        }
+
+        #[test]
+        fn test_render_blends_corner_into_background() {
+            let img =
+                DynamicImage::ImageRgba8(RgbaImage::from_pixel(100, 100, Rgba([0, 0, 0, 255])));
+            let mut settings = render::settings(0);
+            settings.border_radius = 40.0;
+
+            let out = render_effects(&img, &settings).unwrap();
+
+            // An edge pixel of the arc is a gray mix of the black image and white background
+            let partial = (0..40)
+                .map(|i| out.get_pixel(i, i)[0])
+                .find(|&v| v > 0 && v < 255);
+            assert!(partial.is_some());
+            assert!(out.pixels().all(|p| p[3] == 255));
+        }
+
+        #[test]
+        fn test_border_stroke_on_edges() {
+            let img =
+                DynamicImage::ImageRgba8(RgbaImage::from_pixel(40, 40, Rgba([0, 0, 255, 255])));
+            let mut settings = render::settings(10);
+            settings.border_width = 3.0;
+            settings.border_color = "#ff0000".to_string();
+
+            let out = render_effects(&img, &settings).unwrap();
+
+            // Stroke covers the outermost 3 pixels of the screenshot
+            for offset in 0..3 {
+                assert_eq!(*out.get_pixel(10 + offset, 30), Rgba([255, 0, 0, 255]));
+                assert_eq!(*out.get_pixel(49 - offset, 30), Rgba([255, 0, 0, 255]));
+            }
+            assert_eq!(*out.get_pixel(13, 30), Rgba([0, 0, 255, 255]));
+            // ...and never spills onto the padding
+            assert_eq!(*out.get_pixel(9, 30), Rgba([255, 255, 255, 255]));
+        }
+
+        #[test]
+        fn test_border_stroke_opacity() {
+            let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(20, 20, Rgba([0, 0, 0, 255])));
+            let mut settings = render::settings(0);
+            settings.border_width = 2.0;
+            settings.border_color = "#ffffff".to_string();
+            settings.border_opacity = 50.0;
+
+            let out = render_effects(&img, &settings).unwrap();
+
+            assert_eq!(*out.get_pixel(0, 10), Rgba([128, 128, 128, 255]));
+            assert_eq!(*out.get_pixel(10, 10), Rgba([0, 0, 0, 255]));
+        }
+
+        #[test]
+        fn test_border_stroke_follows_rounded_corner() {
+            let stroke = rounded_rect_stroke_mask(100, 100, 30.0, 2.0);
+
+            // Nothing at the cut-off corner, full coverage along the straight edge
+            assert_eq!(stroke.get_pixel(0, 0)[0], 0);
+            assert_eq!(stroke.get_pixel(50, 0)[0], 255);
+            assert_eq!(stroke.get_pixel(50, 5)[0], 0);
+            // The arc itself carries the stroke, with soft edges
+            let arc: Vec<u8> = (0..30).map(|i| stroke.get_pixel(i, i)[0]).collect();
+            assert!(arc.iter().any(|&c| c > 128));
+            assert!(arc.iter().any(|&c| c > 0 && c < 255));
+        }
+
+        #[test]
+        fn test_border_disabled_by_default() {
+            let json = r##"{
+                "background_type": "white",
+                "custom_color": "#ffffff",
+                "blur_amount": 0,
+                "noise_amount": 0,
+                "border_radius": 0,
+                "padding_top": 0,
+                "padding_bottom": 0,
+                "padding_left": 0,
+                "padding_right": 0,
+                "shadow_blur": 0,
+                "shadow_offset_x": 0,
+                "shadow_offset_y": 0,
+                "shadow_opacity": 0
+            }"##;
+            let settings: RenderSettings = serde_json::from_str(json).unwrap();
+
+            assert_eq!(settings.border_width, 0.0);
+            assert_eq!(settings.border_opacity, 100.0);
+        }
+    }
+
+    mod background {
+        use super::*;
+
+        fn stops(colors: &[(f32, &str)]) -> Vec<GradientStop> {
+            colors
+                .iter()
+                .map(|(offset, color)| GradientStop {
+                    offset: *offset,
+                    color: color.to_string(),
+                })
+                .collect()
+        }
+
+        #[test]
+        fn test_linear_gradient_left_to_right() {
+            let gradient = Gradient::Linear {
+                angle: 90.0,
+                stops: stops(&[(0.0, "#000000"), (1.0, "#ffffff")]),
+            };
+            let img = render_gradient(100, 10, &gradient).unwrap();
+
+            assert!(img.get_pixel(0, 5)[0] < 5);
+            assert!(img.get_pixel(99, 5)[0] > 250);
+            let middle = img.get_pixel(50, 5)[0];
+            assert!((120..=135).contains(&middle));
+        }
+
+        #[test]
+        fn test_linear_gradient_unsorted_stops() {
+            let gradient = Gradient::Linear {
+                angle: 180.0,
+                stops: stops(&[(0.9, "#0000ff"), (0.1, "#ff0000")]),
+            };
+            let img = render_gradient(10, 100, &gradient).unwrap();
+
+            assert_eq!(*img.get_pixel(5, 0), Rgba([255, 0, 0, 255]));
+            assert_eq!(*img.get_pixel(5, 99), Rgba([0, 0, 255, 255]));
+        }
+
+        #[test]
+        fn test_radial_gradient_center_and_corner() {
+            let gradient = Gradient::Radial {
+                center_x: 0.5,
+                center_y: 0.5,
+                radius: 1.0,
+                stops: stops(&[(0.0, "#ffffff"), (1.0, "#000000")]),
+            };
+            let img = render_gradient(101, 101, &gradient).unwrap();
+
+            assert_eq!(*img.get_pixel(50, 50), Rgba([255, 255, 255, 255]));
+            assert!(img.get_pixel(0, 0)[0] < 5);
+            assert!(img.get_pixel(100, 100)[0] < 5);
+        }
+
+        #[test]
+        fn test_mesh_gradient_hits_point_colors() {
+            let gradient = Gradient::Mesh {
+                points: vec![
+                    MeshPoint {
+                        x: 0.0,
+                        y: 0.0,
+                        color: "#ff0000".to_string(),
+                    },
+                    MeshPoint {
+                        x: 1.0,
+                        y: 1.0,
+                        color: "#0000ff".to_string(),
+                    },
+                ],
+            };
+            let img = render_gradient(100, 100, &gradient).unwrap();
+
+            let top_left = img.get_pixel(0, 0);
+            let bottom_right = img.get_pixel(99, 99);
+            assert!(top_left[0] > 250 && top_left[2] < 5);
+            assert!(bottom_right[2] > 250 && bottom_right[0] < 5);
+            // Equidistant from both points: an even mix
+            let corner = img.get_pixel(99, 0);
+            assert!((corner[0] as i32 - corner[2] as i32).abs() <= 2);
+        }
+
+        #[test]
+        fn test_gradient_rejects_invalid_color() {
+            let gradient = Gradient::Linear {
+                angle: 0.0,
+                stops: stops(&[(0.0, "not-a-color")]),
+            };
+            assert!(render_gradient(10, 10, &gradient).is_err());
+
+            let empty = Gradient::Mesh { points: vec![] };
+            assert!(render_gradient(10, 10, &empty).is_err());
+        }
+
+        /// 2x1 source: red on the left, blue on the right
+        fn two_tone() -> RgbaImage {
+            let mut img = RgbaImage::new(2, 1);
+            img.put_pixel(0, 0, Rgba([255, 0, 0, 255]));
+            img.put_pixel(1, 0, Rgba([0, 0, 255, 255]));
+            img
+        }
+
+        #[test]
+        fn test_fit_cover_fills_canvas() {
+            let out = fit_background_image(&two_tone(), 20, 20, BackgroundFit::Cover);
+
+            assert_eq!(out.dimensions(), (20, 20));
+            assert!(out.pixels().all(|p| p[3] == 255));
+        }
+
+        #[test]
+        fn test_fit_contain_letterboxes() {
+            let out = fit_background_image(&two_tone(), 20, 20, BackgroundFit::Contain);
+
+            assert_eq!(out.dimensions(), (20, 20));
+            assert_eq!(out.get_pixel(10, 0)[3], 0);
+            assert_eq!(out.get_pixel(10, 19)[3], 0);
+            assert_eq!(out.get_pixel(0, 10)[3], 255);
+        }
+
+        #[test]
+        fn test_fit_tile_repeats_source() {
+            let out = fit_background_image(&two_tone(), 5, 2, BackgroundFit::Tile);
+
+            assert_eq!(*out.get_pixel(0, 1), Rgba([255, 0, 0, 255]));
+            assert_eq!(*out.get_pixel(3, 0), Rgba([0, 0, 255, 255]));
+            assert_eq!(*out.get_pixel(4, 1), Rgba([255, 0, 0, 255]));
+        }
+
+        #[test]
+        fn test_settings_deserialize_gradient() {
+            let json = r##"{
+                "background_type": "gradient",
+                "custom_color": "#667eea",
+                "blur_amount": 0,
+                "noise_amount": 0,
+                "border_radius": 12,
+                "padding_top": 10,
+                "padding_bottom": 10,
+                "padding_left": 10,
+                "padding_right": 10,
+                "shadow_blur": 0,
+                "shadow_offset_x": 0,
+                "shadow_offset_y": 0,
+                "shadow_opacity": 0,
+                "gradient": {
+                    "type": "radial",
+                    "stops": [{ "offset": 0, "color": "#ffffff" }]
+                }
+            }"##;
+            let settings: RenderSettings = serde_json::from_str(json).unwrap();
+
+            assert_eq!(settings.background_fit, BackgroundFit::Stretch);
+            assert!(settings.background_image.is_none());
+            match settings.gradient {
+                Some(Gradient::Radial {
+                    center_x, radius, ..
+                }) => {
+                    assert_eq!(center_x, 0.5);
+                    assert_eq!(radius, 1.0);
+                }
+                other => panic!("unexpected gradient: {:?}", other),
+            }
+        }
+
+        #[test]
+        fn test_image_background_from_file() {
+            let dir =
+                std::env::temp_dir().join(format!("bettershot_test_bg_{}", std::process::id()));
+            ensure_dir(&dir).unwrap();
+            let bg_path = dir.join("wallpaper.png");
+            RgbaImage::from_pixel(4, 4, Rgba([0, 128, 0, 255]))
+                .save(&bg_path)
+                .unwrap();
+
+            let mut settings = render::settings(5);
+            settings.background_type = "image".to_string();
+            settings.background_image = Some(bg_path.to_string_lossy().into_owned());
+            settings.background_fit = BackgroundFit::Cover;
+
+            let img =
+                DynamicImage::ImageRgba8(RgbaImage::from_pixel(10, 10, Rgba([255, 0, 0, 255])));
+            let out = render_effects(&img, &settings).unwrap();
+
+            assert_eq!(*out.get_pixel(1, 1), Rgba([0, 128, 0, 255]));
+
+            settings.background_image =
+                Some(dir.join("missing.png").to_string_lossy().into_owned());
+            assert!(render_effects(&img, &settings).is_err());
+
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+    }
+
+    mod redaction {
+        use super::*;
+        use rand::{Rng, SeedableRng};
+
+        fn noise_image(width: u32, height: u32, seed: u64) -> RgbaImage {
+            let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+            RgbaImage::from_fn(width, height, |_, _| {
+                Rgba([rng.gen(), rng.gen(), rng.gen(), 255])
+            })
+        }
+
+        /// Shuffle pixels inside each block of the grid anchored at (origin_x, origin_y).
+        /// Block averages are unchanged, but the individual pixels are not.
+        fn shuffle_within_blocks(
+            img: &RgbaImage,
+            origin_x: u32,
+            origin_y: u32,
+            block: u32,
+        ) -> RgbaImage {
+            let mut out = img.clone();
+            for y in (origin_y..img.height()).step_by(block as usize) {
+                for x in (origin_x..img.width()).step_by(block as usize) {
+                    let w = block.min(img.width() - x);
+                    let h = block.min(img.height() - y);
+                    // Reverse the block's pixels in scan order
+                    let pixels: Vec<Rgba<u8>> = (0..h)
+                        .flat_map(|by| (0..w).map(move |bx| (bx, by)))
+                        .map(|(bx, by)| *img.get_pixel(x + bx, y + by))
+                        .collect();
+                    for (i, pixel) in pixels.iter().rev().enumerate() {
+                        out.put_pixel(x + i as u32 % w, y + i as u32 / w, *pixel);
+                    }
+                }
+            }
+            out
+        }
+
+        fn region(
+            x: i32,
+            y: i32,
+            width: u32,
+            height: u32,
+            method: RedactionMethod,
+        ) -> RedactionRegion {
+            RedactionRegion {
+                x,
+                y,
+                width,
+                height,
+                shape: RedactionShape::Rectangle,
+                method,
+            }
+        }
+
+        fn redacted(img: &RgbaImage, regions: &[RedactionRegion]) -> RgbaImage {
+            let mut out = img.clone();
+            apply_redactions(&mut out, regions).unwrap();
+            out
+        }
+
+        #[test]
+        fn test_fill_is_independent_of_source() {
+            let regions = [region(
+                8,
+                8,
+                32,
+                32,
+                RedactionMethod::Fill {
+                    color: "#000000".to_string(),
+                },
+            )];
+            let a = redacted(&noise_image(64, 64, 1), &regions);
+            let b = redacted(&noise_image(64, 64, 2), &regions);
+
+            for y in 8..40 {
+                for x in 8..40 {
+                    assert_eq!(*a.get_pixel(x, y), Rgba([0, 0, 0, 255]));
+                    assert_eq!(a.get_pixel(x, y), b.get_pixel(x, y));
+                }
+            }
+        }
+
+        #[test]
+        fn test_redaction_leaves_outside_untouched() {
+            let original = noise_image(64, 64, 3);
+            let out = redacted(
+                &original,
+                &[region(
+                    16,
+                    16,
+                    16,
+                    16,
+                    RedactionMethod::Pixelate { block_size: 8 },
+                )],
+            );
+
+            for (x, y, pixel) in out.enumerate_pixels() {
+                let inside = (16..32).contains(&x) && (16..32).contains(&y);
+                if !inside {
+                    assert_eq!(pixel, original.get_pixel(x, y));
+                }
+            }
+        }
+
+        #[test]
+        fn test_pixelate_depends_only_on_block_averages() {
+            let original = noise_image(64, 64, 4);
+            let shuffled = shuffle_within_blocks(&original, 0, 0, 16);
+            assert_ne!(original, shuffled);
+
+            let regions = [region(
+                0,
+                0,
+                64,
+                64,
+                RedactionMethod::Pixelate { block_size: 16 },
+            )];
+            let a = redacted(&original, &regions);
+            let b = redacted(&shuffled, &regions);
+
+            // Different sources with equal block averages are indistinguishable afterwards
+            assert_eq!(a, b);
+        }
+
+        #[test]
+        fn test_pixelate_enforces_minimum_block() {
+            let out = redacted(
+                &noise_image(32, 32, 5),
+                &[region(
+                    0,
+                    0,
+                    32,
+                    32,
+                    RedactionMethod::Pixelate { block_size: 1 },
+                )],
+            );
+
+            // At most one distinct value per minimum-size block survives
+            let mut distinct: Vec<Rgba<u8>> = out.pixels().copied().collect();
+            distinct.sort_by_key(|p| p.0);
+            distinct.dedup();
+            let max_blocks = (32 / MIN_REDACTION_BLOCK).pow(2) as usize;
+            assert!(distinct.len() <= max_blocks);
+        }
+
+        #[test]
+        fn test_low_radius_blur_is_not_reversible() {
+            let original = noise_image(64, 64, 6);
+            let shuffled = shuffle_within_blocks(&original, 0, 0, MIN_REDACTION_BLOCK);
+            assert_ne!(original, shuffled);
+
+            // A radius this small would normally be trivially deconvolvable
+            let regions = [region(0, 0, 64, 64, RedactionMethod::Blur { radius: 0.5 })];
+            let a = redacted(&original, &regions);
+            let b = redacted(&shuffled, &regions);
+
+            assert_eq!(a, b);
+            // Neighbouring pixels end up close to each other
+            let max_step = a
+                .enumerate_pixels()
+                .filter(|(x, _, _)| *x > 0)
+                .map(|(x, y, p)| (p[0] as i32 - a.get_pixel(x - 1, y)[0] as i32).abs())
+                .max()
+                .unwrap();
+            assert!(max_step < 32, "blur left hard edges: {}", max_step);
+        }
+
+        #[test]
+        fn test_blur_grid_follows_region_origin() {
+            let original = noise_image(64, 64, 7);
+            let shuffled = shuffle_within_blocks(&original, 5, 3, MIN_REDACTION_BLOCK);
+
+            let regions = [region(5, 3, 48, 40, RedactionMethod::Blur { radius: 4.0 })];
+            let a = redacted(&original, &regions);
+            let b = redacted(&shuffled, &regions);
+
+            for y in 3..43 {
+                for x in 5..53 {
+                    assert_eq!(a.get_pixel(x, y), b.get_pixel(x, y));
+                }
+            }
+        }
+
+        #[test]
+        fn test_ellipse_spares_bounding_box_corners() {
+            let original = noise_image(64, 64, 8);
+            let mut ellipse = region(
+                0,
+                0,
+                64,
+                64,
+                RedactionMethod::Fill {
+                    color: "#ff0000".to_string(),
+                },
+            );
+            ellipse.shape = RedactionShape::Ellipse;
+            let out = redacted(&original, &[ellipse]);
+
+            assert_eq!(out.get_pixel(0, 0), original.get_pixel(0, 0));
+            assert_eq!(out.get_pixel(63, 63), original.get_pixel(63, 63));
+            assert_eq!(*out.get_pixel(32, 32), Rgba([255, 0, 0, 255]));
+            assert_eq!(*out.get_pixel(32, 0), Rgba([255, 0, 0, 255]));
+        }
+
+        #[test]
+        fn test_regions_are_clipped_to_image() {
+            let original = noise_image(16, 16, 9);
+            let out = redacted(
+                &original,
+                &[
+                    region(-8, -8, 16, 16, RedactionMethod::Pixelate { block_size: 8 }),
+                    region(100, 100, 10, 10, RedactionMethod::Blur { radius: 10.0 }),
+                ],
+            );
+
+            assert_eq!(out.get_pixel(15, 15), original.get_pixel(15, 15));
+            let first = *out.get_pixel(0, 0);
+            assert!((0..8).all(|y| (0..8).all(|x| *out.get_pixel(x, y) == first)));
+        }
+
+        #[test]
+        fn test_invalid_fill_color_is_rejected() {
+            let mut img = noise_image(8, 8, 10);
+            let result = apply_redactions(
+                &mut img,
+                &[region(
+                    0,
+                    0,
+                    8,
+                    8,
+                    RedactionMethod::Fill {
+                        color: "red".to_string(),
+                    },
+                )],
+            );
+            assert!(result.is_err());
+        }
+
+        #[test]
+        fn test_region_deserialize() {
+            let json = r##"[
+                { "x": 1, "y": 2, "width": 3, "height": 4, "method": "blur", "radius": 12 },
+                { "x": 0, "y": 0, "width": 5, "height": 5, "shape": "ellipse",
+                  "method": "fill", "color": "#000000" }
+            ]"##;
+            let regions: Vec<RedactionRegion> = serde_json::from_str(json).unwrap();
+
+            assert_eq!(regions[0].shape, RedactionShape::Rectangle);
+            assert_eq!(regions[0].method, RedactionMethod::Blur { radius: 12.0 });
+            assert_eq!(regions[1].shape, RedactionShape::Ellipse);
+        }
+    }
+
+    mod scrolling {
+        use super::*;
+
+        /// A page with non-repeating content so every scroll offset is distinct
+        fn page(width: u32, height: u32) -> RgbaImage {
+            RgbaImage::from_fn(width, height, |x, y| {
+                let v = (x.wrapping_mul(2654435761) ^ y.wrapping_mul(40503)).wrapping_mul(97);
+                let v = (v >> 13) as u8;
+                Rgba([v, v.wrapping_add(40), v / 2, 255])
+            })
+        }
+
+        fn viewport(page: &RgbaImage, scroll: u32, height: u32) -> RgbaImage {
+            image::imageops::crop_imm(page, 0, scroll, page.width(), height).to_image()
+        }
+
+        fn with_bars(frame: &RgbaImage, header: u32, footer: u32) -> RgbaImage {
+            let mut framed = frame.clone();
+            let height = framed.height();
+            for (_, y, pixel) in framed.enumerate_pixels_mut() {
+                if y < header {
+                    *pixel = Rgba([200, 0, 0, 255]);
+                } else if y >= height - footer {
+                    *pixel = Rgba([0, 0, 200, 255]);
+                }
+            }
+            framed
+        }
+
+        #[test]
+        fn test_stitch_reassembles_page() {
+            let page = page(60, 390);
+            let frames: Vec<RgbaImage> = [0, 70, 140, 200, 290]
+                .iter()
+                .map(|&s| viewport(&page, s, 100))
+                .collect();
+
+            let stitched = stitch_scrolling_frames(&frames).unwrap();
+            assert_eq!(stitched, page);
+        }
+
+        #[test]
+        fn test_stitch_keeps_sticky_bars_once() {
+            let page = page(60, 400);
+            let frames: Vec<RgbaImage> = [0, 50, 120, 200]
+                .iter()
+                .map(|&s| with_bars(&viewport(&page, s, 120), 12, 8))
+                .collect();
+
+            let stitched = stitch_scrolling_frames(&frames).unwrap();
+
+            // Header, page rows 12..312 (the last frame shows 212..312), footer
+            assert_eq!(stitched.dimensions(), (60, 120 + 200));
+            assert_eq!(*stitched.get_pixel(5, 0), Rgba([200, 0, 0, 255]));
+            assert_eq!(*stitched.get_pixel(5, 11), Rgba([200, 0, 0, 255]));
+            for y in 12..312 {
+                assert_eq!(stitched.get_pixel(7, y), page.get_pixel(7, y), "row {}", y);
+            }
+            assert_eq!(*stitched.get_pixel(5, 312), Rgba([0, 0, 200, 255]));
+            assert_eq!(*stitched.get_pixel(5, 319), Rgba([0, 0, 200, 255]));
+        }
+
+        #[test]
+        fn test_stitch_skips_frames_that_did_not_scroll() {
+            let page = page(40, 300);
+            let first = viewport(&page, 0, 100);
+            let second = viewport(&page, 60, 100);
+            let frames = vec![first.clone(), first.clone(), second.clone(), second];
+
+            let stitched = stitch_scrolling_frames(&frames).unwrap();
+            assert_eq!(stitched, viewport(&page, 0, 160));
+        }
+
+        #[test]
+        fn test_stitch_single_frame() {
+            let frame = page(20, 30);
+            assert_eq!(
+                stitch_scrolling_frames(std::slice::from_ref(&frame)).unwrap(),
+                frame
+            );
+        }
+
+        #[test]
+        fn test_stitch_rejects_unrelated_frames() {
+            let a = page(60, 100);
+            let b = RgbaImage::from_pixel(60, 100, Rgba([255, 255, 255, 255]));
+            assert!(stitch_scrolling_frames(&[a, b]).is_err());
+        }
+
+        #[test]
+        fn test_stitch_rejects_mismatched_sizes() {
+            let frames = vec![page(60, 100), page(50, 100)];
+            assert!(stitch_scrolling_frames(&frames).is_err());
+            assert!(stitch_scrolling_frames(&[]).is_err());
+        }
+
+        #[test]
+        fn test_find_scroll_offset() {
+            let page = page(50, 200);
+            let prev = luma_rows(&viewport(&page, 20, 80));
+            let next = luma_rows(&viewport(&page, 55, 80));
+
+            assert_eq!(find_scroll_offset(&prev, &next), Some(35));
+            assert_eq!(find_scroll_offset(&prev, &prev), Some(0));
+        }
+    }
+
+    mod optimization {
+        use super::*;
+
+        fn gradient(width: u32, height: u32) -> RgbaImage {
+            RgbaImage::from_fn(width, height, |x, y| {
+                Rgba([(x * 255 / width) as u8, (y * 255 / height) as u8, 96, 255])
+            })
+        }
+
+        fn encoded(img: &RgbaImage) -> Vec<u8> {
+            let output = OutputOptions::default();
+            encode_image(&DynamicImage::ImageRgba8(img.clone()), &output).unwrap()
+        }
+
+        fn decode(bytes: &[u8]) -> RgbaImage {
+            image::load_from_memory(bytes).unwrap().to_rgba8()
+        }
+
+        fn with_text_chunk(png: &[u8]) -> Vec<u8> {
+            // Insert a tEXt chunk right after the IHDR chunk (8 + 25 bytes)
+            let mut chunk = Vec::new();
+            let data = b"Comment\0captured by bettershot";
+            chunk.extend_from_slice(&(data.len() as u32).to_be_bytes());
+            chunk.extend_from_slice(b"tEXt");
+            chunk.extend_from_slice(data);
+            let crc = crc32(&chunk[4..]);
+            chunk.extend_from_slice(&crc.to_be_bytes());
+
+            let mut bytes = png[..33].to_vec();
+            bytes.extend_from_slice(&chunk);
+            bytes.extend_from_slice(&png[33..]);
+            bytes
+        }
+
+        fn crc32(bytes: &[u8]) -> u32 {
+            let mut crc = 0xFFFF_FFFFu32;
+            for &byte in bytes {
+                crc ^= byte as u32;
+                for _ in 0..8 {
+                    crc = if crc & 1 == 1 {
+                        (crc >> 1) ^ 0xEDB8_8320
+                    } else {
+                        crc >> 1
+                    };
+                }
+            }
+            !crc
+        }
+
+        fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+            haystack
+                .windows(needle.len())
+                .any(|window| window == needle)
+        }
+
+        #[test]
+        fn test_disabled_optimization_keeps_bytes() {
+            let bytes = encoded(&gradient(32, 32));
+
+            let (optimized, quantized) =
+                optimize_png(bytes.clone(), &PngOptimization::default()).unwrap();
+            assert_eq!(optimized, bytes);
+            assert!(!quantized);
+        }
+
+        #[test]
+        fn test_recompression_is_lossless() {
+            let img = gradient(64, 48);
+            let bytes = encoded(&img);
+            let optimization = PngOptimization {
+                recompress: true,
+                ..PngOptimization::default()
+            };
+
+            let (optimized, quantized) = optimize_png(bytes.clone(), &optimization).unwrap();
+            assert!(optimized.len() <= bytes.len());
+            assert!(!quantized);
+            assert_eq!(decode(&optimized), img);
+        }
+
+        #[test]
+        fn test_strip_metadata_removes_text_chunks() {
+            let img = gradient(16, 16);
+            let bytes = with_text_chunk(&encoded(&img));
+            assert_eq!(decode(&bytes), img);
+            assert!(contains(&bytes, b"tEXt"));
+
+            let optimization = PngOptimization {
+                strip_metadata: true,
+                ..PngOptimization::default()
+            };
+            let (stripped, _) = optimize_png(bytes, &optimization).unwrap();
+            assert!(!contains(&stripped, b"tEXt"));
+            assert_eq!(decode(&stripped), img);
+        }
+
+        #[test]
+        fn test_quantize_flat_screenshot() {
+            // Few flat colors, as in most UI captures, survive quantization
+            let img = RgbaImage::from_fn(64, 64, |x, y| match (x / 16 + y / 16) % 3 {
+                0 => Rgba([255, 255, 255, 255]),
+                1 => Rgba([30, 120, 220, 255]),
+                _ => Rgba([20, 20, 20, 255]),
+            });
+            let bytes = encoded(&img);
+            let optimization = PngOptimization {
+                quantize: true,
+                ..PngOptimization::default()
+            };
+
+            let (optimized, quantized) = optimize_png(bytes.clone(), &optimization).unwrap();
+            assert!(quantized);
+            assert!(optimized.len() < bytes.len());
+            assert!(quantization_quality(&img, &decode(&optimized)) >= 80);
+        }
+
+        #[test]
+        fn test_quantize_respects_quality_floor() {
+            let img = RgbaImage::from_fn(64, 64, |x, y| {
+                Rgba([(x * 4) as u8, (y * 4) as u8, ((x + y) * 2) as u8, 255])
+            });
+            let bytes = encoded(&img);
+            let (palette_bytes, palette_img) = quantize_png(&img).unwrap();
+            let quality = quantization_quality(&img, &palette_img);
+            assert!(quality < 100);
+            assert_eq!(decode(&palette_bytes), palette_img);
+
+            let optimization = PngOptimization {
+                quantize: true,
+                min_quality: quality + 1,
+                ..PngOptimization::default()
+            };
+            let (optimized, quantized) = optimize_png(bytes.clone(), &optimization).unwrap();
+            assert!(!quantized);
+            assert_eq!(optimized, bytes);
+        }
+
+        #[test]
+        fn test_quantization_quality_bounds() {
+            let img = gradient(8, 8);
+            assert_eq!(quantization_quality(&img, &img), 100);
+
+            let inverted = RgbaImage::from_fn(8, 8, |x, y| {
+                let Rgba([r, g, b, a]) = *img.get_pixel(x, y);
+                Rgba([255 - r, 255 - g, 255 - b, a])
+            });
+            assert_eq!(quantization_quality(&img, &inverted), 0);
+        }
+
+        #[test]
+        fn test_save_reports_sizes() {
+            let dir = std::env::temp_dir().join(format!("bs_optimize_{}", std::process::id()));
+            let output = OutputOptions {
+                png: PngOptimization {
+                    recompress: true,
+                    strip_metadata: true,
+                    ..PngOptimization::default()
+                },
+                ..OutputOptions::default()
+            };
+
+            let img = DynamicImage::ImageRgba8(gradient(64, 64));
+            let saved = save_image(&img, dir.to_str().unwrap(), "optimized", &output).unwrap();
+            assert!(saved.path.ends_with(".png"));
+            assert!(saved.saved_bytes <= saved.original_bytes);
+            assert_eq!(fs::metadata(&saved.path).unwrap().len(), saved.saved_bytes);
+
+            let _ = fs::remove_dir_all(&dir);
+        }
+    }
+
     mod base64_validation {
         #[test]
         fn test_base64_prefix_validation() {
@@ -417,5 +2403,56 @@ mod tests {
             let result = invalid_data.strip_prefix("data:image/png;base64,");
             assert!(result.is_none());
         }
+
+        #[test]
+        fn test_data_url_format_is_detected() {
+            use super::super::decode_data_url;
+            use crate::output::OutputFormat;
+
+            let (format, bytes) = decode_data_url("data:image/jpeg;base64,/9j/4A==").unwrap();
+            assert_eq!(format, OutputFormat::Jpeg);
+            assert_eq!(bytes, vec![0xFF, 0xD8, 0xFF, 0xE0]);
+
+            let (format, _) = decode_data_url("data:image/png;base64,iVBORw0KGgo=").unwrap();
+            assert_eq!(format, OutputFormat::Png);
+        }
+
+        #[test]
+        fn test_data_url_rejects_unknown_types() {
+            use super::super::decode_data_url;
+
+            assert!(decode_data_url("data:image/gif;base64,R0lGOD==").is_err());
+            assert!(decode_data_url("data:text/plain,hello").is_err());
+            assert!(decode_data_url("iVBORw0KGgo=").is_err());
+        }
+
+        #[test]
+        fn test_data_in_output_format_is_written_as_is() {
+            use super::super::save_base64_image;
+            use crate::output::{encode_image, OutputFormat, OutputOptions};
+            use base64::{engine::general_purpose, Engine as _};
+            use image::{DynamicImage, Rgba, RgbaImage};
+            use std::fs;
+
+            let dir = std::env::temp_dir().join(format!("bs_base64_jpeg_{}", std::process::id()));
+            let output = OutputOptions {
+                format: OutputFormat::Jpeg,
+                quality: 60,
+                ..OutputOptions::default()
+            };
+            let img = RgbaImage::from_fn(32, 32, |x, y| Rgba([x as u8 * 8, y as u8 * 8, 90, 255]));
+            let jpeg = encode_image(&DynamicImage::ImageRgba8(img), &output).unwrap();
+            let data_url = format!(
+                "data:image/jpeg;base64,{}",
+                general_purpose::STANDARD.encode(&jpeg)
+            );
+
+            let saved =
+                save_base64_image(&data_url, dir.to_str().unwrap(), "edited", &output).unwrap();
+            assert!(saved.path.ends_with(".jpg"));
+            assert_eq!(fs::read(&saved.path).unwrap(), jpeg);
+
+            let _ = fs::remove_dir_all(&dir);
+        }
     }
 }
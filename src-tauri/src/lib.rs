@@ -4,18 +4,44 @@
 //! and saving screenshots with various features like region selection
 //! and background customization.
 
+mod animation;
+mod annotations;
+mod capture;
 mod clipboard;
+mod clipboard_history;
 mod commands;
+mod cursor;
 mod image;
+#[cfg(target_os = "linux")]
+mod linux_capture;
+#[cfg(target_os = "linux")]
+mod linux_clipboard;
 mod ocr;
+mod output;
+mod recording;
+mod render_cache;
 mod screenshot;
+mod sequence;
+mod session;
+mod timer;
 mod utils;
 
+use clipboard_history::{CLIPBOARD_HISTORY, HISTORY_FILE};
 use commands::{
-    capture_all_monitors, capture_once, capture_region, copy_image_file_to_clipboard,
-    get_desktop_directory, get_mouse_position, get_temp_directory, move_window_to_active_space,
-    native_capture_fullscreen, native_capture_interactive, native_capture_window,
-    native_capture_ocr_region, play_screenshot_sound, render_image_with_effects_rust, save_edited_image,
+    cancel_capture_timer, capture_all_monitors, capture_all_monitors_stitched,
+    capture_desktop_region, capture_monitor, capture_once, capture_region, capture_window,
+    clear_clipboard_history, copy_image_file_to_clipboard, crop_frame_sequence,
+    delete_sequence_frames, export_frame_sequence, get_desktop_directory, get_mouse_position,
+    get_temp_directory, list_capture_sessions, list_clipboard_history, list_windows,
+    load_frame_sequence, move_window_to_active_space, native_capture_fullscreen,
+    native_capture_interactive, native_capture_ocr_region, native_capture_window,
+    paste_image_from_clipboard, pause_recording, play_screenshot_sound,
+    recopy_clipboard_history_entry, redact_image_regions, release_rendered_image,
+    remove_clipboard_history_entry, render_annotations_rust, render_frame_sequence,
+    render_image_with_effects_rust, render_image_with_effects_to_file, resume_recording,
+    save_edited_image, save_rendered_image, set_frame_sequence_speed, start_capture_session,
+    start_recording, stitch_scrolling_capture, stop_capture_session, stop_recording,
+    trim_frame_sequence,
 };
 
 use tauri::{Emitter, Manager, WebviewUrl, WebviewWindowBuilder};
@@ -92,6 +118,16 @@ pub fn run() {
                 }
             }
 
+            // Restore clipboard history from the previous run
+            match app.path().app_data_dir() {
+                Ok(dir) => {
+                    if let Err(e) = CLIPBOARD_HISTORY.load(&dir.join(HISTORY_FILE)) {
+                        eprintln!("Failed to load clipboard history: {}", e);
+                    }
+                }
+                Err(e) => eprintln!("Failed to locate app data folder: {}", e),
+            }
+
             // Create the main window but keep it hidden initially
             // This allows the React frontend to run and set up event listeners
             let window =
@@ -244,19 +280,50 @@ pub fn run() {
         .invoke_handler(tauri::generate_handler![
             capture_once,
             capture_all_monitors,
+            capture_all_monitors_stitched,
+            capture_monitor,
+            capture_desktop_region,
+            capture_window,
+            list_windows,
+            start_capture_session,
+            stop_capture_session,
+            list_capture_sessions,
+            start_recording,
+            pause_recording,
+            resume_recording,
+            stop_recording,
             capture_region,
             save_edited_image,
+            save_rendered_image,
             render_image_with_effects_rust,
+            render_image_with_effects_to_file,
+            release_rendered_image,
+            render_annotations_rust,
+            redact_image_regions,
+            stitch_scrolling_capture,
+            export_frame_sequence,
+            load_frame_sequence,
+            trim_frame_sequence,
+            delete_sequence_frames,
+            set_frame_sequence_speed,
+            crop_frame_sequence,
+            render_frame_sequence,
             get_desktop_directory,
             get_temp_directory,
             native_capture_interactive,
             native_capture_fullscreen,
             native_capture_window,
             native_capture_ocr_region,
+            cancel_capture_timer,
             play_screenshot_sound,
             get_mouse_position,
             move_window_to_active_space,
-            copy_image_file_to_clipboard
+            copy_image_file_to_clipboard,
+            paste_image_from_clipboard,
+            list_clipboard_history,
+            recopy_clipboard_history_entry,
+            remove_clipboard_history_entry,
+            clear_clipboard_history
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,242 @@
+//! Linux capture backend
+//!
+//! Non-interactive captures go through the capture backend (xcap), which
+//! reads X11 directly and uses the compositor or desktop portal on Wayland.
+//! If that fails on Wayland, fullscreen captures fall back to a
+//! non-interactive xdg-desktop-portal screenshot. Interactive selection uses
+//! the portal's Screenshot dialog where available and falls back to `slop`
+//! on X11.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use zbus::blocking::{Connection, Proxy};
+use zbus::zvariant::{OwnedObjectPath, OwnedValue, Value};
+
+use crate::capture::CaptureBackend;
+use crate::utils::{ensure_dir, file_uri_to_path, generate_filename, get_timestamp, AppResult};
+
+const CANCELLED: &str = "Screenshot was cancelled or failed";
+
+/// Whether the current session is running under Wayland
+pub fn is_wayland() -> bool {
+    let session_type = std::env::var("XDG_SESSION_TYPE").unwrap_or_default();
+    session_type.eq_ignore_ascii_case("wayland") || std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+/// Build a destination path for a new screenshot in `save_dir`
+fn screenshot_path(save_dir: &str) -> AppResult<PathBuf> {
+    let save_path = PathBuf::from(save_dir);
+    ensure_dir(&save_path)?;
+    Ok(save_path.join(generate_filename("screenshot", "png")?))
+}
+
+/// Capture the primary monitor.
+///
+/// On Wayland, if the backend can't read the screen, this asks the portal
+/// for a screenshot instead, which covers the whole desktop.
+pub fn capture_fullscreen(backend: &dyn CaptureBackend, save_dir: &str) -> AppResult<String> {
+    match capture_primary_monitor(backend, save_dir) {
+        Err(capture_err) if is_wayland() => match portal_screenshot(false) {
+            Ok(Some(portal_path)) => move_into_save_dir(&portal_path, save_dir),
+            Ok(None) => Err(CANCELLED.to_string()),
+            Err(portal_err) => Err(format!(
+                "{}; screenshot portal unavailable: {}",
+                capture_err, portal_err
+            )),
+        },
+        result => result,
+    }
+}
+
+fn capture_primary_monitor(backend: &dyn CaptureBackend, save_dir: &str) -> AppResult<String> {
+    let monitor = backend.primary_monitor()?;
+    let image = backend.capture_monitor(monitor.id)?;
+
+    let path = screenshot_path(save_dir)?;
+    image
+        .save(&path)
+        .map_err(|e| format!("Failed to save screenshot: {}", e))?;
+
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Let the user pick a region (or window) and capture it. `backend` captures
+/// `slop` selections; the portal draws its own screenshot.
+pub fn capture_interactive(backend: &dyn CaptureBackend, save_dir: &str) -> AppResult<String> {
+    match portal_screenshot(true) {
+        Ok(Some(portal_path)) => move_into_save_dir(&portal_path, save_dir),
+        Ok(None) => Err(CANCELLED.to_string()),
+        Err(portal_err) if !is_wayland() => {
+            capture_with_slop(backend, save_dir).map_err(|slop_err| {
+                format!(
+                    "No region picker available ({}; {}). Install xdg-desktop-portal or slop.",
+                    portal_err, slop_err
+                )
+            })
+        }
+        Err(portal_err) => Err(format!("Screenshot portal unavailable: {}", portal_err)),
+    }
+}
+
+/// Let the user pick a window and capture it.
+///
+/// Both the portal dialog and `slop` allow clicking a window, so this shares
+/// the interactive flow.
+pub fn capture_window(backend: &dyn CaptureBackend, save_dir: &str) -> AppResult<String> {
+    capture_interactive(backend, save_dir)
+}
+
+/// Move a file produced by the portal into `save_dir` under our naming scheme
+fn move_into_save_dir(source: &Path, save_dir: &str) -> AppResult<String> {
+    let dest = screenshot_path(save_dir)?;
+
+    if std::fs::rename(source, &dest).is_err() {
+        // Different filesystems: fall back to copy + delete
+        std::fs::copy(source, &dest).map_err(|e| format!("Failed to copy screenshot: {}", e))?;
+        let _ = std::fs::remove_file(source);
+    }
+
+    Ok(dest.to_string_lossy().into_owned())
+}
+
+/// Ask the desktop portal for a screenshot, letting the user pick what to
+/// capture when `interactive` is set.
+///
+/// Returns `Ok(None)` if the user cancelled the dialog and `Err` if the
+/// portal isn't available or failed.
+fn portal_screenshot(interactive: bool) -> AppResult<Option<PathBuf>> {
+    let conn = Connection::session().map_err(|e| format!("Failed to connect to D-Bus: {}", e))?;
+
+    let token = format!("bettershot_{}", get_timestamp()?);
+    let sender = conn
+        .unique_name()
+        .ok_or("D-Bus connection has no unique name")?
+        .trim_start_matches(':')
+        .replace('.', "_");
+    let request_path = format!(
+        "/org/freedesktop/portal/desktop/request/{}/{}",
+        sender, token
+    );
+
+    // Subscribe before calling so a fast response can't be missed
+    let request = Proxy::new(
+        &conn,
+        "org.freedesktop.portal.Desktop",
+        request_path.as_str(),
+        "org.freedesktop.portal.Request",
+    )
+    .map_err(|e| format!("Failed to create portal request proxy: {}", e))?;
+    let mut responses = request
+        .receive_signal("Response")
+        .map_err(|e| format!("Failed to subscribe to portal response: {}", e))?;
+
+    let portal = Proxy::new(
+        &conn,
+        "org.freedesktop.portal.Desktop",
+        "/org/freedesktop/portal/desktop",
+        "org.freedesktop.portal.Screenshot",
+    )
+    .map_err(|e| format!("Failed to create screenshot portal proxy: {}", e))?;
+
+    let mut options: HashMap<&str, Value> = HashMap::new();
+    options.insert("handle_token", Value::from(token.as_str()));
+    options.insert("interactive", Value::from(interactive));
+    options.insert("modal", Value::from(true));
+
+    let _handle: OwnedObjectPath = portal
+        .call("Screenshot", &("", options))
+        .map_err(|e| format!("Screenshot portal call failed: {}", e))?;
+
+    let message = responses
+        .next()
+        .ok_or("Screenshot portal closed without a response")?;
+    let (code, results): (u32, HashMap<String, OwnedValue>) = message
+        .body()
+        .deserialize()
+        .map_err(|e| format!("Invalid screenshot portal response: {}", e))?;
+
+    match code {
+        0 => {}
+        1 => return Ok(None),
+        _ => return Err(format!("Screenshot portal failed with code {}", code)),
+    }
+
+    let uri = results
+        .get("uri")
+        .and_then(|value| String::try_from(value.clone()).ok())
+        .ok_or("Screenshot portal response has no uri")?;
+
+    file_uri_to_path(&uri).map(Some)
+}
+
+/// Parse `slop -f "%x %y %w %h"` output
+fn parse_slop_geometry(output: &str) -> AppResult<(i32, i32, u32, u32)> {
+    let parts: Vec<&str> = output.split_whitespace().collect();
+    if parts.len() != 4 {
+        return Err(format!("Unexpected slop output: {}", output.trim()));
+    }
+
+    let invalid = || format!("Unexpected slop output: {}", output.trim());
+    let x = parts[0].parse().map_err(|_| invalid())?;
+    let y = parts[1].parse().map_err(|_| invalid())?;
+    let width = parts[2].parse().map_err(|_| invalid())?;
+    let height = parts[3].parse().map_err(|_| invalid())?;
+
+    if width == 0 || height == 0 {
+        return Err(CANCELLED.to_string());
+    }
+
+    Ok((x, y, width, height))
+}
+
+/// Select a region with `slop` and capture it from the monitor it starts on
+fn capture_with_slop(backend: &dyn CaptureBackend, save_dir: &str) -> AppResult<String> {
+    let output = Command::new("slop")
+        .arg("-f")
+        .arg("%x %y %w %h")
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|e| format!("Failed to run slop: {}", e))?;
+
+    if !output.status.success() {
+        return Err(CANCELLED.to_string());
+    }
+
+    let (x, y, width, height) = parse_slop_geometry(&String::from_utf8_lossy(&output.stdout))?;
+
+    let image = backend.capture_rect(x, y, width, height)?;
+
+    let path = screenshot_path(save_dir)?;
+    image
+        .save(&path)
+        .map_err(|e| format!("Failed to save screenshot: {}", e))?;
+
+    Ok(path.to_string_lossy().into_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_slop_geometry() {
+        assert_eq!(
+            parse_slop_geometry("10 -20 300 200\n").unwrap(),
+            (10, -20, 300, 200)
+        );
+    }
+
+    #[test]
+    fn test_parse_slop_geometry_rejects_garbage() {
+        assert!(parse_slop_geometry("").is_err());
+        assert!(parse_slop_geometry("1 2 3").is_err());
+        assert!(parse_slop_geometry("a b c d").is_err());
+        assert!(parse_slop_geometry("0 0 -5 10").is_err());
+    }
+
+    #[test]
+    fn test_parse_slop_geometry_empty_selection_is_cancelled() {
+        assert_eq!(parse_slop_geometry("5 5 0 0").unwrap_err(), CANCELLED);
+    }
+}
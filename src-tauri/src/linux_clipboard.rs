@@ -0,0 +1,532 @@
+//! Linux clipboard backend
+//!
+//! On Wayland compositors with the data-control protocol (wlroots, KDE) the
+//! clipboard is set through wl-clipboard-rs. Elsewhere, including GNOME,
+//! which syncs XWayland's clipboard into Wayland, a background thread owns
+//! the X11 `CLIPBOARD` selection and answers paste requests until another
+//! app takes it over. Large payloads such as full-screen PNGs are sent with
+//! the ICCCM `INCR` protocol. Reading asks the current owner for the first
+//! offered type we understand, the same way.
+
+use std::collections::HashMap;
+use std::io::Read;
+use std::sync::mpsc::sync_channel;
+use std::time::{Duration, Instant};
+use wl_clipboard_rs::copy::{MimeSource, MimeType, Options, Source};
+use wl_clipboard_rs::paste;
+use x11rb::connection::{Connection, RequestConnection};
+use x11rb::protocol::xproto::{
+    Atom, AtomEnum, ChangeWindowAttributesAux, ConnectionExt as _, CreateWindowAux, EventMask,
+    PropMode, Property, SelectionNotifyEvent, SelectionRequestEvent, Window, WindowClass,
+    SELECTION_NOTIFY_EVENT,
+};
+use x11rb::protocol::Event;
+use x11rb::wrapper::ConnectionExt as _;
+use x11rb::{CURRENT_TIME, NONE};
+
+use crate::clipboard::{ClipboardEntry, TEXT_MIME};
+use crate::linux_capture::is_wayland;
+use crate::utils::AppResult;
+
+/// Largest property chunk written at once; bigger payloads go through INCR
+const MAX_CHUNK_BYTES: usize = 256 * 1024;
+
+/// Legacy X11 targets that also receive plain text
+const TEXT_TARGETS: [&str; 4] = ["UTF8_STRING", "TEXT", "text/plain", "STRING"];
+
+/// Property on our window that clipboard owners write into when reading
+const READ_PROPERTY: &str = "BETTERSHOT_CLIPBOARD";
+
+/// How long the clipboard owner gets to answer each step of a read
+const READ_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Pause between checks for the owner's answer
+const READ_POLL_INTERVAL: Duration = Duration::from_millis(5);
+
+/// How long a requestor gets to ask for the next chunk of an INCR transfer
+/// before the transfer is dropped
+const INCR_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Replace the clipboard contents with all of `entries`
+pub fn write(entries: &[ClipboardEntry]) -> AppResult<()> {
+    if is_wayland() {
+        match write_wayland(entries) {
+            Ok(()) => return Ok(()),
+            // Compositors without data-control still run XWayland
+            Err(e) => eprintln!("Wayland clipboard unavailable, using X11: {}", e),
+        }
+    }
+
+    write_x11(entries)
+}
+
+/// The first of `mime_types` the clipboard holds
+pub fn read(mime_types: &[&str]) -> AppResult<Option<ClipboardEntry>> {
+    if is_wayland() {
+        match read_wayland(mime_types) {
+            Ok(entry) => return Ok(entry),
+            Err(e) => eprintln!("Wayland clipboard unavailable, using X11: {}", e),
+        }
+    }
+
+    read_x11(mime_types)
+}
+
+fn write_wayland(entries: &[ClipboardEntry]) -> AppResult<()> {
+    let sources = entries
+        .iter()
+        .map(|entry| MimeSource {
+            source: Source::Bytes(entry.data.clone().into_boxed_slice()),
+            mime_type: if entry.mime_type == TEXT_MIME {
+                MimeType::Text
+            } else {
+                MimeType::Specific(entry.mime_type.clone())
+            },
+        })
+        .collect();
+
+    // Copies are served from a background thread until replaced
+    Options::new()
+        .copy_multi(sources)
+        .map_err(|e| format!("Failed to set Wayland clipboard: {}", e))
+}
+
+fn read_wayland(mime_types: &[&str]) -> AppResult<Option<ClipboardEntry>> {
+    let clipboard = paste::ClipboardType::Regular;
+    let offered = match paste::get_mime_types(clipboard, paste::Seat::Unspecified) {
+        Ok(offered) => offered,
+        Err(paste::Error::ClipboardEmpty) => return Ok(None),
+        Err(e) => return Err(format!("Failed to read Wayland clipboard: {}", e)),
+    };
+    let Some(&mime_type) = mime_types.iter().find(|&&m| offered.contains(m)) else {
+        return Ok(None);
+    };
+
+    let (mut pipe, _) = paste::get_contents(
+        clipboard,
+        paste::Seat::Unspecified,
+        paste::MimeType::Specific(mime_type),
+    )
+    .map_err(|e| format!("Failed to read Wayland clipboard: {}", e))?;
+    let mut data = Vec::new();
+    pipe.read_to_end(&mut data)
+        .map_err(|e| format!("Failed to read Wayland clipboard: {}", e))?;
+
+    Ok(Some(ClipboardEntry::new(mime_type, data)))
+}
+
+/// An X11 target atom and the index of the entry served for it
+type Target = (Atom, usize);
+
+/// A large transfer sent in chunks as the requestor deletes the property
+struct IncrTransfer {
+    target: Atom,
+    entry: usize,
+    offset: usize,
+    deadline: Instant,
+}
+
+fn intern(conn: &impl Connection, name: &str) -> AppResult<Atom> {
+    conn.intern_atom(false, name.as_bytes())
+        .map_err(|e| e.to_string())
+        .and_then(|cookie| cookie.reply().map_err(|e| e.to_string()))
+        .map(|reply| reply.atom)
+        .map_err(|e| format!("Failed to intern atom {}: {}", name, e))
+}
+
+/// Take ownership of the X11 clipboard and serve `entries` from a background
+/// thread, returning once ownership is confirmed
+fn write_x11(entries: &[ClipboardEntry]) -> AppResult<()> {
+    let entries = entries.to_vec();
+    let (tx, rx) = sync_channel(1);
+
+    std::thread::Builder::new()
+        .name("x11-clipboard".to_string())
+        .spawn(move || {
+            if let Err(e) = serve_x11(&entries, |result| {
+                let _ = tx.send(result);
+            }) {
+                eprintln!("X11 clipboard owner stopped: {}", e);
+            }
+        })
+        .map_err(|e| format!("Failed to start clipboard owner: {}", e))?;
+
+    rx.recv()
+        .map_err(|_| "Clipboard owner exited before taking the selection".to_string())?
+}
+
+/// Own the clipboard until another client takes it and every pending
+/// transfer has finished, been abandoned or lost its requestor window. `ready` is called once with the outcome of taking
+/// ownership.
+fn serve_x11(entries: &[ClipboardEntry], ready: impl FnOnce(AppResult<()>)) -> AppResult<()> {
+    let setup = || -> AppResult<_> {
+        let (conn, screen) =
+            x11rb::connect(None).map_err(|e| format!("Failed to connect to X server: {}", e))?;
+        let root = conn.setup().roots[screen].root;
+
+        let window = conn
+            .generate_id()
+            .map_err(|e| format!("Failed to create clipboard window: {}", e))?;
+        conn.create_window(
+            x11rb::COPY_DEPTH_FROM_PARENT,
+            window,
+            root,
+            0,
+            0,
+            1,
+            1,
+            0,
+            WindowClass::INPUT_OUTPUT,
+            0,
+            &CreateWindowAux::new(),
+        )
+        .map_err(|e| format!("Failed to create clipboard window: {}", e))?;
+
+        let clipboard = intern(&conn, "CLIPBOARD")?;
+        let mut targets: Vec<Target> = Vec::new();
+        for (index, entry) in entries.iter().enumerate() {
+            targets.push((intern(&conn, &entry.mime_type)?, index));
+            if entry.mime_type == TEXT_MIME {
+                for name in TEXT_TARGETS {
+                    targets.push((intern(&conn, name)?, index));
+                }
+            }
+        }
+
+        conn.set_selection_owner(window, clipboard, CURRENT_TIME)
+            .map_err(|e| format!("Failed to take the clipboard: {}", e))?;
+        let owner = conn
+            .get_selection_owner(clipboard)
+            .map_err(|e| e.to_string())
+            .and_then(|cookie| cookie.reply().map_err(|e| e.to_string()))
+            .map_err(|e| format!("Failed to take the clipboard: {}", e))?
+            .owner;
+        if owner != window {
+            return Err("Another client kept the clipboard".to_string());
+        }
+
+        Ok((conn, window, clipboard, targets))
+    };
+
+    let (conn, window, clipboard, targets) = match setup() {
+        Ok(state) => {
+            ready(Ok(()));
+            state
+        }
+        Err(e) => {
+            ready(Err(e.clone()));
+            return Err(e);
+        }
+    };
+
+    let targets_atom = intern(&conn, "TARGETS")?;
+    let incr_atom = intern(&conn, "INCR")?;
+    let chunk = MAX_CHUNK_BYTES.min(conn.maximum_request_bytes().saturating_sub(64));
+
+    let mut transfers: HashMap<(Window, Atom), IncrTransfer> = HashMap::new();
+    let mut owned = true;
+
+    while owned || !transfers.is_empty() {
+        let event = if transfers.is_empty() {
+            conn.wait_for_event()
+                .map_err(|e| format!("Lost connection to X server: {}", e))?
+        } else {
+            let event = conn
+                .poll_for_event()
+                .map_err(|e| format!("Lost connection to X server: {}", e))?;
+            let Some(event) = event else {
+                // A requestor that stopped reading must not keep us alive
+                let now = Instant::now();
+                transfers.retain(|_, transfer| transfer.deadline > now);
+                std::thread::sleep(READ_POLL_INTERVAL);
+                continue;
+            };
+            event
+        };
+
+        match event {
+            Event::SelectionClear(event) if event.selection == clipboard => owned = false,
+            Event::SelectionRequest(request) => {
+                let property = if owned {
+                    answer_request(
+                        &conn,
+                        &request,
+                        entries,
+                        &targets,
+                        (targets_atom, incr_atom),
+                        chunk,
+                        &mut transfers,
+                    )?
+                } else {
+                    NONE
+                };
+                conn.send_event(
+                    false,
+                    request.requestor,
+                    EventMask::NO_EVENT,
+                    SelectionNotifyEvent {
+                        response_type: SELECTION_NOTIFY_EVENT,
+                        sequence: 0,
+                        time: request.time,
+                        requestor: request.requestor,
+                        selection: request.selection,
+                        target: request.target,
+                        property,
+                    },
+                )
+                .map_err(|e| format!("Failed to answer paste request: {}", e))?;
+            }
+            Event::PropertyNotify(event) if event.state == Property::DELETE => {
+                let key = (event.window, event.atom);
+                if let Some(transfer) = transfers.get_mut(&key) {
+                    let bytes = &entries[transfer.entry].data;
+                    let end = (transfer.offset + chunk).min(bytes.len());
+                    conn.change_property8(
+                        PropMode::APPEND,
+                        event.window,
+                        event.atom,
+                        transfer.target,
+                        &bytes[transfer.offset..end],
+                    )
+                    .map_err(|e| format!("Failed to send clipboard data: {}", e))?;
+                    // The final, empty chunk tells the requestor we are done
+                    if transfer.offset == end {
+                        transfers.remove(&key);
+                    } else {
+                        transfer.offset = end;
+                        transfer.deadline = Instant::now() + INCR_TIMEOUT;
+                    }
+                }
+            }
+            Event::DestroyNotify(event) => {
+                transfers.retain(|&(requestor, _), _| requestor != event.window);
+            }
+            _ => {}
+        }
+
+        conn.flush()
+            .map_err(|e| format!("Lost connection to X server: {}", e))?;
+    }
+
+    let _ = conn.destroy_window(window);
+    let _ = conn.flush();
+    Ok(())
+}
+
+/// Write the requested target to the requestor's property, returning the
+/// property to report back or `NONE` when the request is refused
+fn answer_request(
+    conn: &impl Connection,
+    request: &SelectionRequestEvent,
+    entries: &[ClipboardEntry],
+    targets: &[Target],
+    (targets_atom, incr_atom): (Atom, Atom),
+    chunk: usize,
+    transfers: &mut HashMap<(Window, Atom), IncrTransfer>,
+) -> AppResult<Atom> {
+    // Obsolete clients leave the property unset and expect the target
+    let property = if request.property == NONE {
+        request.target
+    } else {
+        request.property
+    };
+    let failed =
+        |e: x11rb::errors::ConnectionError| format!("Failed to send clipboard data: {}", e);
+
+    if request.target == targets_atom {
+        let mut atoms: Vec<Atom> = targets.iter().map(|&(atom, _)| atom).collect();
+        atoms.push(targets_atom);
+        conn.change_property32(
+            PropMode::REPLACE,
+            request.requestor,
+            property,
+            AtomEnum::ATOM,
+            &atoms,
+        )
+        .map_err(failed)?;
+        return Ok(property);
+    }
+
+    let Some(&(_, entry)) = targets.iter().find(|&&(atom, _)| atom == request.target) else {
+        return Ok(NONE);
+    };
+    let bytes = &entries[entry].data;
+
+    if bytes.len() <= chunk {
+        conn.change_property8(
+            PropMode::REPLACE,
+            request.requestor,
+            property,
+            request.target,
+            bytes,
+        )
+        .map_err(failed)?;
+    } else {
+        conn.change_window_attributes(
+            request.requestor,
+            &ChangeWindowAttributesAux::new()
+                .event_mask(EventMask::PROPERTY_CHANGE | EventMask::STRUCTURE_NOTIFY),
+        )
+        .map_err(failed)?;
+        conn.change_property32(
+            PropMode::REPLACE,
+            request.requestor,
+            property,
+            incr_atom,
+            &[bytes.len() as u32],
+        )
+        .map_err(failed)?;
+        transfers.insert(
+            (request.requestor, property),
+            IncrTransfer {
+                target: request.target,
+                entry,
+                offset: 0,
+                deadline: Instant::now() + INCR_TIMEOUT,
+            },
+        );
+    }
+
+    Ok(property)
+}
+
+/// Wait for the first event `matches` accepts, giving up after `READ_TIMEOUT`
+fn wait_for<T>(
+    conn: &impl Connection,
+    mut matches: impl FnMut(Event) -> Option<T>,
+) -> AppResult<T> {
+    let deadline = Instant::now() + READ_TIMEOUT;
+    loop {
+        while let Some(event) = conn
+            .poll_for_event()
+            .map_err(|e| format!("Lost connection to X server: {}", e))?
+        {
+            if let Some(found) = matches(event) {
+                return Ok(found);
+            }
+        }
+        if Instant::now() >= deadline {
+            return Err("Timed out waiting for the clipboard owner".to_string());
+        }
+        std::thread::sleep(READ_POLL_INTERVAL);
+    }
+}
+
+/// Atoms a reader needs
+struct ReadAtoms {
+    clipboard: Atom,
+    property: Atom,
+    incr: Atom,
+}
+
+/// Ask the clipboard owner to convert its contents to `target`, returning
+/// `None` when it can't
+fn convert_selection(
+    conn: &impl Connection,
+    window: Window,
+    atoms: &ReadAtoms,
+    target: Atom,
+) -> AppResult<Option<Vec<u8>>> {
+    let failed = |e: String| format!("Failed to read the clipboard: {}", e);
+    let get_property = || {
+        conn.get_property(true, window, atoms.property, AtomEnum::ANY, 0, u32::MAX / 4)
+            .map_err(|e| e.to_string())
+            .and_then(|cookie| cookie.reply().map_err(|e| e.to_string()))
+            .map_err(failed)
+    };
+
+    conn.convert_selection(
+        window,
+        atoms.clipboard,
+        target,
+        atoms.property,
+        CURRENT_TIME,
+    )
+    .map_err(|e| failed(e.to_string()))?;
+    conn.flush().map_err(|e| failed(e.to_string()))?;
+
+    let notify = wait_for(conn, |event| match event {
+        Event::SelectionNotify(notify) if notify.requestor == window => Some(notify),
+        _ => None,
+    })?;
+    if notify.property == NONE {
+        return Ok(None);
+    }
+
+    let reply = get_property()?;
+    if reply.type_ != atoms.incr {
+        return Ok(Some(reply.value));
+    }
+
+    // Deleting the INCR property asked the owner for the first chunk; each
+    // chunk is read and deleted in turn until an empty one arrives
+    let mut data = Vec::new();
+    loop {
+        wait_for(conn, |event| match event {
+            Event::PropertyNotify(event)
+                if event.window == window
+                    && event.atom == atoms.property
+                    && event.state == Property::NEW_VALUE =>
+            {
+                Some(())
+            }
+            _ => None,
+        })?;
+        let chunk = get_property()?;
+        if chunk.value.is_empty() {
+            return Ok(Some(data));
+        }
+        data.extend_from_slice(&chunk.value);
+    }
+}
+
+/// Read the first of `mime_types` the X11 clipboard owner offers
+fn read_x11(mime_types: &[&str]) -> AppResult<Option<ClipboardEntry>> {
+    let (conn, screen) =
+        x11rb::connect(None).map_err(|e| format!("Failed to connect to X server: {}", e))?;
+    let root = conn.setup().roots[screen].root;
+
+    // Closing the connection destroys the window
+    let window = conn
+        .generate_id()
+        .map_err(|e| format!("Failed to create clipboard window: {}", e))?;
+    conn.create_window(
+        x11rb::COPY_DEPTH_FROM_PARENT,
+        window,
+        root,
+        0,
+        0,
+        1,
+        1,
+        0,
+        WindowClass::INPUT_OUTPUT,
+        0,
+        &CreateWindowAux::new().event_mask(EventMask::PROPERTY_CHANGE),
+    )
+    .map_err(|e| format!("Failed to create clipboard window: {}", e))?;
+
+    let atoms = ReadAtoms {
+        clipboard: intern(&conn, "CLIPBOARD")?,
+        property: intern(&conn, READ_PROPERTY)?,
+        incr: intern(&conn, "INCR")?,
+    };
+    let targets_atom = intern(&conn, "TARGETS")?;
+
+    let Some(targets) = convert_selection(&conn, window, &atoms, targets_atom)? else {
+        return Ok(None);
+    };
+    let offered: Vec<Atom> = targets
+        .chunks_exact(4)
+        .map(|atom| u32::from_ne_bytes([atom[0], atom[1], atom[2], atom[3]]))
+        .collect();
+
+    for &mime_type in mime_types {
+        let target = intern(&conn, mime_type)?;
+        if offered.contains(&target) {
+            return convert_selection(&conn, window, &atoms, target)
+                .map(|data| data.map(|data| ClipboardEntry::new(mime_type, data)));
+        }
+    }
+
+    Ok(None)
+}
@@ -0,0 +1,433 @@
+//! Output image formats
+//!
+//! Captures are kept as PNG while they are being edited, so the editor always
+//! works from lossless pixels. `OutputOptions` decides how an image is encoded
+//! when it leaves the app: saved to the user's folder, previewed after
+//! rendering, or exported. Formats without alpha are flattened onto white.
+
+use image::codecs::avif::AvifEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::tiff::TiffEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::{DynamicImage, ExtendedColorType, ImageEncoder, Rgba, RgbaImage};
+use jpeg_encoder::{ColorType, Encoder as JpegEncoder, SamplingFactor};
+use serde::Deserialize;
+use std::io::Cursor;
+
+use crate::image::PngOptimization;
+use crate::utils::AppResult;
+
+/// Store key of the saved output format preferences
+pub const OUTPUT_OPTIONS_SETTING: &str = "outputOptions";
+
+/// Quality used when none is given
+const DEFAULT_QUALITY: u8 = 90;
+
+/// AVIF encoder speed from 1 (slowest, smallest) to 10; 6 keeps saving a
+/// full-screen capture under a couple of seconds
+const AVIF_SPEED: u8 = 6;
+
+/// Largest side a baseline JPEG can describe
+const MAX_JPEG_DIMENSION: u32 = u16::MAX as u32;
+
+/// File format of a saved image
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    #[default]
+    Png,
+    Jpeg,
+    Webp,
+    Avif,
+    Tiff,
+}
+
+impl OutputFormat {
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "png",
+            OutputFormat::Jpeg => "jpg",
+            OutputFormat::Webp => "webp",
+            OutputFormat::Avif => "avif",
+            OutputFormat::Tiff => "tiff",
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            OutputFormat::Png => "image/png",
+            OutputFormat::Jpeg => "image/jpeg",
+            OutputFormat::Webp => "image/webp",
+            OutputFormat::Avif => "image/avif",
+            OutputFormat::Tiff => "image/tiff",
+        }
+    }
+
+    /// Format of a `data:` URL or file with the given MIME type
+    pub fn from_mime_type(mime_type: &str) -> Option<Self> {
+        match mime_type {
+            "image/png" => Some(OutputFormat::Png),
+            "image/jpeg" | "image/jpg" => Some(OutputFormat::Jpeg),
+            "image/webp" => Some(OutputFormat::Webp),
+            "image/avif" => Some(OutputFormat::Avif),
+            "image/tiff" => Some(OutputFormat::Tiff),
+            _ => None,
+        }
+    }
+
+    /// Format implied by a file's extension
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(path)
+            .extension()?
+            .to_str()?
+            .to_ascii_lowercase();
+        match extension.as_str() {
+            "png" => Some(OutputFormat::Png),
+            "jpg" | "jpeg" => Some(OutputFormat::Jpeg),
+            "webp" => Some(OutputFormat::Webp),
+            "avif" => Some(OutputFormat::Avif),
+            "tif" | "tiff" => Some(OutputFormat::Tiff),
+            _ => None,
+        }
+    }
+
+    /// Whether the format can be asked to encode losslessly
+    pub fn supports_lossless(self) -> bool {
+        matches!(
+            self,
+            OutputFormat::Png | OutputFormat::Tiff | OutputFormat::Webp
+        )
+    }
+
+    /// Whether saved files in this format can be decoded again. AVIF is
+    /// encoded in pure Rust, but decoding it needs the native dav1d library,
+    /// which isn't linked, so its pixels must come from the lossless source.
+    pub fn can_decode(self) -> bool {
+        !matches!(self, OutputFormat::Avif)
+    }
+}
+
+/// Reject image files in a format that can be saved but not decoded again
+pub fn ensure_decodable(path: &str) -> AppResult<()> {
+    match OutputFormat::from_path(path) {
+        Some(format) if !format.can_decode() => Err(format!(
+            "{} images can be saved but not opened again: {}",
+            format.extension().to_ascii_uppercase(),
+            path
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Chroma resolution of lossy encodings. Only JPEG lets us choose: lossy
+/// WebP is always 4:2:0 and AVIF is always encoded at 4:4:4.
+#[derive(Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum ChromaSubsampling {
+    /// Full color resolution, which keeps colored text and UI edges crisp
+    #[default]
+    Yuv444,
+    /// Half color resolution in both directions, for smaller photos
+    Yuv420,
+}
+
+/// How images leaving the app are encoded
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq)]
+pub struct OutputOptions {
+    #[serde(default)]
+    pub format: OutputFormat,
+    /// 1-100, ignored by lossless encodings
+    #[serde(default = "default_quality")]
+    pub quality: u8,
+    #[serde(default)]
+    pub chroma_subsampling: ChromaSubsampling,
+    #[serde(default)]
+    pub lossless: bool,
+    /// Extra size optimizations for PNG output
+    #[serde(default)]
+    pub png: PngOptimization,
+}
+
+fn default_quality() -> u8 {
+    DEFAULT_QUALITY
+}
+
+impl Default for OutputOptions {
+    fn default() -> Self {
+        Self {
+            format: OutputFormat::Png,
+            quality: DEFAULT_QUALITY,
+            chroma_subsampling: ChromaSubsampling::Yuv444,
+            lossless: false,
+            png: PngOptimization::default(),
+        }
+    }
+}
+
+impl OutputOptions {
+    /// Whether files already in this format can be kept as they are.
+    /// Decoding and re-encoding a lossy file in the same format only loses
+    /// more detail, so its bytes are kept whatever the quality setting.
+    pub fn keeps_encoded(&self, format: OutputFormat) -> bool {
+        format == self.format
+    }
+}
+
+/// Composite an image onto white for formats without an alpha channel
+fn flatten_onto_white(img: &RgbaImage) -> Vec<u8> {
+    let mut rgb = Vec::with_capacity(img.width() as usize * img.height() as usize * 3);
+    for Rgba([r, g, b, a]) in img.pixels() {
+        let alpha = *a as u32;
+        for channel in [*r, *g, *b] {
+            let blended = (channel as u32 * alpha + 255 * (255 - alpha) + 127) / 255;
+            rgb.push(blended as u8);
+        }
+    }
+    rgb
+}
+
+fn encode_jpeg(img: &RgbaImage, options: &OutputOptions) -> AppResult<Vec<u8>> {
+    if img.width() > MAX_JPEG_DIMENSION || img.height() > MAX_JPEG_DIMENSION {
+        return Err(format!(
+            "JPEG images can be at most {}x{} pixels",
+            MAX_JPEG_DIMENSION, MAX_JPEG_DIMENSION
+        ));
+    }
+
+    let mut bytes = Vec::new();
+    let mut encoder = JpegEncoder::new(&mut bytes, options.quality.clamp(1, 100));
+    encoder.set_sampling_factor(match options.chroma_subsampling {
+        ChromaSubsampling::Yuv444 => SamplingFactor::R_4_4_4,
+        ChromaSubsampling::Yuv420 => SamplingFactor::R_4_2_0,
+    });
+    encoder.set_optimized_huffman_tables(true);
+    encoder
+        .encode(
+            &flatten_onto_white(img),
+            img.width() as u16,
+            img.height() as u16,
+            ColorType::Rgb,
+        )
+        .map_err(|e| format!("Failed to encode JPEG: {}", e))?;
+    Ok(bytes)
+}
+
+fn encode_webp(img: &RgbaImage, options: &OutputOptions) -> AppResult<Vec<u8>> {
+    if options.lossless {
+        let mut bytes = Vec::new();
+        WebPEncoder::new_lossless(&mut bytes)
+            .encode(
+                img.as_raw(),
+                img.width(),
+                img.height(),
+                ExtendedColorType::Rgba8,
+            )
+            .map_err(|e| format!("Failed to encode WebP: {}", e))?;
+        return Ok(bytes);
+    }
+
+    let encoded = webp::Encoder::from_rgba(img.as_raw(), img.width(), img.height())
+        .encode_simple(false, options.quality.clamp(1, 100) as f32)
+        .map_err(|e| format!("Failed to encode WebP: {:?}", e))?;
+    Ok(encoded.to_vec())
+}
+
+fn encode_avif(img: &RgbaImage, options: &OutputOptions) -> AppResult<Vec<u8>> {
+    let mut bytes = Vec::new();
+    AvifEncoder::new_with_speed_quality(&mut bytes, AVIF_SPEED, options.quality.clamp(1, 100))
+        .write_image(
+            img.as_raw(),
+            img.width(),
+            img.height(),
+            ExtendedColorType::Rgba8,
+        )
+        .map_err(|e| format!("Failed to encode AVIF: {}", e))?;
+    Ok(bytes)
+}
+
+/// Encode an image with the given output options
+pub fn encode_image(img: &DynamicImage, options: &OutputOptions) -> AppResult<Vec<u8>> {
+    if options.lossless && !options.format.supports_lossless() {
+        return Err(format!(
+            "{} does not support lossless encoding",
+            options.format.extension().to_uppercase()
+        ));
+    }
+
+    let rgba = img.to_rgba8();
+    match options.format {
+        OutputFormat::Png => {
+            let mut bytes = Vec::new();
+            PngEncoder::new(&mut bytes)
+                .write_image(
+                    rgba.as_raw(),
+                    rgba.width(),
+                    rgba.height(),
+                    ExtendedColorType::Rgba8,
+                )
+                .map_err(|e| format!("Failed to encode PNG: {}", e))?;
+            Ok(bytes)
+        }
+        OutputFormat::Jpeg => encode_jpeg(&rgba, options),
+        OutputFormat::Webp => encode_webp(&rgba, options),
+        OutputFormat::Avif => encode_avif(&rgba, options),
+        OutputFormat::Tiff => {
+            let mut cursor = Cursor::new(Vec::new());
+            TiffEncoder::new(&mut cursor)
+                .write_image(
+                    rgba.as_raw(),
+                    rgba.width(),
+                    rgba.height(),
+                    ExtendedColorType::Rgba8,
+                )
+                .map_err(|e| format!("Failed to encode TIFF: {}", e))?;
+            Ok(cursor.into_inner())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn options(format: OutputFormat) -> OutputOptions {
+        OutputOptions {
+            format,
+            ..OutputOptions::default()
+        }
+    }
+
+    fn gradient(width: u32, height: u32) -> DynamicImage {
+        DynamicImage::ImageRgba8(RgbaImage::from_fn(width, height, |x, y| {
+            Rgba([(x * 8) as u8, (y * 8) as u8, 128, 255])
+        }))
+    }
+
+    fn decode(bytes: &[u8]) -> RgbaImage {
+        image::load_from_memory(bytes).unwrap().to_rgba8()
+    }
+
+    #[test]
+    fn test_lossless_formats_round_trip() {
+        let img = gradient(24, 16);
+        let webp_lossless = OutputOptions {
+            lossless: true,
+            ..options(OutputFormat::Webp)
+        };
+
+        for options in [
+            options(OutputFormat::Png),
+            options(OutputFormat::Tiff),
+            webp_lossless,
+        ] {
+            let bytes = encode_image(&img, &options).unwrap();
+            assert_eq!(decode(&bytes), img.to_rgba8(), "{:?}", options.format);
+        }
+    }
+
+    #[test]
+    fn test_lossy_formats_decode_to_same_size() {
+        let img = gradient(24, 16);
+
+        for format in [OutputFormat::Jpeg, OutputFormat::Webp] {
+            let bytes = encode_image(&img, &options(format)).unwrap();
+            assert_eq!(decode(&bytes).dimensions(), (24, 16), "{:?}", format);
+        }
+    }
+
+    #[test]
+    fn test_jpeg_quality_changes_size() {
+        let img = gradient(32, 32);
+        let low = OutputOptions {
+            quality: 10,
+            ..options(OutputFormat::Jpeg)
+        };
+        let high = OutputOptions {
+            quality: 100,
+            ..options(OutputFormat::Jpeg)
+        };
+
+        let low = encode_image(&img, &low).unwrap();
+        let high = encode_image(&img, &high).unwrap();
+        assert!(low.len() < high.len());
+    }
+
+    #[test]
+    fn test_jpeg_subsampling_is_written_to_frame_header() {
+        let img = gradient(16, 16);
+        let sampling = |chroma_subsampling| {
+            let options = OutputOptions {
+                chroma_subsampling,
+                ..options(OutputFormat::Jpeg)
+            };
+            let bytes = encode_image(&img, &options).unwrap();
+            // Luma sampling factors follow the first component id in SOF
+            let sof = bytes
+                .windows(2)
+                .position(|marker| marker == [0xFF, 0xC0] || marker == [0xFF, 0xC2])
+                .unwrap();
+            bytes[sof + 11]
+        };
+
+        assert_eq!(sampling(ChromaSubsampling::Yuv444), 0x11);
+        assert_eq!(sampling(ChromaSubsampling::Yuv420), 0x22);
+    }
+
+    #[test]
+    fn test_jpeg_flattens_alpha_onto_white() {
+        let img = DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 8, Rgba([0, 0, 0, 0])));
+        let options = OutputOptions {
+            quality: 100,
+            ..options(OutputFormat::Jpeg)
+        };
+
+        let decoded = decode(&encode_image(&img, &options).unwrap());
+        assert!(decoded
+            .pixels()
+            .all(|p| p[0] > 250 && p[1] > 250 && p[2] > 250));
+    }
+
+    #[test]
+    fn test_lossless_rejected_for_lossy_only_formats() {
+        let img = gradient(4, 4);
+
+        for format in [OutputFormat::Jpeg, OutputFormat::Avif] {
+            let options = OutputOptions {
+                lossless: true,
+                ..options(format)
+            };
+            assert!(encode_image(&img, &options).is_err());
+        }
+    }
+
+    #[test]
+    fn test_format_from_path_and_mime_type() {
+        assert_eq!(
+            OutputFormat::from_path("/tmp/a.JPEG"),
+            Some(OutputFormat::Jpeg)
+        );
+        assert_eq!(
+            OutputFormat::from_path("/tmp/a.tif"),
+            Some(OutputFormat::Tiff)
+        );
+        assert_eq!(OutputFormat::from_path("/tmp/a"), None);
+        assert_eq!(
+            OutputFormat::from_mime_type("image/avif"),
+            Some(OutputFormat::Avif)
+        );
+        assert_eq!(OutputFormat::from_mime_type("image/gif"), None);
+    }
+
+    #[test]
+    fn test_options_deserialize_with_defaults() {
+        let options: OutputOptions = serde_json::from_str(r#"{"format": "webp"}"#).unwrap();
+        assert_eq!(options.format, OutputFormat::Webp);
+        assert_eq!(options.quality, DEFAULT_QUALITY);
+        assert_eq!(options.chroma_subsampling, ChromaSubsampling::Yuv444);
+        assert!(!options.lossless);
+
+        let options: OutputOptions =
+            serde_json::from_str(r#"{"format": "jpeg", "chroma_subsampling": "yuv420"}"#).unwrap();
+        assert_eq!(options.chroma_subsampling, ChromaSubsampling::Yuv420);
+    }
+}
@@ -0,0 +1,652 @@
+//! Screen recording to animated GIF, APNG or WebP
+//!
+//! A recording captures a monitor, window or rectangle at a fixed frame rate
+//! on a background thread and hands frames to a second thread that encodes
+//! them, so slow encoding doesn't disturb capture timing. GIFs are streamed
+//! through `GifWriter` as frames arrive; APNG and WebP are written in one go
+//! when the recording ends, so their frames are held in memory until then.
+//! Only one recording runs at a time; it can be paused, resumed and stopped,
+//! and ends on its own once it reaches its maximum duration.
+
+use image::imageops::{resize, FilterType};
+use image::RgbaImage;
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::animation::{write_animation, AnimationFormat, FrameSource, GifWriter, SequenceFrame};
+use crate::capture::{CaptureBackend, CaptureTarget};
+use crate::utils::{ensure_dir, generate_filename, AppResult};
+
+/// Event emitted to the frontend with a `RecordingStatus` payload
+pub const RECORDING_STATUS_EVENT: &str = "recording-status";
+
+/// Highest supported frame rate; GIF delays can't go below 20 ms
+const MAX_FPS: u32 = 50;
+
+/// Longest allowed recording, in seconds
+const MAX_DURATION_SECONDS: f64 = 600.0;
+
+/// How often a waiting recorder checks for pause and stop requests
+const POLL_INTERVAL: Duration = Duration::from_millis(20);
+
+/// Consecutive failed captures after which a recording gives up
+const MAX_CONSECUTIVE_ERRORS: u32 = 5;
+
+/// Status updates are sent at most this often while recording
+const STATUS_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Most frame memory an APNG or WebP recording may hold before it is written
+const MAX_BUFFERED_BYTES: u64 = 2 * 1024 * 1024 * 1024;
+
+fn default_fps() -> u32 {
+    10
+}
+
+fn default_max_duration() -> f64 {
+    60.0
+}
+
+fn default_quality() -> u8 {
+    80
+}
+
+/// Settings for a recording
+#[derive(Deserialize, Clone, Debug)]
+pub struct RecordingConfig {
+    pub target: CaptureTarget,
+    pub save_dir: String,
+    #[serde(default)]
+    pub format: AnimationFormat,
+    #[serde(default = "default_fps")]
+    pub fps: u32,
+    #[serde(default = "default_max_duration")]
+    pub max_duration_seconds: f64,
+    /// GIF palette quality from 1 (fastest) to 100 (best)
+    #[serde(default = "default_quality")]
+    pub quality: u8,
+    /// Draw the cursor; the saved preference applies when unset
+    #[serde(default)]
+    pub show_cursor: Option<bool>,
+    /// Ring the cursor while clicking; the saved preference applies when unset
+    #[serde(default)]
+    pub highlight_clicks: Option<bool>,
+}
+
+/// Lifecycle state reported with each status update
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum RecordingState {
+    Recording,
+    Paused,
+    Stopped,
+    MaxDurationReached,
+    Failed,
+}
+
+/// Progress of the current recording
+#[derive(Serialize, Clone, Debug)]
+pub struct RecordingStatus {
+    pub state: RecordingState,
+    pub frames: u32,
+    /// Recorded time, not counting pauses
+    pub duration_ms: u64,
+    /// Saved animation, once the recording has ended
+    pub path: Option<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Default)]
+struct Controls {
+    stop: AtomicBool,
+    paused: AtomicBool,
+}
+
+struct ActiveRecording {
+    controls: Arc<Controls>,
+    handle: JoinHandle<RecordingStatus>,
+}
+
+/// Owns the single active recording
+pub struct Recorder {
+    active: Mutex<Option<ActiveRecording>>,
+}
+
+/// The app-wide recorder
+pub static RECORDER: Recorder = Recorder::new();
+
+impl Recorder {
+    pub const fn new() -> Self {
+        Self {
+            active: Mutex::new(None),
+        }
+    }
+
+    /// Validate `config` and start recording on a background thread
+    pub fn start(
+        &self,
+        backend: Box<dyn CaptureBackend>,
+        config: RecordingConfig,
+        on_status: impl Fn(&RecordingStatus) + Send + 'static,
+    ) -> AppResult<()> {
+        validate_config(&config)?;
+        ensure_dir(&PathBuf::from(&config.save_dir))?;
+
+        let mut active = self
+            .active
+            .lock()
+            .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+        if active.as_ref().is_some_and(|a| !a.handle.is_finished()) {
+            return Err("A recording is already in progress".to_string());
+        }
+
+        let controls = Arc::new(Controls::default());
+        let thread_controls = controls.clone();
+        let handle = std::thread::Builder::new()
+            .name("screen-recording".to_string())
+            .spawn(move || record(backend.as_ref(), &config, &thread_controls, on_status))
+            .map_err(|e| format!("Failed to start recording: {}", e))?;
+
+        *active = Some(ActiveRecording { controls, handle });
+        Ok(())
+    }
+
+    fn set_paused(&self, paused: bool) -> AppResult<()> {
+        let active = self
+            .active
+            .lock()
+            .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+
+        match active.as_ref() {
+            Some(recording) if !recording.handle.is_finished() => {
+                recording.controls.paused.store(paused, Ordering::SeqCst);
+                Ok(())
+            }
+            _ => Err("No recording in progress".to_string()),
+        }
+    }
+
+    /// Pause the recording; paused time is left out of the animation
+    pub fn pause(&self) -> AppResult<()> {
+        self.set_paused(true)
+    }
+
+    /// Resume a paused recording
+    pub fn resume(&self) -> AppResult<()> {
+        self.set_paused(false)
+    }
+
+    /// Stop the recording, wait for the animation to be written and return its status
+    pub fn stop(&self) -> AppResult<RecordingStatus> {
+        let recording = self
+            .active
+            .lock()
+            .map_err(|e| format!("Failed to acquire lock: {}", e))?
+            .take()
+            .ok_or("No recording in progress")?;
+
+        recording.controls.stop.store(true, Ordering::SeqCst);
+        recording
+            .handle
+            .join()
+            .map_err(|_| "Recording thread panicked".to_string())
+    }
+}
+
+fn validate_config(config: &RecordingConfig) -> AppResult<()> {
+    if config.fps == 0 || config.fps > MAX_FPS {
+        return Err(format!("Frame rate must be between 1 and {} fps", MAX_FPS));
+    }
+    if !(config.max_duration_seconds > 0.0 && config.max_duration_seconds <= MAX_DURATION_SECONDS) {
+        return Err(format!(
+            "Maximum duration must be between 0 and {} seconds",
+            MAX_DURATION_SECONDS
+        ));
+    }
+    Ok(())
+}
+
+/// Reject APNG and WebP recordings whose frames could outgrow
+/// `MAX_BUFFERED_BYTES` before they are written
+fn check_buffered_size(config: &RecordingConfig, width: u32, height: u32) -> AppResult<()> {
+    if config.format == AnimationFormat::Gif {
+        return Ok(());
+    }
+
+    let max_frames = (config.fps as f64 * config.max_duration_seconds).ceil() as u64 + 1;
+    if max_frames * width as u64 * height as u64 * 4 > MAX_BUFFERED_BYTES {
+        return Err(format!(
+            "A {}x{} recording this long is too large to save as {:?}; shorten it or record a GIF",
+            width, height, config.format
+        ));
+    }
+    Ok(())
+}
+
+/// Encode frames from `frames` into the configured format at `path` until
+/// the channel closes
+fn encode_recording(
+    path: &PathBuf,
+    width: u32,
+    height: u32,
+    config: &RecordingConfig,
+    frames: Receiver<(RgbaImage, u32)>,
+) -> AppResult<u32> {
+    let file = File::create(path).map_err(|e| format!("Failed to create recording: {}", e))?;
+
+    if config.format == AnimationFormat::Gif {
+        let mut writer = GifWriter::new(BufWriter::new(file), width, height, config.quality)?;
+        for (frame, delay_ms) in frames {
+            writer.push(frame, delay_ms)?;
+        }
+
+        let frame_count = writer.frames();
+        writer.finish()?;
+        return Ok(frame_count);
+    }
+
+    let frames: Vec<SequenceFrame> = frames
+        .into_iter()
+        .map(|(frame, delay_ms)| SequenceFrame {
+            source: FrameSource::Image(frame),
+            delay_ms,
+        })
+        .collect();
+    write_animation(&frames, config.format, BufWriter::new(file))?;
+    Ok(frames.len() as u32)
+}
+
+/// Sleep until `deadline`, returning early if a stop or pause is requested
+fn wait_until(deadline: Instant, controls: &Controls) {
+    loop {
+        if controls.stop.load(Ordering::SeqCst) || controls.paused.load(Ordering::SeqCst) {
+            return;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return;
+        }
+        std::thread::sleep(POLL_INTERVAL.min(deadline - now));
+    }
+}
+
+/// Capture frames until stopped or out of time, then finish the animation
+fn record(
+    backend: &dyn CaptureBackend,
+    config: &RecordingConfig,
+    controls: &Controls,
+    on_status: impl Fn(&RecordingStatus),
+) -> RecordingStatus {
+    let mut status = RecordingStatus {
+        state: RecordingState::Recording,
+        frames: 0,
+        duration_ms: 0,
+        path: None,
+        error: None,
+    };
+
+    let first = match config.target.capture(backend) {
+        Ok(frame) => frame,
+        Err(e) => {
+            status.state = RecordingState::Failed;
+            status.error = Some(e);
+            on_status(&status);
+            return status;
+        }
+    };
+    let (width, height) = first.dimensions();
+
+    if let Err(e) = check_buffered_size(config, width, height) {
+        status.state = RecordingState::Failed;
+        status.error = Some(e);
+        on_status(&status);
+        return status;
+    }
+
+    let path = match generate_filename("recording", config.format.extension()) {
+        Ok(filename) => PathBuf::from(&config.save_dir).join(filename),
+        Err(e) => {
+            status.state = RecordingState::Failed;
+            status.error = Some(e);
+            on_status(&status);
+            return status;
+        }
+    };
+
+    let interval = Duration::from_secs_f64(1.0 / config.fps as f64);
+    let max_duration = Duration::from_secs_f64(config.max_duration_seconds);
+
+    // Bound the queue so a slow encoder throttles capture instead of
+    // buffering unbounded frames in memory
+    let (sender, receiver) = sync_channel::<(RgbaImage, u32)>(config.fps as usize * 2);
+
+    let encoded = std::thread::scope(|scope| {
+        let encoder = scope.spawn(|| encode_recording(&path, width, height, config, receiver));
+
+        let mut last: Option<(RgbaImage, Instant)> = Some((first, Instant::now()));
+        let mut recorded = Duration::ZERO;
+        let mut consecutive_errors = 0;
+        let mut next_frame = Instant::now() + interval;
+        let mut last_status = Instant::now();
+        on_status(&status);
+
+        loop {
+            wait_until(next_frame, controls);
+
+            if controls.stop.load(Ordering::SeqCst) {
+                status.state = RecordingState::Stopped;
+                break;
+            }
+
+            if controls.paused.load(Ordering::SeqCst) {
+                // Close out the frame on screen so the pause isn't recorded
+                if let Some((frame, shown_at)) = last.take() {
+                    let shown = shown_at.elapsed().min(max_duration - recorded);
+                    recorded += shown;
+                    if sender.send((frame, shown.as_millis() as u32)).is_err() {
+                        break;
+                    }
+                    status.frames += 1;
+                    status.state = RecordingState::Paused;
+                    status.duration_ms = recorded.as_millis() as u64;
+                    on_status(&status);
+                }
+                std::thread::sleep(POLL_INTERVAL);
+                next_frame = Instant::now();
+                continue;
+            }
+
+            let frame = match config.target.capture(backend) {
+                Ok(frame) if frame.dimensions() == (width, height) => frame,
+                // Keep the frame size fixed if a window is resized mid-recording
+                Ok(frame) => resize(&frame, width, height, FilterType::Triangle),
+                Err(e) => {
+                    consecutive_errors += 1;
+                    if consecutive_errors >= MAX_CONSECUTIVE_ERRORS {
+                        status.state = RecordingState::Failed;
+                        status.error = Some(e);
+                        break;
+                    }
+                    next_frame += interval;
+                    continue;
+                }
+            };
+            consecutive_errors = 0;
+
+            let now = Instant::now();
+            if let Some((previous, shown_at)) = last.replace((frame, now)) {
+                let shown = (now - shown_at).min(max_duration - recorded);
+                recorded += shown;
+                if sender.send((previous, shown.as_millis() as u32)).is_err() {
+                    break;
+                }
+                status.frames += 1;
+            }
+
+            status.state = RecordingState::Recording;
+            status.duration_ms = recorded.as_millis() as u64;
+            if recorded >= max_duration {
+                status.state = RecordingState::MaxDurationReached;
+                last = None;
+                break;
+            }
+            if now - last_status >= STATUS_INTERVAL {
+                on_status(&status);
+                last_status = now;
+            }
+
+            next_frame += interval;
+            if next_frame < now {
+                // Capture fell behind; don't try to catch up with a burst
+                next_frame = now + interval;
+            }
+        }
+
+        // The frame on screen when recording ended gets one frame interval
+        if let Some((frame, _)) = last.take() {
+            if sender.send((frame, interval.as_millis() as u32)).is_ok() {
+                status.frames += 1;
+                recorded += interval;
+                status.duration_ms = recorded.as_millis() as u64;
+            }
+        }
+
+        drop(sender);
+        encoder
+            .join()
+            .unwrap_or_else(|_| Err("Recording encoder panicked".to_string()))
+    });
+
+    match encoded {
+        Ok(_) => status.path = Some(path.to_string_lossy().into_owned()),
+        Err(e) => {
+            status.state = RecordingState::Failed;
+            status.error = Some(e);
+            let _ = std::fs::remove_file(&path);
+        }
+    }
+
+    on_status(&status);
+    status
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::MockBackend;
+    use image::codecs::gif::GifDecoder;
+    use image::codecs::png::PngDecoder;
+    use image::codecs::webp::WebPDecoder;
+    use image::AnimationDecoder;
+
+    fn temp_save_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "bettershot_test_recording_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn config(dir: &std::path::Path) -> RecordingConfig {
+        RecordingConfig {
+            target: CaptureTarget::Rect {
+                x: 10,
+                y: 10,
+                width: 40,
+                height: 30,
+            },
+            save_dir: dir.to_string_lossy().into_owned(),
+            format: AnimationFormat::Gif,
+            fps: 20,
+            max_duration_seconds: 0.3,
+            quality: 50,
+            show_cursor: None,
+            highlight_clicks: None,
+        }
+    }
+
+    fn decoded_frames(path: &str) -> Vec<RgbaImage> {
+        let file = std::io::BufReader::new(File::open(path).unwrap());
+        GifDecoder::new(file)
+            .unwrap()
+            .into_frames()
+            .map(|f| f.unwrap().into_buffer())
+            .collect()
+    }
+
+    #[test]
+    fn test_config_deserialize_defaults() {
+        let config: RecordingConfig =
+            serde_json::from_str(r#"{"target": {"type": "monitor", "id": 1}, "save_dir": "/tmp"}"#)
+                .unwrap();
+
+        assert_eq!(config.format, AnimationFormat::Gif);
+        assert_eq!(config.fps, default_fps());
+        assert_eq!(config.max_duration_seconds, default_max_duration());
+        assert_eq!(config.quality, default_quality());
+    }
+
+    #[test]
+    fn test_validate_config() {
+        let dir = std::env::temp_dir();
+        assert!(validate_config(&config(&dir)).is_ok());
+
+        let mut bad = config(&dir);
+        bad.fps = 0;
+        assert!(validate_config(&bad).is_err());
+
+        let mut bad = config(&dir);
+        bad.fps = 60;
+        assert!(validate_config(&bad).is_err());
+
+        let mut bad = config(&dir);
+        bad.max_duration_seconds = 0.0;
+        assert!(validate_config(&bad).is_err());
+
+        let mut bad = config(&dir);
+        bad.max_duration_seconds = 3600.0;
+        assert!(validate_config(&bad).is_err());
+    }
+
+    #[test]
+    fn test_recording_stops_at_max_duration() {
+        let dir = temp_save_dir("max_duration");
+        let status = record(
+            &MockBackend::default(),
+            &config(&dir),
+            &Controls::default(),
+            |_| {},
+        );
+
+        assert_eq!(status.state, RecordingState::MaxDurationReached);
+        assert!(status.duration_ms >= 300);
+
+        // The mock desktop is static, so every frame collapses into one
+        let frames = decoded_frames(status.path.as_ref().unwrap());
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].dimensions(), (40, 30));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_recording_to_apng_and_webp() {
+        let dir = temp_save_dir("lossless_formats");
+
+        for (format, extension) in [
+            (AnimationFormat::Apng, ".png"),
+            (AnimationFormat::Webp, ".webp"),
+        ] {
+            let mut config = config(&dir);
+            config.format = format;
+            let status = record(
+                &MockBackend::default(),
+                &config,
+                &Controls::default(),
+                |_| {},
+            );
+
+            assert_eq!(status.state, RecordingState::MaxDurationReached);
+            let path = status.path.unwrap();
+            assert!(path.ends_with(extension));
+
+            let file = std::io::BufReader::new(File::open(&path).unwrap());
+            let frames: Vec<RgbaImage> = match format {
+                AnimationFormat::Apng => PngDecoder::new(file)
+                    .unwrap()
+                    .apng()
+                    .unwrap()
+                    .into_frames()
+                    .map(|f| f.unwrap().into_buffer())
+                    .collect(),
+                _ => WebPDecoder::new(file)
+                    .unwrap()
+                    .into_frames()
+                    .map(|f| f.unwrap().into_buffer())
+                    .collect(),
+            };
+            assert_eq!(frames.len() as u32, status.frames);
+            assert!(frames.iter().all(|f| f.dimensions() == (40, 30)));
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_buffered_formats_are_size_limited() {
+        let mut config = config(&std::env::temp_dir());
+        config.fps = 50;
+        config.max_duration_seconds = 600.0;
+
+        assert!(check_buffered_size(&config, 3840, 2160).is_ok());
+        config.format = AnimationFormat::Webp;
+        assert!(check_buffered_size(&config, 3840, 2160).is_err());
+        assert!(check_buffered_size(&config, 40, 30).is_ok());
+    }
+
+    #[test]
+    fn test_recording_fails_for_missing_window() {
+        let dir = temp_save_dir("missing_window");
+        let mut config = config(&dir);
+        config.target = CaptureTarget::Window { id: 999 };
+
+        let status = record(
+            &MockBackend::default(),
+            &config,
+            &Controls::default(),
+            |_| {},
+        );
+
+        assert_eq!(status.state, RecordingState::Failed);
+        assert!(status.path.is_none());
+        assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 0);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_recorder_start_pause_stop() {
+        let dir = temp_save_dir("recorder");
+        let recorder = Recorder::new();
+        let mut config = config(&dir);
+        config.max_duration_seconds = 30.0;
+
+        let (sender, receiver) = std::sync::mpsc::channel();
+        recorder
+            .start(Box::new(MockBackend::default()), config.clone(), move |s| {
+                let _ = sender.send(s.state);
+            })
+            .unwrap();
+        assert_eq!(receiver.recv().unwrap(), RecordingState::Recording);
+
+        // Only one recording at a time
+        assert!(recorder
+            .start(Box::new(MockBackend::default()), config, |_| {})
+            .is_err());
+
+        std::thread::sleep(Duration::from_millis(120));
+        recorder.pause().unwrap();
+        std::thread::sleep(Duration::from_millis(100));
+        recorder.resume().unwrap();
+        std::thread::sleep(Duration::from_millis(60));
+
+        let status = recorder.stop().unwrap();
+        assert_eq!(status.state, RecordingState::Stopped);
+        assert!(status.path.is_some());
+        assert!(status.duration_ms < 30_000);
+
+        assert!(recorder.stop().is_err());
+        assert!(recorder.pause().is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
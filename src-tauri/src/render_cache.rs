@@ -0,0 +1,241 @@
+//! Rendered image handles
+//!
+//! Rendering a large capture and sending it back as a base64 data URL costs
+//! tens of megabytes of string copies per preview. Instead, renders are
+//! written to a temp folder the asset protocol can read, and the frontend
+//! gets a handle plus the file's path to show with `convertFileSrc`. Saving
+//! takes the handle, so the pixels never cross IPC. Renders are always kept
+//! as lossless PNG; the output format is applied once, when one is saved.
+//! Only the most recent renders are kept; older files are deleted as new
+//! ones arrive.
+
+use image::{DynamicImage, RgbaImage};
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+
+use crate::image::{render_effects, RenderSettings};
+use crate::output::{encode_image, OutputFormat, OutputOptions};
+use crate::utils::{ensure_dir, generate_filename_with_id, AppResult};
+
+/// Folder under the system temp dir that holds rendered files
+const RENDER_DIR_NAME: &str = "bettershot-renders";
+
+/// Renders kept on disk before the oldest is deleted; a few are enough for
+/// the editor to swap previews without a just-released file disappearing
+const MAX_RENDERS: usize = 8;
+
+/// A rendered image written to disk
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct RenderedImage {
+    pub handle: u32,
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+    pub bytes: u64,
+}
+
+/// Tracks rendered files by handle
+pub struct RenderCache {
+    /// Folder for rendered files, the system temp dir's when `None`
+    dir: Option<PathBuf>,
+    next_handle: AtomicU32,
+    renders: Mutex<BTreeMap<u32, PathBuf>>,
+}
+
+/// The app-wide render cache
+pub static RENDER_CACHE: RenderCache = RenderCache::new();
+
+impl RenderCache {
+    pub const fn new() -> Self {
+        Self {
+            dir: None,
+            next_handle: AtomicU32::new(1),
+            renders: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn dir(&self) -> PathBuf {
+        self.dir
+            .clone()
+            .unwrap_or_else(|| std::env::temp_dir().join(RENDER_DIR_NAME))
+    }
+
+    /// Encode an image as PNG and store it under a new handle
+    pub fn insert(&self, img: &RgbaImage) -> AppResult<RenderedImage> {
+        let bytes = encode_image(
+            &DynamicImage::ImageRgba8(img.clone()),
+            &OutputOptions::default(),
+        )?;
+
+        let dir = self.dir();
+        ensure_dir(&dir)?;
+
+        let handle = self.next_handle.fetch_add(1, Ordering::SeqCst);
+        let path = dir.join(generate_filename_with_id(
+            "render",
+            handle,
+            OutputFormat::Png.extension(),
+        )?);
+        fs::write(&path, &bytes).map_err(|e| format!("Failed to write render: {}", e))?;
+
+        let mut renders = self
+            .renders
+            .lock()
+            .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+        renders.insert(handle, path.clone());
+        while renders.len() > MAX_RENDERS {
+            if let Some((_, oldest)) = renders.pop_first() {
+                let _ = fs::remove_file(oldest);
+            }
+        }
+
+        Ok(RenderedImage {
+            handle,
+            path: path.to_string_lossy().into_owned(),
+            width: img.width(),
+            height: img.height(),
+            bytes: bytes.len() as u64,
+        })
+    }
+
+    /// Apply effects to an image file and store the result under a new handle
+    pub fn render(&self, image_path: &str, settings: &RenderSettings) -> AppResult<RenderedImage> {
+        let img = image::open(image_path).map_err(|e| format!("Failed to open image: {}", e))?;
+        let rendered = render_effects(&img, settings)?;
+        self.insert(&rendered)
+    }
+
+    /// Path of the file behind a handle
+    pub fn path(&self, handle: u32) -> AppResult<String> {
+        let renders = self
+            .renders
+            .lock()
+            .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+        renders
+            .get(&handle)
+            .filter(|path| path.exists())
+            .map(|path| path.to_string_lossy().into_owned())
+            .ok_or_else(|| format!("Render {} not found", handle))
+    }
+
+    /// Delete a render's file, returning whether the handle was known
+    pub fn release(&self, handle: u32) -> AppResult<bool> {
+        let removed = self
+            .renders
+            .lock()
+            .map_err(|e| format!("Failed to acquire lock: {}", e))?
+            .remove(&handle);
+
+        match removed {
+            Some(path) => {
+                let _ = fs::remove_file(path);
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::image::save_image_file;
+    use image::Rgba;
+
+    fn cache(name: &str) -> RenderCache {
+        RenderCache {
+            dir: Some(std::env::temp_dir().join(format!(
+                "bettershot_renders_{}_{}",
+                name,
+                std::process::id()
+            ))),
+            ..RenderCache::new()
+        }
+    }
+
+    fn solid(width: u32, height: u32) -> RgbaImage {
+        RgbaImage::from_pixel(width, height, Rgba([40, 80, 160, 255]))
+    }
+
+    #[test]
+    fn test_insert_writes_file_behind_handle() {
+        let cache = cache("insert");
+        let img = solid(12, 8);
+
+        let rendered = cache.insert(&img).unwrap();
+        assert_eq!((rendered.width, rendered.height), (12, 8));
+        assert!(rendered.path.ends_with(".png"));
+        assert_eq!(cache.path(rendered.handle).unwrap(), rendered.path);
+        assert_eq!(fs::metadata(&rendered.path).unwrap().len(), rendered.bytes);
+        assert_eq!(image::open(&rendered.path).unwrap().to_rgba8(), img);
+
+        let _ = fs::remove_dir_all(cache.dir());
+    }
+
+    #[test]
+    fn test_output_format_is_applied_when_saving() {
+        let cache = cache("format");
+        let rendered = cache.insert(&solid(8, 8)).unwrap();
+        let save_dir = cache.dir().join("saved");
+        let output = OutputOptions {
+            format: OutputFormat::Jpeg,
+            ..OutputOptions::default()
+        };
+
+        let saved = save_image_file(
+            &cache.path(rendered.handle).unwrap(),
+            &save_dir.to_string_lossy(),
+            "bettershot",
+            &output,
+        )
+        .unwrap();
+        assert!(saved.path.ends_with(".jpg"));
+        assert_eq!(
+            image::ImageFormat::from_path(&saved.path).unwrap(),
+            image::guess_format(&fs::read(&saved.path).unwrap()).unwrap()
+        );
+
+        let _ = fs::remove_dir_all(cache.dir());
+    }
+
+    #[test]
+    fn test_release_deletes_file() {
+        let cache = cache("release");
+        let rendered = cache.insert(&solid(4, 4)).unwrap();
+
+        assert!(cache.release(rendered.handle).unwrap());
+        assert!(!PathBuf::from(&rendered.path).exists());
+        assert!(cache.path(rendered.handle).is_err());
+        assert!(!cache.release(rendered.handle).unwrap());
+
+        let _ = fs::remove_dir_all(cache.dir());
+    }
+
+    #[test]
+    fn test_oldest_renders_are_evicted() {
+        let cache = cache("evict");
+        let renders: Vec<_> = (0..MAX_RENDERS + 2)
+            .map(|_| cache.insert(&solid(4, 4)).unwrap())
+            .collect();
+
+        for evicted in &renders[..2] {
+            assert!(cache.path(evicted.handle).is_err());
+            assert!(!PathBuf::from(&evicted.path).exists());
+        }
+        for kept in &renders[2..] {
+            assert_eq!(cache.path(kept.handle).unwrap(), kept.path);
+        }
+
+        let _ = fs::remove_dir_all(cache.dir());
+    }
+
+    #[test]
+    fn test_unknown_handle_is_rejected() {
+        let cache = cache("unknown");
+        assert!(cache.path(42).is_err());
+    }
+}
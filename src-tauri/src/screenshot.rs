@@ -1,10 +1,22 @@
 //! Screenshot capture module
+//!
+//! Captures are sources for the editor, not finished images. This module,
+//! the Linux capture backend and macOS `screencapture` all write them as
+//! lossless PNG without an `IMAGE_SAVED_EVENT`, and the frontend keeps them
+//! in its temp directory. The user's output format and save directory apply
+//! when the edited image is saved (`save_edited_image`, `save_rendered_image`).
+//! The one exception is `capture_primary_monitor`, which backs the quick
+//! capture that skips the editor and saves straight to the save directory.
 
+use image::DynamicImage;
 use serde::Serialize;
-use std::path::PathBuf;
-use xcap::Monitor;
+use std::path::{Path, PathBuf};
 
-use crate::utils::{ensure_dir, generate_filename_with_id, AppResult};
+use crate::capture::{capture_desktop, CaptureBackend, MonitorInfo, WindowInfo};
+use crate::clipboard::{copy_image_with, ClipboardBackend, ClipboardFlavor};
+use crate::image::{save_image, SavedImage};
+use crate::output::OutputOptions;
+use crate::utils::{ensure_dir, generate_filename, generate_filename_with_id, AppResult};
 
 /// Represents a captured monitor screenshot with geometry info
 #[derive(Serialize, Clone, Debug)]
@@ -18,9 +30,63 @@ pub struct MonitorShot {
     pub path: String,
 }
 
+/// Represents a captured window screenshot with geometry info
+#[derive(Serialize, Clone, Debug)]
+pub struct WindowShot {
+    pub id: u32,
+    pub app_name: String,
+    pub title: String,
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub path: String,
+}
+
+/// Represents a captured desktop region with geometry info
+///
+/// `scale_factor` is the number of image pixels per desktop unit.
+#[derive(Serialize, Clone, Debug)]
+pub struct RegionShot {
+    pub x: i32,
+    pub y: i32,
+    pub width: u32,
+    pub height: u32,
+    pub scale_factor: f32,
+    pub path: String,
+}
+
+/// Criteria for picking a window to capture
+///
+/// `app_name` and `title` are case-insensitive substring matches. When
+/// several windows match, the frontmost one wins.
+#[derive(Clone, Debug, Default)]
+pub struct WindowQuery {
+    pub id: Option<u32>,
+    pub app_name: Option<String>,
+    pub title: Option<String>,
+}
+
+impl WindowQuery {
+    fn matches(&self, window: &WindowInfo) -> bool {
+        let contains = |haystack: &str, needle: &Option<String>| {
+            needle
+                .as_ref()
+                .is_none_or(|n| haystack.to_lowercase().contains(&n.to_lowercase()))
+        };
+
+        self.id.is_none_or(|id| window.id == id)
+            && contains(&window.app_name, &self.app_name)
+            && contains(&window.title, &self.title)
+    }
+}
+
 /// Capture screenshots of all available monitors
-pub fn capture_all_monitors(save_dir: &str) -> AppResult<Vec<MonitorShot>> {
-    let monitors = Monitor::all().map_err(|e| format!("Failed to get monitors: {}", e))?;
+pub fn capture_all_monitors(
+    backend: &dyn CaptureBackend,
+    save_dir: &str,
+) -> AppResult<Vec<MonitorShot>> {
+    let monitors = backend.monitors()?;
 
     if monitors.is_empty() {
         return Err("No monitors available".into());
@@ -31,27 +97,77 @@ pub fn capture_all_monitors(save_dir: &str) -> AppResult<Vec<MonitorShot>> {
 
     let mut shots = Vec::with_capacity(monitors.len());
 
-    for monitor in monitors {
-        let shot = capture_single_monitor(&monitor, &save_path)?;
+    for monitor in &monitors {
+        let shot = capture_single_monitor(backend, monitor, &save_path)?;
         shots.push(shot);
     }
 
     Ok(shots)
 }
 
-/// Capture a single monitor screenshot
-fn capture_single_monitor(monitor: &Monitor, save_path: &PathBuf) -> AppResult<MonitorShot> {
-    let monitor_id = monitor
-        .id()
-        .map_err(|e| format!("Failed to get monitor id: {}", e))?;
+/// Capture all monitors into a single image of the whole desktop
+///
+/// `target_scale` is the number of image pixels per desktop unit; it defaults
+/// to the densest monitor's.
+pub fn capture_all_monitors_stitched(
+    backend: &dyn CaptureBackend,
+    save_dir: &str,
+    target_scale: Option<f32>,
+) -> AppResult<RegionShot> {
+    let (bounds, image) = capture_desktop(backend, target_scale)?;
+
+    let save_path = PathBuf::from(save_dir);
+    ensure_dir(&save_path)?;
+
+    let filename = generate_filename("desktop", "png")?;
+    let screenshot_path = save_path.join(&filename);
+
+    image
+        .save(&screenshot_path)
+        .map_err(|e| format!("Failed to save screenshot: {}", e))?;
+
+    Ok(RegionShot {
+        x: bounds.x,
+        y: bounds.y,
+        width: bounds.width,
+        height: bounds.height,
+        scale_factor: image.width() as f32 / bounds.width as f32,
+        path: screenshot_path.to_string_lossy().into_owned(),
+    })
+}
 
+/// Capture one monitor by id, or the primary monitor when no id is given
+pub fn capture_monitor(
+    backend: &dyn CaptureBackend,
+    save_dir: &str,
+    monitor_id: Option<u32>,
+) -> AppResult<MonitorShot> {
+    let monitor = match monitor_id {
+        Some(id) => backend
+            .monitors()?
+            .into_iter()
+            .find(|m| m.id == id)
+            .ok_or_else(|| format!("Monitor {} not found", id))?,
+        None => backend.primary_monitor()?,
+    };
+
+    let save_path = PathBuf::from(save_dir);
+    ensure_dir(&save_path)?;
+
+    capture_single_monitor(backend, &monitor, &save_path)
+}
+
+/// Capture a single monitor screenshot
+fn capture_single_monitor(
+    backend: &dyn CaptureBackend,
+    monitor: &MonitorInfo,
+    save_path: &Path,
+) -> AppResult<MonitorShot> {
     // Capture the screenshot
-    let image = monitor
-        .capture_image()
-        .map_err(|e| format!("Failed to capture monitor {}: {}", monitor_id, e))?;
+    let image = backend.capture_monitor(monitor.id)?;
 
     // Generate unique filename
-    let filename = generate_filename_with_id("monitor", monitor_id, "png")?;
+    let filename = generate_filename_with_id("monitor", monitor.id, "png")?;
     let screenshot_path = save_path.join(&filename);
 
     // Save the image
@@ -59,51 +175,414 @@ fn capture_single_monitor(monitor: &Monitor, save_path: &PathBuf) -> AppResult<M
         .save(&screenshot_path)
         .map_err(|e| format!("Failed to save screenshot: {}", e))?;
 
-    // Get monitor geometry
-    let x = monitor
-        .x()
-        .map_err(|e| format!("Failed to get monitor x: {}", e))?;
-    let y = monitor
-        .y()
-        .map_err(|e| format!("Failed to get monitor y: {}", e))?;
-    let width = monitor
-        .width()
-        .map_err(|e| format!("Failed to get monitor width: {}", e))?;
-    let height = monitor
-        .height()
-        .map_err(|e| format!("Failed to get monitor height: {}", e))?;
-    let scale_factor = monitor
-        .scale_factor()
-        .map_err(|e| format!("Failed to get monitor scale factor: {}", e))?;
-
     Ok(MonitorShot {
-        id: monitor_id,
+        id: monitor.id,
+        x: monitor.x,
+        y: monitor.y,
+        width: monitor.width,
+        height: monitor.height,
+        scale_factor: monitor.scale_factor,
+        path: screenshot_path.to_string_lossy().into_owned(),
+    })
+}
+
+/// Capture a rectangle in global desktop coordinates, across monitors if needed
+pub fn capture_desktop_region(
+    backend: &dyn CaptureBackend,
+    save_dir: &str,
+    x: i32,
+    y: i32,
+    width: u32,
+    height: u32,
+) -> AppResult<RegionShot> {
+    let image = backend.capture_rect(x, y, width, height)?;
+
+    let save_path = PathBuf::from(save_dir);
+    ensure_dir(&save_path)?;
+
+    let filename = generate_filename("region", "png")?;
+    let screenshot_path = save_path.join(&filename);
+
+    image
+        .save(&screenshot_path)
+        .map_err(|e| format!("Failed to save screenshot: {}", e))?;
+
+    Ok(RegionShot {
         x,
         y,
         width,
         height,
-        scale_factor,
+        scale_factor: image.width() as f32 / width as f32,
         path: screenshot_path.to_string_lossy().into_owned(),
     })
 }
 
-/// Capture primary monitor using the screenshots plugin
-pub async fn capture_primary_monitor(app_handle: tauri::AppHandle) -> AppResult<PathBuf> {
-    use tauri_plugin_screenshots::{get_monitor_screenshot, get_screenshotable_monitors};
+/// List capturable windows, frontmost first
+pub fn list_windows(backend: &dyn CaptureBackend) -> AppResult<Vec<WindowInfo>> {
+    let mut windows: Vec<WindowInfo> = backend
+        .windows()?
+        .into_iter()
+        .filter(|w| w.width > 0 && w.height > 0)
+        .collect();
 
-    let monitors = get_screenshotable_monitors()
-        .await
-        .map_err(|e| format!("Failed to get monitors: {}", e))?;
+    windows.sort_by_key(|w| std::cmp::Reverse(w.z));
+    Ok(windows)
+}
 
-    if monitors.is_empty() {
-        return Err("No monitors available".into());
+/// Pick the frontmost visible window matching a query
+fn find_window(windows: &[WindowInfo], query: &WindowQuery) -> AppResult<WindowInfo> {
+    if query.id.is_none() && query.app_name.is_none() && query.title.is_none() {
+        return Err("Specify a window id, app name or title".into());
     }
 
-    let primary_monitor = monitors.first().ok_or("No monitor found")?;
+    let matching: Vec<&WindowInfo> = windows.iter().filter(|w| query.matches(w)).collect();
+
+    if matching.is_empty() {
+        return Err("No matching window found".into());
+    }
+
+    matching
+        .into_iter()
+        .filter(|w| !w.is_minimized)
+        .max_by_key(|w| w.z)
+        .cloned()
+        .ok_or_else(|| "Matching window is minimized".into())
+}
 
-    let screenshot_path = get_monitor_screenshot(app_handle, primary_monitor.id)
-        .await
-        .map_err(|e| format!("Failed to capture screenshot: {}", e))?;
+/// Capture a window chosen by id or by app name and title
+pub fn capture_window(
+    backend: &dyn CaptureBackend,
+    save_dir: &str,
+    query: &WindowQuery,
+) -> AppResult<WindowShot> {
+    let window = find_window(&list_windows(backend)?, query)?;
+    let image = backend.capture_window(window.id)?;
+
+    let save_path = PathBuf::from(save_dir);
+    ensure_dir(&save_path)?;
+
+    let filename = generate_filename_with_id("window", window.id, "png")?;
+    let screenshot_path = save_path.join(&filename);
+
+    image
+        .save(&screenshot_path)
+        .map_err(|e| format!("Failed to save screenshot: {}", e))?;
 
-    Ok(screenshot_path)
+    Ok(WindowShot {
+        id: window.id,
+        app_name: window.app_name,
+        title: window.title,
+        x: window.x,
+        y: window.y,
+        width: window.width,
+        height: window.height,
+        path: screenshot_path.to_string_lossy().into_owned(),
+    })
+}
+
+/// Capture the primary monitor and save it in the output format, copying
+/// it to `clipboard` in the given flavors when requested. The copy is made
+/// from the captured pixels, so it stays lossless whatever the output format.
+pub fn capture_primary_monitor(
+    backend: &dyn CaptureBackend,
+    save_dir: &str,
+    output: &OutputOptions,
+    clipboard: Option<(&dyn ClipboardBackend, &[ClipboardFlavor])>,
+) -> AppResult<SavedImage> {
+    let monitor = backend.primary_monitor()?;
+    let image = DynamicImage::ImageRgba8(backend.capture_monitor(monitor.id)?);
+
+    let saved = save_image(&image, save_dir, "shot", output)?;
+
+    if let Some((clipboard, flavors)) = clipboard {
+        copy_image_with(clipboard, &saved.path, Some(&image), flavors)?;
+    }
+
+    Ok(saved)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::MockBackend;
+
+    fn temp_save_dir(name: &str) -> PathBuf {
+        let dir =
+            std::env::temp_dir().join(format!("bettershot_test_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        dir
+    }
+
+    mod monitors {
+        use super::*;
+
+        #[test]
+        fn test_capture_all_monitors_saves_each_monitor() {
+            let backend = MockBackend::default();
+            let dir = temp_save_dir("all_monitors");
+
+            let shots = capture_all_monitors(&backend, dir.to_str().unwrap()).unwrap();
+
+            assert_eq!(shots.len(), 2);
+            for (shot, monitor) in shots.iter().zip(&backend.monitors) {
+                assert_eq!(shot.id, monitor.id);
+                assert_eq!((shot.x, shot.y), (monitor.x, monitor.y));
+
+                let saved = image::open(&shot.path).unwrap().to_rgba8();
+                assert_eq!(saved, backend.capture_monitor(monitor.id).unwrap());
+            }
+
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn test_capture_all_monitors_without_monitors() {
+            let backend = MockBackend {
+                monitors: Vec::new(),
+                ..MockBackend::default()
+            };
+            let dir = temp_save_dir("no_monitors");
+
+            assert!(capture_all_monitors(&backend, dir.to_str().unwrap()).is_err());
+        }
+
+        #[test]
+        fn test_capture_all_monitors_stitched() {
+            let mut backend = MockBackend::default();
+            backend.monitors[1].scale_factor = 2.0;
+            let dir = temp_save_dir("stitched");
+
+            let shot =
+                capture_all_monitors_stitched(&backend, dir.to_str().unwrap(), Some(1.5)).unwrap();
+
+            assert_eq!((shot.x, shot.y, shot.width, shot.height), (0, 0, 560, 200));
+            assert_eq!(shot.scale_factor, 1.5);
+
+            let saved = image::open(&shot.path).unwrap().to_rgba8();
+            assert_eq!(saved.dimensions(), (840, 300));
+
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn test_capture_monitor_by_id_and_primary() {
+            let backend = MockBackend::default();
+            let dir = temp_save_dir("single_monitor");
+            let save_dir = dir.to_str().unwrap();
+
+            let shot = capture_monitor(&backend, save_dir, Some(2)).unwrap();
+            assert_eq!((shot.id, shot.width, shot.height), (2, 240, 160));
+
+            let shot = capture_monitor(&backend, save_dir, None).unwrap();
+            assert_eq!(shot.id, 1);
+
+            assert!(capture_monitor(&backend, save_dir, Some(42)).is_err());
+
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+    }
+
+    mod region {
+        use super::*;
+
+        #[test]
+        fn test_capture_desktop_region_spanning_monitors() {
+            let mut backend = MockBackend::default();
+            backend.monitors[1].scale_factor = 2.0;
+            let dir = temp_save_dir("desktop_region");
+
+            let shot =
+                capture_desktop_region(&backend, dir.to_str().unwrap(), 300, 20, 60, 40).unwrap();
+
+            assert_eq!((shot.x, shot.y, shot.width, shot.height), (300, 20, 60, 40));
+            assert_eq!(shot.scale_factor, 2.0);
+
+            let saved = image::open(&shot.path).unwrap().to_rgba8();
+            assert_eq!(saved, backend.capture_rect(300, 20, 60, 40).unwrap());
+
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn test_capture_desktop_region_outside_monitors() {
+            let backend = MockBackend::default();
+            let dir = temp_save_dir("desktop_region_outside");
+
+            let result = capture_desktop_region(&backend, dir.to_str().unwrap(), -500, 0, 10, 10);
+            assert!(result.is_err());
+        }
+    }
+
+    mod windows {
+        use super::*;
+
+        fn query(app_name: Option<&str>, title: Option<&str>) -> WindowQuery {
+            WindowQuery {
+                id: None,
+                app_name: app_name.map(String::from),
+                title: title.map(String::from),
+            }
+        }
+
+        #[test]
+        fn test_list_windows_is_frontmost_first() {
+            let backend = MockBackend::default();
+            let windows = list_windows(&backend).unwrap();
+
+            let ids: Vec<u32> = windows.iter().map(|w| w.id).collect();
+            assert_eq!(ids, vec![101, 102, 103]);
+        }
+
+        #[test]
+        fn test_list_windows_skips_empty_windows() {
+            let mut backend = MockBackend::default();
+            backend.windows[1].width = 0;
+
+            assert_eq!(list_windows(&backend).unwrap().len(), 2);
+        }
+
+        #[test]
+        fn test_find_window_by_id() {
+            let windows = MockBackend::default().windows;
+            let query = WindowQuery {
+                id: Some(102),
+                ..WindowQuery::default()
+            };
+
+            assert_eq!(find_window(&windows, &query).unwrap().id, 102);
+        }
+
+        #[test]
+        fn test_find_window_by_app_and_title() {
+            let windows = MockBackend::default().windows;
+
+            let found = find_window(&windows, &query(Some("browser"), None)).unwrap();
+            assert_eq!(found.id, 101);
+
+            let found = find_window(&windows, &query(None, Some("EXAMPLE"))).unwrap();
+            assert_eq!(found.id, 101);
+
+            assert!(find_window(&windows, &query(Some("Browser"), Some("Inbox"))).is_err());
+        }
+
+        #[test]
+        fn test_find_window_prefers_frontmost() {
+            let mut windows = MockBackend::default().windows;
+            windows[1].app_name = "Browser".to_string();
+            windows[1].z = 5;
+
+            let found = find_window(&windows, &query(Some("Browser"), None)).unwrap();
+            assert_eq!(found.id, 102);
+        }
+
+        #[test]
+        fn test_find_window_skips_minimized() {
+            let windows = MockBackend::default().windows;
+            let err = find_window(&windows, &query(Some("Notes"), None)).unwrap_err();
+            assert!(err.contains("minimized"));
+        }
+
+        #[test]
+        fn test_find_window_requires_criteria() {
+            let windows = MockBackend::default().windows;
+            assert!(find_window(&windows, &WindowQuery::default()).is_err());
+        }
+
+        #[test]
+        fn test_capture_window_saves_image_and_geometry() {
+            let backend = MockBackend::default();
+            let dir = temp_save_dir("window");
+
+            let shot = capture_window(&backend, dir.to_str().unwrap(), &query(Some("term"), None))
+                .unwrap();
+
+            assert_eq!(shot.id, 102);
+            assert_eq!(shot.app_name, "Terminal");
+            assert_eq!(
+                (shot.x, shot.y, shot.width, shot.height),
+                (340, 10, 160, 100)
+            );
+
+            let saved = image::open(&shot.path).unwrap().to_rgba8();
+            assert_eq!(saved, backend.capture_window(102).unwrap());
+
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+    }
+
+    mod quick {
+        use super::*;
+        use crate::clipboard::{RecordingBackend, PNG_MIME};
+        use crate::output::OutputFormat;
+
+        #[test]
+        fn test_capture_primary_monitor_saves_primary() {
+            let mut backend = MockBackend::default();
+            backend.monitors.reverse();
+            let dir = temp_save_dir("quick_primary");
+
+            let saved = capture_primary_monitor(
+                &backend,
+                dir.to_str().unwrap(),
+                &OutputOptions::default(),
+                None,
+            )
+            .unwrap();
+
+            assert!(saved.path.ends_with(".png"));
+            let image = image::open(&saved.path).unwrap().to_rgba8();
+            assert_eq!(image, backend.capture_monitor(1).unwrap());
+
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn test_capture_primary_monitor_copies_lossless_pixels() {
+            let backend = MockBackend::default();
+            let clipboard = RecordingBackend::new();
+            let dir = temp_save_dir("quick_copy");
+            let output = OutputOptions {
+                format: OutputFormat::Jpeg,
+                ..OutputOptions::default()
+            };
+
+            let saved = capture_primary_monitor(
+                &backend,
+                dir.to_str().unwrap(),
+                &output,
+                Some((&clipboard, &[ClipboardFlavor::Png])),
+            )
+            .unwrap();
+
+            assert!(saved.path.ends_with(".jpg"));
+            let writes = clipboard.writes();
+            assert_eq!(writes.len(), 1);
+            assert_eq!(writes[0][0].mime_type, PNG_MIME);
+            let copied = image::load_from_memory(&writes[0][0].data)
+                .unwrap()
+                .to_rgba8();
+            assert_eq!(copied, backend.capture_monitor(1).unwrap());
+
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn test_capture_primary_monitor_without_monitors() {
+            let backend = MockBackend {
+                monitors: Vec::new(),
+                ..MockBackend::default()
+            };
+            let dir = temp_save_dir("quick_none");
+
+            let result = capture_primary_monitor(
+                &backend,
+                dir.to_str().unwrap(),
+                &OutputOptions::default(),
+                None,
+            );
+
+            assert!(result.is_err());
+            assert!(!dir.exists());
+        }
+    }
 }
@@ -0,0 +1,651 @@
+//! Frame sequence editing
+//!
+//! A frame sequence is a folder of captured frames, such as the output of an
+//! interval capture session. Playback order and per-frame delays live in a
+//! `sequence.json` manifest next to the frames; folders without one are read
+//! in natural filename order. Crops and renders rewrite the frame files in
+//! place; trimmed or deleted frames are moved to a `.trash` subfolder rather
+//! than removed, so those edits can be undone by hand.
+
+use image::{DynamicImage, ImageFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use crate::image::{render_background, render_onto_background, CropRegion, RenderSettings};
+use crate::utils::{ensure_dir, AppResult};
+
+/// Manifest file written into a sequence folder
+pub const MANIFEST_FILE: &str = "sequence.json";
+
+/// Subfolder that trimmed and deleted frames are moved to
+const TRASH_DIR: &str = ".trash";
+
+/// Delay given to frames of a folder without a manifest
+const DEFAULT_FRAME_DELAY_MS: u32 = 100;
+
+/// Shortest delay a speed change can produce
+const MIN_FRAME_DELAY_MS: u32 = 10;
+
+/// Extensions recognized as frames when scanning a folder
+const FRAME_EXTENSIONS: [&str; 4] = ["png", "jpg", "jpeg", "webp"];
+
+/// A frame file, relative to the sequence folder, and its display time
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct SequenceEntry {
+    pub file: String,
+    pub delay_ms: u32,
+}
+
+/// Frames of a sequence in playback order
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct FrameSequence {
+    pub frames: Vec<SequenceEntry>,
+}
+
+/// Piece of a filename used for natural ordering
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum NameChunk {
+    /// Digit run, compared by length without leading zeros and then by value
+    Number(usize, String),
+    Text(String),
+}
+
+fn name_chunks(name: &str) -> Vec<NameChunk> {
+    let mut chunks = Vec::new();
+    let mut chars = name.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        let is_digit = c.is_ascii_digit();
+        let mut run = String::new();
+        while let Some(&c) = chars.peek() {
+            if c.is_ascii_digit() != is_digit {
+                break;
+            }
+            run.push(c);
+            chars.next();
+        }
+
+        if is_digit {
+            let digits = run.trim_start_matches('0').to_string();
+            chunks.push(NameChunk::Number(digits.len(), digits));
+        } else {
+            chunks.push(NameChunk::Text(run.to_lowercase()));
+        }
+    }
+
+    chunks
+}
+
+/// Order filenames so that `frame_2` comes before `frame_10`
+fn natural_cmp(a: &str, b: &str) -> Ordering {
+    name_chunks(a).cmp(&name_chunks(b)).then_with(|| a.cmp(b))
+}
+
+fn is_frame_file(path: &Path) -> bool {
+    path.is_file()
+        && path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .is_some_and(|ext| FRAME_EXTENSIONS.contains(&ext.to_lowercase().as_str()))
+}
+
+/// Build a sequence from the frame files in `dir`
+fn scan_sequence(dir: &Path) -> AppResult<FrameSequence> {
+    let mut files: Vec<String> = std::fs::read_dir(dir)
+        .map_err(|e| format!("Failed to read sequence folder: {}", e))?
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| is_frame_file(&entry.path()))
+        .filter_map(|entry| entry.file_name().to_str().map(str::to_string))
+        .collect();
+    files.sort_by(|a, b| natural_cmp(a, b));
+
+    Ok(FrameSequence {
+        frames: files
+            .into_iter()
+            .map(|file| SequenceEntry {
+                file,
+                delay_ms: DEFAULT_FRAME_DELAY_MS,
+            })
+            .collect(),
+    })
+}
+
+/// Reject manifest entries that could reach outside the sequence folder
+fn validate_entry(entry: &SequenceEntry) -> AppResult<()> {
+    let mut components = Path::new(&entry.file).components();
+    let plain_name = matches!(
+        (components.next(), components.next()),
+        (Some(Component::Normal(name)), None) if name == entry.file.as_str()
+    );
+    // Backslashes are separators on Windows but plain characters elsewhere
+    if !plain_name || entry.file.contains('\\') {
+        return Err(format!(
+            "Invalid frame {:?}: frames must be files directly in the sequence folder",
+            entry.file
+        ));
+    }
+    Ok(())
+}
+
+/// Load the sequence in `dir`, from its manifest if it has one
+pub fn load_sequence(dir: &str) -> AppResult<FrameSequence> {
+    let dir = Path::new(dir);
+    let manifest = dir.join(MANIFEST_FILE);
+
+    let sequence = if manifest.exists() {
+        let data = std::fs::read_to_string(&manifest)
+            .map_err(|e| format!("Failed to read sequence manifest: {}", e))?;
+        serde_json::from_str::<FrameSequence>(&data)
+            .map_err(|e| format!("Failed to parse sequence manifest: {}", e))?
+    } else {
+        scan_sequence(dir)?
+    };
+
+    if sequence.frames.is_empty() {
+        return Err(format!("No frames found in {}", dir.display()));
+    }
+    sequence.frames.iter().try_for_each(validate_entry)?;
+    Ok(sequence)
+}
+
+fn save_sequence(dir: &Path, sequence: &FrameSequence) -> AppResult<()> {
+    let data = serde_json::to_string_pretty(sequence)
+        .map_err(|e| format!("Failed to serialize sequence manifest: {}", e))?;
+    std::fs::write(dir.join(MANIFEST_FILE), data)
+        .map_err(|e| format!("Failed to save sequence manifest: {}", e))
+}
+
+/// Save `kept` as the new sequence and move the files of dropped frames to
+/// the trash subfolder
+fn replace_frames(
+    dir: &str,
+    old: FrameSequence,
+    kept: Vec<SequenceEntry>,
+) -> AppResult<FrameSequence> {
+    if kept.is_empty() {
+        return Err("A sequence must keep at least one frame".to_string());
+    }
+
+    let sequence = FrameSequence { frames: kept };
+    save_sequence(Path::new(dir), &sequence)?;
+
+    let trash = Path::new(dir).join(TRASH_DIR);
+    for entry in old.frames {
+        if sequence.frames.iter().any(|kept| kept.file == entry.file) {
+            continue;
+        }
+        // The manifest no longer lists the frame, so a failed move only
+        // leaves an unused file behind
+        if ensure_dir(&trash).is_ok() {
+            let _ = std::fs::rename(Path::new(dir).join(&entry.file), trash.join(&entry.file));
+        }
+    }
+
+    Ok(sequence)
+}
+
+/// Keep frames from `start` up to but not including `end`
+pub fn trim_sequence(dir: &str, start: usize, end: usize) -> AppResult<FrameSequence> {
+    let sequence = load_sequence(dir)?;
+    if start >= end || end > sequence.frames.len() {
+        return Err(format!(
+            "Invalid trim range {}..{} for {} frames",
+            start,
+            end,
+            sequence.frames.len()
+        ));
+    }
+
+    let kept = sequence.frames[start..end].to_vec();
+    replace_frames(dir, sequence, kept)
+}
+
+/// Remove the frames at `indices`
+pub fn delete_frames(dir: &str, indices: &[usize]) -> AppResult<FrameSequence> {
+    let sequence = load_sequence(dir)?;
+    if let Some(&index) = indices.iter().find(|&&i| i >= sequence.frames.len()) {
+        return Err(format!(
+            "Frame {} is out of range for {} frames",
+            index,
+            sequence.frames.len()
+        ));
+    }
+
+    let kept = sequence
+        .frames
+        .iter()
+        .enumerate()
+        .filter(|(i, _)| !indices.contains(i))
+        .map(|(_, entry)| entry.clone())
+        .collect();
+    replace_frames(dir, sequence, kept)
+}
+
+/// Scale playback speed; a `factor` of 2 plays twice as fast
+pub fn change_speed(dir: &str, factor: f64) -> AppResult<FrameSequence> {
+    if !factor.is_finite() || factor <= 0.0 {
+        return Err("Speed factor must be greater than zero".to_string());
+    }
+
+    let mut sequence = load_sequence(dir)?;
+    for entry in &mut sequence.frames {
+        let delay = (entry.delay_ms as f64 / factor).round();
+        entry.delay_ms = delay.clamp(MIN_FRAME_DELAY_MS as f64, u32::MAX as f64) as u32;
+    }
+
+    save_sequence(Path::new(dir), &sequence)?;
+    Ok(sequence)
+}
+
+/// Apply `edit` to every frame, replacing the files only once all succeed
+fn edit_frames(
+    dir: &str,
+    mut edit: impl FnMut(RgbaImage) -> AppResult<RgbaImage>,
+) -> AppResult<FrameSequence> {
+    let sequence = load_sequence(dir)?;
+    let mut edited: Vec<(PathBuf, PathBuf)> = Vec::new();
+
+    let result = sequence.frames.iter().try_for_each(|entry| {
+        let path = Path::new(dir).join(&entry.file);
+        let format = ImageFormat::from_path(&path)
+            .map_err(|e| format!("Unsupported frame {}: {}", entry.file, e))?;
+        let frame = image::open(&path)
+            .map_err(|e| format!("Failed to open frame {}: {}", entry.file, e))?
+            .to_rgba8();
+
+        let frame = edit(frame)?;
+        let output = DynamicImage::ImageRgba8(frame);
+        // JPEG has no alpha channel
+        let output = match format {
+            ImageFormat::Jpeg => DynamicImage::ImageRgb8(output.to_rgb8()),
+            _ => output,
+        };
+
+        let temp = Path::new(dir).join(format!(".{}.tmp", entry.file));
+        edited.push((temp.clone(), path));
+        output
+            .save_with_format(&temp, format)
+            .map_err(|e| format!("Failed to save frame {}: {}", entry.file, e))
+    });
+
+    if let Err(e) = result {
+        for (temp, _) in &edited {
+            let _ = std::fs::remove_file(temp);
+        }
+        return Err(e);
+    }
+
+    for (temp, path) in &edited {
+        std::fs::rename(temp, path).map_err(|e| format!("Failed to replace frame: {}", e))?;
+    }
+
+    save_sequence(Path::new(dir), &sequence)?;
+    Ok(sequence)
+}
+
+/// Crop every frame to `region`, clamped to each frame's bounds
+pub fn crop_sequence(dir: &str, region: CropRegion) -> AppResult<FrameSequence> {
+    edit_frames(dir, |frame| {
+        let clamped = CropRegion::clamped(
+            region.x,
+            region.y,
+            region.width,
+            region.height,
+            frame.width(),
+            frame.height(),
+        );
+        if !clamped.is_valid() {
+            return Err(format!(
+                "Invalid crop region: x={}, y={}, w={}, h={} (frame: {}x{})",
+                region.x,
+                region.y,
+                region.width,
+                region.height,
+                frame.width(),
+                frame.height()
+            ));
+        }
+
+        Ok(
+            image::imageops::crop_imm(&frame, clamped.x, clamped.y, clamped.width, clamped.height)
+                .to_image(),
+        )
+    })
+}
+
+/// Render every frame onto the background described by `settings`. The
+/// background is rendered once per frame size, so noise doesn't flicker.
+pub fn render_sequence(dir: &str, settings: &RenderSettings) -> AppResult<FrameSequence> {
+    let mut backgrounds: HashMap<(u32, u32), RgbaImage> = HashMap::new();
+
+    edit_frames(dir, |frame| {
+        let (width, height) = frame.dimensions();
+        let background = match backgrounds.get(&(width, height)) {
+            Some(background) => background.clone(),
+            None => {
+                let background = render_background(width, height, settings)?;
+                backgrounds.insert((width, height), background.clone());
+                background
+            }
+        };
+        Ok(render_onto_background(
+            &DynamicImage::ImageRgba8(frame),
+            settings,
+            background,
+        ))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::Rgba;
+
+    /// A sequence folder of `count` 20x10 frames, each filled with its index
+    fn sequence_dir(name: &str, count: u8) -> String {
+        let dir = std::env::temp_dir().join(format!(
+            "bettershot_test_sequence_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+
+        for i in 0..count {
+            RgbaImage::from_pixel(20, 10, Rgba([i, 0, 0, 255]))
+                .save(dir.join(format!("frame_{}.png", i + 1)))
+                .unwrap();
+        }
+        dir.to_string_lossy().into_owned()
+    }
+
+    fn files(sequence: &FrameSequence) -> Vec<&str> {
+        sequence.frames.iter().map(|f| f.file.as_str()).collect()
+    }
+
+    fn frame(dir: &str, entry: &SequenceEntry) -> RgbaImage {
+        image::open(Path::new(dir).join(&entry.file))
+            .unwrap()
+            .to_rgba8()
+    }
+
+    #[test]
+    fn test_natural_order() {
+        let mut names = vec![
+            "frame_10.png",
+            "frame_2.png",
+            "Frame_1.png",
+            "frame_02b.png",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+        assert_eq!(
+            names,
+            vec![
+                "Frame_1.png",
+                "frame_2.png",
+                "frame_02b.png",
+                "frame_10.png"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_load_scans_folder_without_manifest() {
+        let dir = sequence_dir("scan", 12);
+        std::fs::write(Path::new(&dir).join("notes.txt"), "not a frame").unwrap();
+
+        let sequence = load_sequence(&dir).unwrap();
+        assert_eq!(sequence.frames.len(), 12);
+        assert_eq!(sequence.frames[1].file, "frame_2.png");
+        assert_eq!(sequence.frames[11].file, "frame_12.png");
+        assert!(sequence
+            .frames
+            .iter()
+            .all(|f| f.delay_ms == DEFAULT_FRAME_DELAY_MS));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_load_empty_folder_fails() {
+        let dir = sequence_dir("empty", 0);
+        assert!(load_sequence(&dir).is_err());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_trim_keeps_range_and_trashes_rest() {
+        let dir = sequence_dir("trim", 5);
+
+        let sequence = trim_sequence(&dir, 1, 4).unwrap();
+        assert_eq!(
+            files(&sequence),
+            vec!["frame_2.png", "frame_3.png", "frame_4.png"]
+        );
+        for dropped in ["frame_1.png", "frame_5.png"] {
+            assert!(!Path::new(&dir).join(dropped).exists());
+            assert!(Path::new(&dir).join(TRASH_DIR).join(dropped).exists());
+        }
+
+        // The manifest now defines the sequence
+        assert_eq!(load_sequence(&dir).unwrap(), sequence);
+
+        assert!(trim_sequence(&dir, 2, 2).is_err());
+        assert!(trim_sequence(&dir, 0, 4).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_delete_frames() {
+        let dir = sequence_dir("delete", 4);
+
+        let sequence = delete_frames(&dir, &[0, 2]).unwrap();
+        assert_eq!(files(&sequence), vec!["frame_2.png", "frame_4.png"]);
+        assert!(!Path::new(&dir).join("frame_3.png").exists());
+        assert!(Path::new(&dir).join(TRASH_DIR).join("frame_3.png").exists());
+
+        assert!(delete_frames(&dir, &[5]).is_err());
+        assert!(delete_frames(&dir, &[0, 1]).is_err());
+        assert_eq!(load_sequence(&dir).unwrap().frames.len(), 2);
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_manifest_entries_outside_folder_are_rejected() {
+        let dir = sequence_dir("escape", 2);
+        let victim = format!(
+            "bettershot_test_sequence_escape_victim_{}.png",
+            std::process::id()
+        );
+        let outside = std::env::temp_dir().join(&victim);
+        std::fs::write(&outside, b"keep me").unwrap();
+
+        for file in [
+            format!("../{}", victim),
+            outside.to_string_lossy().into_owned(),
+            "sub/frame_1.png".to_string(),
+            "sub\\frame_1.png".to_string(),
+            "..".to_string(),
+            ".".to_string(),
+            String::new(),
+        ] {
+            let manifest = FrameSequence {
+                frames: vec![
+                    SequenceEntry {
+                        file: "frame_1.png".to_string(),
+                        delay_ms: 100,
+                    },
+                    SequenceEntry {
+                        file: file.clone(),
+                        delay_ms: 100,
+                    },
+                ],
+            };
+            save_sequence(Path::new(&dir), &manifest).unwrap();
+
+            assert!(load_sequence(&dir).is_err(), "accepted {:?}", file);
+            assert!(delete_frames(&dir, &[1]).is_err());
+        }
+        assert_eq!(std::fs::read(&outside).unwrap(), b"keep me");
+
+        let _ = std::fs::remove_file(&outside);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_change_speed() {
+        let dir = sequence_dir("speed", 2);
+
+        let sequence = change_speed(&dir, 2.0).unwrap();
+        assert!(sequence.frames.iter().all(|f| f.delay_ms == 50));
+
+        let sequence = change_speed(&dir, 0.25).unwrap();
+        assert!(sequence.frames.iter().all(|f| f.delay_ms == 200));
+
+        let sequence = change_speed(&dir, 1000.0).unwrap();
+        assert!(sequence
+            .frames
+            .iter()
+            .all(|f| f.delay_ms == MIN_FRAME_DELAY_MS));
+
+        assert!(change_speed(&dir, 0.0).is_err());
+        assert!(change_speed(&dir, f64::NAN).is_err());
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_crop_every_frame() {
+        let dir = sequence_dir("crop", 3);
+        let region = CropRegion {
+            x: 5,
+            y: 2,
+            width: 100,
+            height: 4,
+        };
+
+        let sequence = crop_sequence(&dir, region).unwrap();
+        for (i, entry) in sequence.frames.iter().enumerate() {
+            let image = frame(&dir, entry);
+            assert_eq!(image.dimensions(), (15, 4));
+            assert_eq!(*image.get_pixel(0, 0), Rgba([i as u8, 0, 0, 255]));
+        }
+
+        let outside = CropRegion {
+            x: 0,
+            y: 0,
+            width: 0,
+            height: 4,
+        };
+        assert!(crop_sequence(&dir, outside).is_err());
+        assert_eq!(frame(&dir, &sequence.frames[0]).dimensions(), (15, 4));
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_failed_edit_leaves_frames_untouched() {
+        let dir = sequence_dir("failed_edit", 3);
+        std::fs::write(Path::new(&dir).join("frame_3.png"), b"not a png").unwrap();
+
+        let region = CropRegion {
+            x: 0,
+            y: 0,
+            width: 4,
+            height: 4,
+        };
+        assert!(crop_sequence(&dir, region).is_err());
+
+        let names: Vec<String> = std::fs::read_dir(&dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+            .collect();
+        assert_eq!(names.len(), 3);
+        assert_eq!(
+            image::open(Path::new(&dir).join("frame_1.png"))
+                .unwrap()
+                .to_rgba8()
+                .dimensions(),
+            (20, 10)
+        );
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_render_every_frame() {
+        let dir = sequence_dir("render", 2);
+        let settings: RenderSettings = serde_json::from_str(
+            r##"{
+                "background_type": "custom",
+                "custom_color": "#00ff00",
+                "blur_amount": 0,
+                "noise_amount": 0,
+                "border_radius": 0,
+                "padding_top": 4,
+                "padding_bottom": 4,
+                "padding_left": 6,
+                "padding_right": 6,
+                "shadow_blur": 0,
+                "shadow_offset_x": 0,
+                "shadow_offset_y": 0,
+                "shadow_opacity": 0
+            }"##,
+        )
+        .unwrap();
+
+        let sequence = render_sequence(&dir, &settings).unwrap();
+        for (i, entry) in sequence.frames.iter().enumerate() {
+            let image = frame(&dir, entry);
+            assert_eq!(image.dimensions(), (32, 18));
+            assert_eq!(*image.get_pixel(0, 0), Rgba([0, 255, 0, 255]));
+            assert_eq!(*image.get_pixel(10, 8), Rgba([i as u8, 0, 0, 255]));
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_render_noise_is_the_same_on_every_frame() {
+        let dir = sequence_dir("render_noise", 3);
+        let settings: RenderSettings = serde_json::from_str(
+            r##"{
+                "background_type": "custom",
+                "custom_color": "#808080",
+                "blur_amount": 0,
+                "noise_amount": 40,
+                "border_radius": 0,
+                "padding_top": 4,
+                "padding_bottom": 4,
+                "padding_left": 6,
+                "padding_right": 6,
+                "shadow_blur": 0,
+                "shadow_offset_x": 0,
+                "shadow_offset_y": 0,
+                "shadow_opacity": 0
+            }"##,
+        )
+        .unwrap();
+
+        let sequence = render_sequence(&dir, &settings).unwrap();
+        let padding = |image: &RgbaImage| -> Vec<Rgba<u8>> {
+            (0..image.width())
+                .flat_map(|x| (0..4).map(move |y| (x, y)))
+                .map(|(x, y)| *image.get_pixel(x, y))
+                .collect()
+        };
+        let first = padding(&frame(&dir, &sequence.frames[0]));
+        assert!(first
+            .iter()
+            .any(|pixel| *pixel != Rgba([128, 128, 128, 255])));
+        for entry in &sequence.frames[1..] {
+            assert_eq!(padding(&frame(&dir, entry)), first);
+        }
+
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
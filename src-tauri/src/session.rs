@@ -0,0 +1,607 @@
+//! Interval capture sessions
+//!
+//! A session captures a monitor, window or rectangle every few seconds on a
+//! background thread, like a time-lapse. Frames are written as sequentially
+//! numbered PNGs until the session is stopped or hits its frame or disk
+//! budget, and progress is reported after every frame.
+
+use image::{ImageFormat, RgbaImage};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use crate::capture::{CaptureBackend, CaptureTarget};
+use crate::utils::{ensure_dir, generate_filename_with_id, AppResult};
+
+/// Event emitted to the frontend with a `SessionProgress` payload
+pub const SESSION_PROGRESS_EVENT: &str = "capture-session-progress";
+
+/// Shortest allowed interval between frames, in seconds
+const MIN_INTERVAL_SECONDS: f64 = 0.1;
+
+/// How often a waiting session checks whether it was stopped
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Consecutive failed captures after which a session gives up
+const MAX_CONSECUTIVE_ERRORS: u32 = 3;
+
+fn default_max_frames() -> u32 {
+    1000
+}
+
+fn default_max_bytes() -> u64 {
+    1024 * 1024 * 1024
+}
+
+/// Settings for a capture session
+#[derive(Deserialize, Clone, Debug)]
+pub struct SessionConfig {
+    pub target: CaptureTarget,
+    pub save_dir: String,
+    pub interval_seconds: f64,
+    #[serde(default = "default_max_frames")]
+    pub max_frames: u32,
+    /// Total size of written frames, in bytes
+    #[serde(default = "default_max_bytes")]
+    pub max_bytes: u64,
+}
+
+/// Lifecycle state reported with each progress update
+#[derive(Serialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum SessionState {
+    Running,
+    Stopped,
+    FrameLimitReached,
+    DiskBudgetReached,
+    Failed,
+}
+
+/// Progress of a capture session
+#[derive(Serialize, Clone, Debug)]
+pub struct SessionProgress {
+    pub session_id: u32,
+    pub state: SessionState,
+    pub frames: u32,
+    pub bytes: u64,
+    pub last_path: Option<String>,
+    pub error: Option<String>,
+}
+
+struct RunningSession {
+    stop: Arc<AtomicBool>,
+    handle: JoinHandle<SessionProgress>,
+}
+
+/// Tracks running sessions so they can be stopped
+pub struct SessionManager {
+    next_id: AtomicU32,
+    sessions: Mutex<Option<HashMap<u32, RunningSession>>>,
+}
+
+/// The app-wide session manager
+pub static CAPTURE_SESSIONS: SessionManager = SessionManager::new();
+
+impl SessionManager {
+    pub const fn new() -> Self {
+        Self {
+            next_id: AtomicU32::new(1),
+            sessions: Mutex::new(None),
+        }
+    }
+
+    /// Validate `config` and start capturing on a background thread.
+    ///
+    /// `on_progress` is called after every frame and once more when the
+    /// session ends. Returns the new session id.
+    pub fn start(
+        &self,
+        backend: Box<dyn CaptureBackend>,
+        config: SessionConfig,
+        on_progress: impl Fn(&SessionProgress) + Send + 'static,
+    ) -> AppResult<u32> {
+        validate_config(&config)?;
+        ensure_dir(&PathBuf::from(&config.save_dir))?;
+
+        let mut sessions = self
+            .sessions
+            .lock()
+            .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+        let sessions = sessions.get_or_insert_with(HashMap::new);
+        sessions.retain(|_, s| !s.handle.is_finished());
+
+        let session_id = self.next_id.fetch_add(1, Ordering::SeqCst);
+        let stop = Arc::new(AtomicBool::new(false));
+        let thread_stop = stop.clone();
+
+        let handle = std::thread::Builder::new()
+            .name(format!("capture-session-{}", session_id))
+            .spawn(move || {
+                run_session(
+                    backend.as_ref(),
+                    session_id,
+                    &config,
+                    &thread_stop,
+                    on_progress,
+                )
+            })
+            .map_err(|e| format!("Failed to start capture session: {}", e))?;
+
+        sessions.insert(session_id, RunningSession { stop, handle });
+        Ok(session_id)
+    }
+
+    /// Stop a session and wait for it to finish, returning its final progress
+    pub fn stop(&self, session_id: u32) -> AppResult<SessionProgress> {
+        let session = self
+            .sessions
+            .lock()
+            .map_err(|e| format!("Failed to acquire lock: {}", e))?
+            .as_mut()
+            .and_then(|sessions| sessions.remove(&session_id))
+            .ok_or_else(|| format!("Capture session {} not found", session_id))?;
+
+        session.stop.store(true, Ordering::SeqCst);
+        session
+            .handle
+            .join()
+            .map_err(|_| format!("Capture session {} panicked", session_id))
+    }
+
+    /// Ids of the sessions that are still capturing
+    pub fn active(&self) -> AppResult<Vec<u32>> {
+        let sessions = self
+            .sessions
+            .lock()
+            .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+
+        let mut ids: Vec<u32> = sessions
+            .iter()
+            .flatten()
+            .filter(|(_, s)| !s.handle.is_finished())
+            .map(|(id, _)| *id)
+            .collect();
+        ids.sort_unstable();
+        Ok(ids)
+    }
+}
+
+fn validate_config(config: &SessionConfig) -> AppResult<()> {
+    if !config.interval_seconds.is_finite() || config.interval_seconds < MIN_INTERVAL_SECONDS {
+        return Err(format!(
+            "Capture interval must be at least {} seconds",
+            MIN_INTERVAL_SECONDS
+        ));
+    }
+    if config.max_frames == 0 {
+        return Err("Maximum frame count must be at least 1".to_string());
+    }
+    if config.max_bytes == 0 {
+        return Err("Disk budget must be greater than zero".to_string());
+    }
+    Ok(())
+}
+
+fn encode_png(image: &RgbaImage) -> AppResult<Vec<u8>> {
+    let mut buffer = Vec::new();
+    image
+        .write_to(&mut Cursor::new(&mut buffer), ImageFormat::Png)
+        .map_err(|e| format!("Failed to encode frame: {}", e))?;
+    Ok(buffer)
+}
+
+/// Sleep until `deadline`, returning early (with `true`) if `stop` is set
+fn wait_until(deadline: Instant, stop: &AtomicBool) -> bool {
+    loop {
+        if stop.load(Ordering::SeqCst) {
+            return true;
+        }
+        let now = Instant::now();
+        if now >= deadline {
+            return false;
+        }
+        std::thread::sleep(POLL_INTERVAL.min(deadline - now));
+    }
+}
+
+/// Capture frames until stopped or a limit is hit
+fn run_session(
+    backend: &dyn CaptureBackend,
+    session_id: u32,
+    config: &SessionConfig,
+    stop: &AtomicBool,
+    on_progress: impl Fn(&SessionProgress),
+) -> SessionProgress {
+    let interval = Duration::from_secs_f64(config.interval_seconds);
+    let save_path = PathBuf::from(&config.save_dir);
+    let prefix = format!("session{}", session_id);
+
+    let mut progress = SessionProgress {
+        session_id,
+        state: SessionState::Running,
+        frames: 0,
+        bytes: 0,
+        last_path: None,
+        error: None,
+    };
+    let mut consecutive_errors = 0;
+    let mut next_frame = Instant::now();
+
+    loop {
+        if stop.load(Ordering::SeqCst) {
+            progress.state = SessionState::Stopped;
+            break;
+        }
+
+        let frame = config
+            .target
+            .capture(backend)
+            .and_then(|image| encode_png(&image));
+
+        match frame {
+            Ok(png) => {
+                consecutive_errors = 0;
+                progress.error = None;
+
+                if progress.bytes + png.len() as u64 > config.max_bytes {
+                    progress.state = SessionState::DiskBudgetReached;
+                    break;
+                }
+
+                let written = generate_filename_with_id(&prefix, progress.frames + 1, "png")
+                    .and_then(|filename| {
+                        let path = save_path.join(filename);
+                        std::fs::write(&path, &png)
+                            .map_err(|e| format!("Failed to save frame: {}", e))?;
+                        Ok(path)
+                    });
+
+                match written {
+                    Ok(path) => {
+                        progress.frames += 1;
+                        progress.bytes += png.len() as u64;
+                        progress.last_path = Some(path.to_string_lossy().into_owned());
+                    }
+                    Err(e) => {
+                        // Failing to write is not transient, unlike a missed capture
+                        progress.state = SessionState::Failed;
+                        progress.error = Some(e);
+                        break;
+                    }
+                }
+            }
+            Err(e) => {
+                consecutive_errors += 1;
+                progress.error = Some(e);
+                if consecutive_errors >= MAX_CONSECUTIVE_ERRORS {
+                    progress.state = SessionState::Failed;
+                    break;
+                }
+            }
+        }
+
+        if progress.frames >= config.max_frames {
+            progress.state = SessionState::FrameLimitReached;
+            break;
+        }
+
+        on_progress(&progress);
+
+        // Schedule from the previous deadline so slow captures don't drift,
+        // but after a stall take one frame now rather than a burst
+        next_frame += interval;
+        let now = Instant::now();
+        if next_frame < now {
+            next_frame = now;
+        }
+        if wait_until(next_frame, stop) {
+            progress.state = SessionState::Stopped;
+            break;
+        }
+    }
+
+    on_progress(&progress);
+    progress
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::capture::MockBackend;
+
+    fn temp_save_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "bettershot_test_session_{}_{}",
+            name,
+            std::process::id()
+        ));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn config(dir: &std::path::Path, target: CaptureTarget) -> SessionConfig {
+        SessionConfig {
+            target,
+            save_dir: dir.to_string_lossy().into_owned(),
+            interval_seconds: MIN_INTERVAL_SECONDS,
+            max_frames: 3,
+            max_bytes: default_max_bytes(),
+        }
+    }
+
+    fn rect_target() -> CaptureTarget {
+        CaptureTarget::Rect {
+            x: 10,
+            y: 10,
+            width: 32,
+            height: 24,
+        }
+    }
+
+    mod config {
+        use super::*;
+
+        #[test]
+        fn test_config_deserialize_defaults() {
+            let config: SessionConfig = serde_json::from_str(
+                r#"{"target": {"type": "window", "id": 3}, "save_dir": "/tmp", "interval_seconds": 5}"#,
+            )
+            .unwrap();
+
+            assert_eq!(config.target, CaptureTarget::Window { id: 3 });
+            assert_eq!(config.max_frames, default_max_frames());
+            assert_eq!(config.max_bytes, default_max_bytes());
+        }
+
+        #[test]
+        fn test_validate_config() {
+            let dir = std::env::temp_dir();
+            assert!(validate_config(&config(&dir, rect_target())).is_ok());
+
+            let mut bad = config(&dir, rect_target());
+            bad.interval_seconds = 0.01;
+            assert!(validate_config(&bad).is_err());
+
+            let mut bad = config(&dir, rect_target());
+            bad.interval_seconds = f64::NAN;
+            assert!(validate_config(&bad).is_err());
+
+            let mut bad = config(&dir, rect_target());
+            bad.max_frames = 0;
+            assert!(validate_config(&bad).is_err());
+
+            let mut bad = config(&dir, rect_target());
+            bad.max_bytes = 0;
+            assert!(validate_config(&bad).is_err());
+        }
+    }
+
+    mod run {
+        use super::*;
+        use crate::capture::{MonitorInfo, WindowInfo};
+        use crate::cursor::CursorState;
+
+        #[test]
+        fn test_session_stops_at_frame_limit() {
+            let dir = temp_save_dir("frame_limit");
+            let stop = AtomicBool::new(false);
+            let updates = Mutex::new(Vec::new());
+
+            let progress = run_session(
+                &MockBackend::default(),
+                7,
+                &config(&dir, rect_target()),
+                &stop,
+                |p| updates.lock().unwrap().push(p.clone()),
+            );
+
+            assert_eq!(progress.state, SessionState::FrameLimitReached);
+            assert_eq!(progress.frames, 3);
+
+            let updates = updates.into_inner().unwrap();
+            let frames: Vec<u32> = updates.iter().map(|p| p.frames).collect();
+            assert_eq!(frames, vec![1, 2, 3]);
+            assert_eq!(updates[2].state, SessionState::FrameLimitReached);
+
+            let mut names: Vec<String> = std::fs::read_dir(&dir)
+                .unwrap()
+                .map(|e| e.unwrap().file_name().to_string_lossy().into_owned())
+                .collect();
+            names.sort();
+            assert_eq!(names.len(), 3);
+            for (i, name) in names.iter().enumerate() {
+                assert!(name.starts_with(&format!("session7_{}_", i + 1)));
+            }
+
+            let total: u64 = names
+                .iter()
+                .map(|n| std::fs::metadata(dir.join(n)).unwrap().len())
+                .sum();
+            assert_eq!(total, progress.bytes);
+
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn test_session_respects_disk_budget() {
+            let dir = temp_save_dir("disk_budget");
+            let backend = MockBackend::default();
+            let frame_size = encode_png(&rect_target().capture(&backend).unwrap())
+                .unwrap()
+                .len() as u64;
+
+            let mut config = config(&dir, rect_target());
+            config.max_frames = 10;
+            config.max_bytes = frame_size * 2 + frame_size / 2;
+
+            let progress = run_session(&backend, 1, &config, &AtomicBool::new(false), |_| {});
+
+            assert_eq!(progress.state, SessionState::DiskBudgetReached);
+            assert_eq!(progress.frames, 2);
+            assert!(progress.bytes <= config.max_bytes);
+            assert_eq!(std::fs::read_dir(&dir).unwrap().count(), 2);
+
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn test_session_fails_after_repeated_capture_errors() {
+            let dir = temp_save_dir("errors");
+            let progress = run_session(
+                &MockBackend::default(),
+                1,
+                &config(&dir, CaptureTarget::Window { id: 999 }),
+                &AtomicBool::new(false),
+                |_| {},
+            );
+
+            assert_eq!(progress.state, SessionState::Failed);
+            assert_eq!(progress.frames, 0);
+            assert!(progress.error.unwrap().contains("999"));
+
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+
+        /// Mock backend whose first window capture stalls, recording when
+        /// each capture starts
+        struct StallingBackend {
+            inner: MockBackend,
+            stall: Duration,
+            starts: Mutex<Vec<Instant>>,
+        }
+
+        impl CaptureBackend for StallingBackend {
+            fn monitors(&self) -> AppResult<Vec<MonitorInfo>> {
+                self.inner.monitors()
+            }
+
+            fn windows(&self) -> AppResult<Vec<WindowInfo>> {
+                self.inner.windows()
+            }
+
+            fn cursor(&self) -> AppResult<CursorState> {
+                self.inner.cursor()
+            }
+
+            fn capture_monitor(&self, id: u32) -> AppResult<RgbaImage> {
+                self.inner.capture_monitor(id)
+            }
+
+            fn capture_window(&self, id: u32) -> AppResult<RgbaImage> {
+                let first = {
+                    let mut starts = self.starts.lock().unwrap();
+                    starts.push(Instant::now());
+                    starts.len() == 1
+                };
+                if first {
+                    std::thread::sleep(self.stall);
+                }
+                self.inner.capture_window(id)
+            }
+
+            fn capture_monitor_region(
+                &self,
+                id: u32,
+                x: u32,
+                y: u32,
+                width: u32,
+                height: u32,
+            ) -> AppResult<RgbaImage> {
+                self.inner.capture_monitor_region(id, x, y, width, height)
+            }
+        }
+
+        #[test]
+        fn test_session_does_not_burst_after_a_stall() {
+            let dir = temp_save_dir("stall");
+            let interval = Duration::from_secs_f64(MIN_INTERVAL_SECONDS);
+            let backend = StallingBackend {
+                inner: MockBackend::default(),
+                stall: interval * 4,
+                starts: Mutex::new(Vec::new()),
+            };
+
+            let mut config = config(&dir, CaptureTarget::Window { id: 101 });
+            config.max_frames = 4;
+
+            let progress = run_session(&backend, 1, &config, &AtomicBool::new(false), |_| {});
+            assert_eq!(progress.frames, 4);
+
+            // The frame missed during the stall is taken at once, then the
+            // schedule resumes instead of firing the other missed frames
+            let starts = backend.starts.into_inner().unwrap();
+            assert_eq!(starts.len(), 4);
+            assert!(starts[3] - starts[1] >= interval * 3 / 2);
+
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn test_stopped_session_captures_nothing() {
+            let dir = temp_save_dir("stopped");
+            let progress = run_session(
+                &MockBackend::default(),
+                1,
+                &config(&dir, rect_target()),
+                &AtomicBool::new(true),
+                |_| {},
+            );
+
+            assert_eq!(progress.state, SessionState::Stopped);
+            assert_eq!(progress.frames, 0);
+
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+    }
+
+    mod manager {
+        use super::*;
+
+        #[test]
+        fn test_start_and_stop_session() {
+            let dir = temp_save_dir("manager");
+            let manager = SessionManager::new();
+
+            let mut config = config(&dir, CaptureTarget::Monitor { id: None });
+            config.interval_seconds = 60.0;
+            config.max_frames = 100;
+
+            let (sender, receiver) = std::sync::mpsc::channel();
+            let id = manager
+                .start(Box::new(MockBackend::default()), config, move |p| {
+                    let _ = sender.send(p.clone());
+                })
+                .unwrap();
+            assert_eq!(manager.active().unwrap(), vec![id]);
+
+            // Wait for the first frame before stopping
+            let first = receiver.recv().unwrap();
+            assert_eq!((first.session_id, first.frames), (id, 1));
+
+            let progress = manager.stop(id).unwrap();
+            assert_eq!(progress.state, SessionState::Stopped);
+            assert_eq!(progress.frames, 1);
+            assert!(manager.active().unwrap().is_empty());
+            assert!(manager.stop(id).is_err());
+
+            let _ = std::fs::remove_dir_all(&dir);
+        }
+
+        #[test]
+        fn test_start_rejects_invalid_config() {
+            let manager = SessionManager::new();
+            let mut config = config(&std::env::temp_dir(), rect_target());
+            config.interval_seconds = 0.0;
+
+            assert!(manager
+                .start(Box::new(MockBackend::default()), config, |_| {})
+                .is_err());
+        }
+    }
+}
@@ -0,0 +1,194 @@
+//! Delayed capture countdown
+//!
+//! Captures can wait a few seconds before grabbing the screen so menus and
+//! hover states can be set up. Only one countdown runs at a time and it can
+//! be cancelled from another command.
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::utils::AppResult;
+
+/// Event emitted to the frontend once per second while counting down
+pub const COUNTDOWN_EVENT: &str = "capture-countdown";
+
+/// Error returned when a countdown is cancelled
+pub const COUNTDOWN_CANCELLED: &str = "Capture was cancelled during the countdown";
+
+/// How often a sleeping countdown checks for cancellation
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Payload of a countdown event; `remaining` reaches 0 right before capture
+#[derive(Serialize, Clone, Debug, PartialEq)]
+pub struct CountdownTick {
+    pub remaining: u32,
+}
+
+/// Tracks the running countdown so it can be cancelled
+pub struct CaptureTimer {
+    active: Mutex<Option<Arc<AtomicBool>>>,
+}
+
+/// The app-wide capture timer
+pub static CAPTURE_TIMER: CaptureTimer = CaptureTimer::new();
+
+/// Clears the active countdown when it finishes, however it finishes
+struct ActiveGuard<'a>(&'a CaptureTimer);
+
+impl Drop for ActiveGuard<'_> {
+    fn drop(&mut self) {
+        if let Ok(mut active) = self.0.active.lock() {
+            *active = None;
+        }
+    }
+}
+
+impl CaptureTimer {
+    pub const fn new() -> Self {
+        Self {
+            active: Mutex::new(None),
+        }
+    }
+
+    /// Count down `seconds`, calling `on_tick` with the seconds remaining
+    pub fn run(&self, seconds: u32, on_tick: impl FnMut(u32)) -> AppResult<()> {
+        self.run_with_step(seconds, Duration::from_secs(1), on_tick)
+    }
+
+    fn run_with_step(
+        &self,
+        seconds: u32,
+        step: Duration,
+        mut on_tick: impl FnMut(u32),
+    ) -> AppResult<()> {
+        let cancelled = Arc::new(AtomicBool::new(false));
+        {
+            let mut active = self
+                .active
+                .lock()
+                .map_err(|e| format!("Failed to acquire lock: {}", e))?;
+            if active.is_some() {
+                return Err("A capture countdown is already running".to_string());
+            }
+            *active = Some(cancelled.clone());
+        }
+        let _guard = ActiveGuard(self);
+
+        for remaining in (1..=seconds).rev() {
+            on_tick(remaining);
+
+            let deadline = Instant::now() + step;
+            loop {
+                if cancelled.load(Ordering::SeqCst) {
+                    return Err(COUNTDOWN_CANCELLED.to_string());
+                }
+                let now = Instant::now();
+                if now >= deadline {
+                    break;
+                }
+                std::thread::sleep(POLL_INTERVAL.min(deadline - now));
+            }
+        }
+
+        if cancelled.load(Ordering::SeqCst) {
+            return Err(COUNTDOWN_CANCELLED.to_string());
+        }
+        on_tick(0);
+        Ok(())
+    }
+
+    /// Cancel the running countdown, returning whether there was one
+    pub fn cancel(&self) -> bool {
+        match self.active.lock() {
+            Ok(active) => match active.as_ref() {
+                Some(cancelled) => {
+                    cancelled.store(true, Ordering::SeqCst);
+                    true
+                }
+                None => false,
+            },
+            Err(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STEP: Duration = Duration::from_millis(10);
+
+    #[test]
+    fn test_countdown_ticks_down_to_zero() {
+        let timer = CaptureTimer::new();
+        let mut ticks = Vec::new();
+
+        timer.run_with_step(3, STEP, |r| ticks.push(r)).unwrap();
+
+        assert_eq!(ticks, vec![3, 2, 1, 0]);
+    }
+
+    #[test]
+    fn test_zero_second_countdown_only_fires_final_tick() {
+        let timer = CaptureTimer::new();
+        let mut ticks = Vec::new();
+
+        timer.run_with_step(0, STEP, |r| ticks.push(r)).unwrap();
+
+        assert_eq!(ticks, vec![0]);
+    }
+
+    #[test]
+    fn test_cancel_without_countdown() {
+        let timer = CaptureTimer::new();
+        assert!(!timer.cancel());
+    }
+
+    #[test]
+    fn test_cancel_stops_countdown() {
+        let timer = CaptureTimer::new();
+        let mut ticks = Vec::new();
+
+        let result = timer.run_with_step(5, STEP, |r| {
+            ticks.push(r);
+            if r == 4 {
+                assert!(timer.cancel());
+            }
+        });
+
+        assert_eq!(result.unwrap_err(), COUNTDOWN_CANCELLED);
+        assert_eq!(ticks, vec![5, 4]);
+    }
+
+    #[test]
+    fn test_cancel_from_another_thread() {
+        let timer = Arc::new(CaptureTimer::new());
+        let runner = {
+            let timer = timer.clone();
+            std::thread::spawn(move || timer.run_with_step(1, Duration::from_secs(10), |_| {}))
+        };
+
+        while !timer.cancel() {
+            std::thread::sleep(Duration::from_millis(5));
+        }
+
+        assert!(runner.join().unwrap().is_err());
+    }
+
+    #[test]
+    fn test_only_one_countdown_at_a_time() {
+        let timer = CaptureTimer::new();
+
+        let result = timer.run_with_step(1, STEP, |r| {
+            if r == 1 {
+                assert!(timer.run_with_step(1, STEP, |_| {}).is_err());
+            }
+        });
+
+        assert!(result.is_ok());
+        // The finished countdown no longer blocks a new one
+        assert!(timer.run_with_step(0, STEP, |_| {}).is_ok());
+    }
+}
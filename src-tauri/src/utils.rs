@@ -38,6 +38,32 @@ pub fn generate_filename_with_id(prefix: &str, id: u32, extension: &str) -> AppR
     Ok(format!("{}_{}_{}.{}", prefix, id, timestamp, extension))
 }
 
+/// Convert a `file://` URI to a local path, decoding percent escapes
+pub fn file_uri_to_path(uri: &str) -> AppResult<PathBuf> {
+    let encoded = uri
+        .strip_prefix("file://")
+        .ok_or_else(|| format!("Unsupported file uri: {}", uri))?;
+
+    let bytes = encoded.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' && i + 2 < bytes.len() {
+            let hex = std::str::from_utf8(&bytes[i + 1..i + 3]).ok();
+            if let Some(value) = hex.and_then(|h| u8::from_str_radix(h, 16).ok()) {
+                decoded.push(value);
+                i += 3;
+                continue;
+            }
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    let path = String::from_utf8(decoded).map_err(|_| format!("Invalid file uri: {}", uri))?;
+    Ok(PathBuf::from(path))
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -93,4 +119,25 @@ mod tests {
         // Cleanup
         let _ = std::fs::remove_dir_all(temp_dir.join("bettershot_test"));
     }
+
+    #[test]
+    fn test_file_uri_to_path() {
+        assert_eq!(
+            file_uri_to_path("file:///home/me/Pictures/Screenshot%20from%202024.png").unwrap(),
+            PathBuf::from("/home/me/Pictures/Screenshot from 2024.png")
+        );
+        assert_eq!(
+            file_uri_to_path("file:///tmp/100%25.png").unwrap(),
+            PathBuf::from("/tmp/100%.png")
+        );
+    }
+
+    #[test]
+    fn test_file_uri_to_path_keeps_stray_percent() {
+        assert_eq!(
+            file_uri_to_path("file:///tmp/a%zz%4").unwrap(),
+            PathBuf::from("/tmp/a%zz%4")
+        );
+        assert!(file_uri_to_path("https://example.com/a.png").is_err());
+    }
 }